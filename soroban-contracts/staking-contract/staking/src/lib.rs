@@ -51,31 +51,413 @@ mod aquarius_pool {
 
         /// Gets total LP shares in circulation
         fn get_total_shares(env: Env) -> u128;
+
+        /// Swaps tokens within the pool
+        /// Returns: u128 amount of token[out_idx] received
+        /// Parameters:
+        /// - user: Address paying `in_amount` of token[in_idx]
+        /// - in_idx / out_idx: token indices in pool order
+        /// - out_min: u128 minimum amount of token[out_idx] to receive
+        fn swap(
+            env: Env,
+            user: Address,
+            in_idx: u32,
+            out_idx: u32,
+            in_amount: u128,
+            out_min: u128,
+        ) -> u128;
+
+        /// Gets the swap fee in basis points (of 10000)
+        fn get_fee_fraction(env: Env) -> u32;
     }
 }
 
 use aquarius_pool::AquariusPoolClient;
 
-/// Old Config struct for migration (matches deployed v1.0.0 contract)
-#[contracttype]
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub struct OldConfig {
-    pub admin: Address,
-    pub aqua_token: Address,
-    pub blub_token: Address,
-    pub ice_contract: Address,
-    pub liquidity_contract: Address,
-    pub period_unit_minutes: u64,
-    pub reward_rate: i128,
-    pub total_supply: i128,
-    pub treasury_address: Address,
-    pub version: u32,
+// ============================================================================
+// Price Oracle Interface (SEP-40, External Contract)
+// ============================================================================
+
+// Only the SEP-40 types are declared here; `lastprice(asset) -> Option<PriceData>`
+// is called through `try_invoke_contract` so a failing oracle surfaces as an error.
+mod price_oracle {
+    use soroban_sdk::{Address, Symbol, contracttype};
+
+    /// Asset identifier as defined by SEP-40
+    #[contracttype]
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub enum Asset {
+        Stellar(Address),
+        Other(Symbol),
+    }
+
+    /// Price record as defined by SEP-40
+    #[contracttype]
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct PriceData {
+        pub price: i128,
+        pub timestamp: u64,
+    }
+}
+
+// ============================================================================
+// Vault Strategies
+// Every call a vault pool makes to its AMM goes through the adapter selected
+// by `PoolInfo.strategy`. Supporting another Soroban AMM means adding a
+// `StrategyKind` variant and one `VaultStrategy` implementation.
+// ============================================================================
+
+/// AMM adapter behind a vault pool.
+///
+/// Amounts are indexed in the pool's own token order (`PoolInfo.tokens`).
+/// Tokens and LP are always held by this contract, so each adapter authorizes
+/// the sub-calls its AMM makes on the contract's behalf.
+trait VaultStrategy {
+    /// Checks the pool against `tokens` (any order), `share_token` and
+    /// `strategy.params`. Returns the tokens in the pool's own order.
+    fn verify(&self, env: &Env, pool_info: &PoolInfo) -> Result<Vec<Address>, Error>;
+
+    /// Current reserves per token; empty if unknown
+    fn reserves(&self, env: &Env, pool_info: &PoolInfo) -> Vec<u128>;
+
+    /// Amounts to offer out of the desired amounts so the pool's curve accepts them
+    fn deposit_amounts(&self, env: &Env, pool_info: &PoolInfo, desired: &Vec<i128>) -> Vec<i128>;
+
+    /// Deposits tokens held by this contract (any amount may be 0).
+    /// Returns (used_amounts, lp_minted).
+    fn deposit(&self, env: &Env, pool_info: &PoolInfo, amounts: &Vec<i128>, min_shares: u128) -> (Vec<u128>, u128);
+
+    /// Burns `lp_amount` LP held by this contract. Returns the amounts withdrawn.
+    fn withdraw(&self, env: &Env, pool_info: &PoolInfo, lp_amount: i128, min_amounts: &Vec<u128>) -> Vec<u128>;
+
+    /// Swaps `amount_in` of the token at `in_idx` for the token at `out_idx`
+    fn swap(&self, env: &Env, pool_info: &PoolInfo, in_idx: u32, out_idx: u32, amount_in: i128, out_min: u128) -> u128;
+
+    /// How much of `amount_in` (token at `in_idx` of a two-token pool) to swap
+    /// so the remainder and the swap output deposit without leftovers
+    fn zap_swap_amount(&self, env: &Env, pool_info: &PoolInfo, in_idx: u32, amount_in: i128) -> Result<i128, Error>;
+
+    /// Claims this contract's pending rewards; returns the reward-token amount
+    fn claim(&self, env: &Env, pool_info: &PoolInfo) -> u128;
+}
+
+/// Adapter for Aquarius constant-product and stableswap pools.
+struct AquariusStrategy {
+    stable: bool,
+}
+
+impl AquariusStrategy {
+    /// Stableswap deposit amounts: the largest reserve-proportional subset of
+    /// `desired` (a balanced deposit pays no imbalance fee), unless the pool's
+    /// `estimate_deposit` shows the full amounts mint at least as many shares
+    /// per token deposited. An empty pool takes any mix.
+    fn stable_deposit_amounts(env: &Env, pool_info: &PoolInfo, reserves: &Vec<u128>, desired: &Vec<i128>) -> Vec<i128> {
+        let n = desired.len();
+        if reserves.len() != n || reserves.iter().any(|r| r == 0) || desired.iter().any(|d| d < 0) {
+            return desired.clone();
+        }
+
+        // Limiting token: smallest desired / reserve
+        let mut lim: u32 = 0;
+        for i in 1..n {
+            let lhs = (desired.get(i).unwrap() as u128).checked_mul(reserves.get(lim).unwrap());
+            let rhs = (desired.get(lim).unwrap() as u128).checked_mul(reserves.get(i).unwrap());
+            if let (Some(lhs), Some(rhs)) = (lhs, rhs) {
+                if lhs < rhs {
+                    lim = i;
+                }
+            }
+        }
+        let desired_lim = desired.get(lim).unwrap() as u128;
+        let reserve_lim = reserves.get(lim).unwrap();
+        let mut balanced = Vec::new(env);
+        for i in 0..n {
+            let amount = desired_lim
+                .checked_mul(reserves.get(i).unwrap())
+                .and_then(|v| v.checked_div(reserve_lim))
+                .unwrap_or(0)
+                .min(desired.get(i).unwrap() as u128);
+            balanced.push_back(amount as i128);
+        }
+        if balanced == *desired {
+            return balanced;
+        }
+
+        let full_shares = Self::estimate_deposit(env, pool_info, desired);
+        let balanced_shares = Self::estimate_deposit(env, pool_info, &balanced);
+        let full_total: u128 = desired.iter().map(|d| d as u128).fold(0, |acc, d| acc.saturating_add(d));
+        let balanced_total: u128 = balanced.iter().map(|d| d as u128).fold(0, |acc, d| acc.saturating_add(d));
+        match (full_shares, balanced_shares) {
+            // A zero leg leaves nothing balanced to offer: the imbalanced mix is the only option
+            (_, Some(0)) => desired.clone(),
+            (Some(full), Some(bal))
+                if full.saturating_mul(balanced_total) >= bal.saturating_mul(full_total) =>
+            {
+                desired.clone()
+            }
+            _ if balanced_total == 0 => desired.clone(),
+            _ => balanced,
+        }
+    }
+
+    /// LP the pool would mint for `amounts`, if it answers `estimate_deposit`
+    fn estimate_deposit(env: &Env, pool_info: &PoolInfo, amounts: &Vec<i128>) -> Option<u128> {
+        let mut desired = Vec::new(env);
+        for amount in amounts.iter() {
+            desired.push_back(amount.max(0) as u128);
+        }
+        match env.try_invoke_contract::<u128, soroban_sdk::Error>(
+            &pool_info.pool_address,
+            &Symbol::new(env, "estimate_deposit"),
+            (desired,).into_val(env),
+        ) {
+            Ok(Ok(shares)) => Some(shares),
+            _ => None,
+        }
+    }
+
+    /// Strategy kind matching the pool's own `pool_type`
+    fn detect_kind(env: &Env, pool_address: &Address) -> Result<StrategyKind, Error> {
+        match env.try_invoke_contract::<Symbol, soroban_sdk::Error>(
+            pool_address,
+            &Symbol::new(env, "pool_type"),
+            ().into_val(env),
+        ) {
+            Ok(Ok(kind)) if kind == Symbol::new(env, "constant_product") => Ok(StrategyKind::AquariusConstantProduct),
+            Ok(Ok(kind)) if kind == Symbol::new(env, "stable") => Ok(StrategyKind::AquariusStable),
+            _ => Err(Error::PoolMetadataMismatch),
+        }
+    }
+
+    /// Auth entry for `token.transfer(this contract, pool, amount)`
+    fn transfer_auth(env: &Env, pool_info: &PoolInfo, token: &Address, amount: i128) -> InvokerContractAuthEntry {
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token.clone(),
+                fn_name: Symbol::new(env, "transfer"),
+                args: (
+                    env.current_contract_address(),
+                    pool_info.pool_address.clone(),
+                    amount,
+                ).into_val(env),
+            },
+            sub_invocations: soroban_sdk::vec![env],
+        })
+    }
+}
+
+impl VaultStrategy for AquariusStrategy {
+    fn verify(&self, env: &Env, pool_info: &PoolInfo) -> Result<Vec<Address>, Error> {
+        if !pool_info.strategy.params.is_empty() {
+            return Err(Error::InvalidInput);
+        }
+
+        let tokens = match env.try_invoke_contract::<Vec<Address>, soroban_sdk::Error>(
+            &pool_info.pool_address,
+            &Symbol::new(env, "get_tokens"),
+            ().into_val(env),
+        ) {
+            Ok(Ok(tokens)) if tokens.len() >= 2 => tokens,
+            _ => return Err(Error::PoolMetadataMismatch),
+        };
+
+        // Same set of tokens: equal length, every pool token expected, no duplicates
+        if tokens.len() != pool_info.tokens.len() {
+            return Err(Error::PoolMetadataMismatch);
+        }
+        for (i, token) in pool_info.tokens.iter().enumerate() {
+            if !tokens.contains(&token) || pool_info.tokens.first_index_of(&token) != Some(i as u32) {
+                return Err(Error::PoolMetadataMismatch);
+            }
+        }
+
+        match env.try_invoke_contract::<Address, soroban_sdk::Error>(
+            &pool_info.pool_address,
+            &Symbol::new(env, "share_id"),
+            ().into_val(env),
+        ) {
+            Ok(Ok(share_id)) if share_id == pool_info.share_token => {}
+            _ => return Err(Error::PoolMetadataMismatch),
+        }
+
+        let expected = if self.stable {
+            StrategyKind::AquariusStable
+        } else {
+            StrategyKind::AquariusConstantProduct
+        };
+        if Self::detect_kind(env, &pool_info.pool_address)? != expected {
+            return Err(Error::PoolMetadataMismatch);
+        }
+
+        Ok(tokens)
+    }
+
+    fn reserves(&self, env: &Env, pool_info: &PoolInfo) -> Vec<u128> {
+        AquariusPoolClient::new(env, &pool_info.pool_address).get_reserves()
+    }
+
+    /// Constant-product pools only accept the reserve ratio, so the larger side
+    /// is trimmed to match. Stableswap pools accept any mix but charge for
+    /// imbalance; see `stable_deposit_amounts`.
+    fn deposit_amounts(&self, env: &Env, pool_info: &PoolInfo, desired: &Vec<i128>) -> Vec<i128> {
+        if self.stable {
+            return Self::stable_deposit_amounts(env, pool_info, &self.reserves(env, pool_info), desired);
+        }
+        if desired.len() != 2 {
+            return desired.clone();
+        }
+        let desired_a = desired.get(0).unwrap();
+        let desired_b = desired.get(1).unwrap();
+
+        let reserves = self.reserves(env, pool_info);
+        let (r_a, r_b): (u128, u128) = if reserves.len() >= 2 {
+            (reserves.get(0).unwrap(), reserves.get(1).unwrap())
+        } else {
+            (0, 0)
+        };
+
+        let (amount_a, amount_b) = if r_a > 0 && r_b > 0 {
+            // Pool has liquidity – calculate proportional amounts.
+            let optimal_b = (desired_a as u128)
+                .checked_mul(r_b)
+                .unwrap_or(0)
+                .checked_div(r_a)
+                .unwrap_or(0);
+
+            if optimal_b <= desired_b as u128 {
+                (desired_a, optimal_b as i128)
+            } else {
+                let optimal_a = (desired_b as u128)
+                    .checked_mul(r_a)
+                    .unwrap_or(0)
+                    .checked_div(r_b)
+                    .unwrap_or(0);
+                (optimal_a as i128, desired_b)
+            }
+        } else {
+            (desired_a, desired_b)
+        };
+
+        soroban_sdk::vec![env, amount_a, amount_b]
+    }
+
+    fn deposit(&self, env: &Env, pool_info: &PoolInfo, amounts: &Vec<i128>, min_shares: u128) -> (Vec<u128>, u128) {
+        // Auth entries follow the pool's token order; zero legs are not transferred
+        let mut auth_entries = Vec::new(env);
+        let mut desired_amounts = Vec::new(env);
+        for (token, amount) in pool_info.tokens.iter().zip(amounts.iter()) {
+            if amount > 0 {
+                auth_entries.push_back(Self::transfer_auth(env, pool_info, &token, amount));
+            }
+            desired_amounts.push_back(amount.max(0) as u128);
+        }
+        env.authorize_as_current_contract(auth_entries);
+
+        AquariusPoolClient::new(env, &pool_info.pool_address)
+            .deposit(&env.current_contract_address(), &desired_amounts, &min_shares)
+    }
+
+    fn withdraw(&self, env: &Env, pool_info: &PoolInfo, lp_amount: i128, min_amounts: &Vec<u128>) -> Vec<u128> {
+        let contract_address = env.current_contract_address();
+
+        // Note: Aquarius pool calls burn(from, amount), not transfer
+        let auth_entries = soroban_sdk::vec![
+            env,
+            InvokerContractAuthEntry::Contract(SubContractInvocation {
+                context: ContractContext {
+                    contract: pool_info.share_token.clone(),
+                    fn_name: Symbol::new(env, "burn"),
+                    args: (
+                        contract_address.clone(),
+                        lp_amount,
+                    ).into_val(env),
+                },
+                sub_invocations: soroban_sdk::vec![env],
+            }),
+        ];
+        env.authorize_as_current_contract(auth_entries);
+
+        AquariusPoolClient::new(env, &pool_info.pool_address).withdraw(
+            &contract_address,
+            &(lp_amount as u128),
+            min_amounts,
+        )
+    }
+
+    fn swap(&self, env: &Env, pool_info: &PoolInfo, in_idx: u32, out_idx: u32, amount_in: i128, out_min: u128) -> u128 {
+        let token_in = pool_info.tokens.get(in_idx).unwrap();
+        env.authorize_as_current_contract(soroban_sdk::vec![
+            env,
+            Self::transfer_auth(env, pool_info, &token_in, amount_in),
+        ]);
+
+        AquariusPoolClient::new(env, &pool_info.pool_address).swap(
+            &env.current_contract_address(),
+            &in_idx,
+            &out_idx,
+            &(amount_in as u128),
+            &out_min,
+        )
+    }
+
+    /// Constant product uses the closed form for swapping into your own pool,
+    /// `s = (sqrt(r² (2-f)² + 4 (1-f) r A) - r (2-f)) / (2 (1-f))`, computed in
+    /// basis points and scaled down so the square stays within u128. Stableswap
+    /// trades near 1:1, so the split follows the reserve ratio directly.
+    fn zap_swap_amount(&self, env: &Env, pool_info: &PoolInfo, in_idx: u32, amount_in: i128) -> Result<i128, Error> {
+        let reserves = self.reserves(env, pool_info);
+        if reserves.len() != 2 {
+            return Err(Error::InvalidInput);
+        }
+        let r_in = reserves.get(in_idx).unwrap();
+        let r_out = reserves.get(1 - in_idx).unwrap();
+        if r_in == 0 || r_out == 0 {
+            // Nothing to price the swap against
+            return Err(Error::InvalidInput);
+        }
+        let amount = amount_in as u128;
+
+        let swap_amount = if self.stable {
+            amount
+                .checked_mul(r_out)
+                .and_then(|v| v.checked_div(r_in.saturating_add(r_out)))
+                .ok_or(Error::InvalidInput)?
+        } else {
+            let fee = (AquariusPoolClient::new(env, &pool_info.pool_address).get_fee_fraction() as u128).min(9999);
+            let two_minus_fee = 20000 - fee;
+            let one_minus_fee = 10000 - fee;
+
+            let scale = (r_in.max(amount) / 10_000_000_000_000).max(1);
+            let r = r_in / scale;
+            let a = amount / scale;
+
+            let r_term = r * two_minus_fee;
+            let disc = r_term * r_term + 4 * one_minus_fee * r * a * 10000;
+            let scaled = (StakingRegistry::integer_sqrt(disc as i128) as u128 - r_term) / (2 * one_minus_fee);
+            scaled.saturating_mul(scale)
+        };
+
+        Ok(swap_amount.min(amount) as i128)
+    }
+
+    fn claim(&self, env: &Env, pool_info: &PoolInfo) -> u128 {
+        AquariusPoolClient::new(env, &pool_info.pool_address).claim(&env.current_contract_address())
+    }
 }
 
-/// Config struct for v1.1.0 (before cooldown fields were added)
+/// Adapter for a strategy kind
+fn strategy_adapter(kind: StrategyKind) -> &'static dyn VaultStrategy {
+    match kind {
+        StrategyKind::AquariusConstantProduct => &AquariusStrategy { stable: false },
+        StrategyKind::AquariusStable => &AquariusStrategy { stable: true },
+    }
+}
+
+/// Monolithic Config struct as stored under `DataKey::Config` up to v1.8.x.
+/// Only read by `migrate_v1_9_0`, which splits it into the keyed settings below.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct ConfigV1_1 {
+pub struct LegacyConfig {
     pub admin: Address,
     pub version: u32,
     pub total_supply: i128,
@@ -91,16 +473,18 @@ pub struct ConfigV1_1 {
     pub period_unit_minutes: u64,
     pub vault_treasury: Address,
     pub vault_fee_bps: u32,
+    pub unstake_cooldown_seconds: u64,
+    pub claim_reward_cooldown_seconds: u64,
 }
 
-/// New Config struct (v1.2.0)
+/// Assembled configuration view returned by `get_config`.
+/// Never stored as a whole — each group lives under its own key (v1.9.0+).
 /// Version encoding: major * 10000 + minor * 100 + patch
-/// 1.2.0 = 10200
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct Config {
     pub admin: Address,
-    pub version: u32, // 10200 = v1.2.0
+    pub version: u32, // 10900 = v1.9.0
     pub total_supply: i128,
     pub treasury_address: Address,
     pub reward_rate: i128, // basis points per period
@@ -116,11 +500,55 @@ pub struct Config {
     // Vault settings
     pub vault_treasury: Address, // Treasury for vault fees (15% on-chain; POL gets extra cut in backend)
     pub vault_fee_bps: u32, // Vault fee in basis points (1500 = 15% on-chain; POL adds 17.65% extra in backend → effective 30%)
-    // Cooldown settings (v1.2.0)
+    // Cooldown settings
     pub unstake_cooldown_seconds: u64,      // Default: 864000 (10 days)
     pub claim_reward_cooldown_seconds: u64, // Default: 604800 (7 days)
 }
 
+// ============================================================================
+// Keyed Configuration (v1.9.0)
+// Each group is stored under its own DataKey so adding a field to one group
+// (or adding a new group) never makes the others undecodable.
+// ============================================================================
+
+/// Core token and pool addresses.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenConfig {
+    pub aqua_token: Address,
+    pub blub_token: Address,
+    pub liquidity_contract: Address, // AQUA/BLUB pool used for POL
+}
+
+/// Fee recipients and rates.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FeeConfig {
+    pub treasury_address: Address,
+    pub vault_treasury: Address,
+    pub vault_fee_bps: u32,
+}
+
+/// Staking cooldowns.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CooldownConfig {
+    pub unstake_cooldown_seconds: u64,
+    pub claim_reward_cooldown_seconds: u64,
+}
+
+/// Staking period and legacy reward-rate settings.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PeriodConfig {
+    pub period_unit_minutes: u64,
+    pub reward_rate: i128, // basis points per period
+}
+
+/// Default cooldowns: 10 days unstake, 7 days claim
+pub const DEFAULT_UNSTAKE_COOLDOWN_SECONDS: u64 = 864000;
+pub const DEFAULT_CLAIM_COOLDOWN_SECONDS: u64 = 604800;
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct IceTokens {
@@ -197,6 +625,19 @@ pub struct LpPosition {
 /// Used to maintain precision in reward_per_token calculations
 pub const REWARD_PRECISION: i128 = 1_000_000_000_000;
 
+/// Longest period a vault incentive funding may be streamed over.
+pub const VAULT_REWARD_MAX_DURATION: u64 = 31_536_000;
+
+/// Emission schedule of a vault pool's BLUB incentives. Funding streams out
+/// at `reward_rate` until `period_finish`; unstreamed BLUB of a running
+/// period rolls into the next funding.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct VaultRewardStream {
+    pub reward_rate: i128,            // BLUB per second, scaled by REWARD_PRECISION
+    pub period_finish: u64,
+}
+
 /// Global reward state - tracks accumulated rewards for the entire pool
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -323,7 +764,7 @@ pub struct GlobalState {
     pub downvote_ice_balance: i128,       // downvoteICE token balance
     pub ice_lock_counter: u64,            // Counter for ICE lock authorizations
     // Vault (Request 2)
-    pub pool_count: u32,                  // Number of vault pools (max 10)
+    pub pool_count: u32,                  // Number of vault pools (unbounded since v1.9.0)
 }
 
 #[contracttype]
@@ -341,9 +782,145 @@ pub struct ProtocolOwnedLiquidity {
 // ICE Locking Structures (Request 1)
 // ============================================================================
 
+/// Seconds after which an authorization that was never transferred can be
+/// cancelled by anyone (7 days).
+pub const ICE_AUTH_STALE_SECONDS: u64 = 604800;
+
+/// Length of one ICE lock year (365 days).
+pub const ICE_LOCK_YEAR_SECONDS: u64 = 31_536_000;
+
+/// Most maturity days read per `get_ice_maturities` / `get_upcoming_ice_unlocks` call.
+pub const ICE_MATURITY_PAGE_DAYS: u64 = 30;
+
+/// Lifecycle of an ICE lock (v1.9.0+).
+/// Authorized -> Transferred -> Confirmed -> Unlocked, or Authorized -> Expired.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IceLockState {
+    Authorized,  // AQUA reserved out of pending_aqua_for_ice
+    Transferred, // AQUA sent to the manager for locking on Stellar Classic
+    Confirmed,   // ICE received, attributed by confirm_ice_lock
+    Expired,     // Cancelled before transfer; AQUA returned to pending_aqua_for_ice
+    Unlocked,    // Lock term over; the AQUA was released on Stellar Classic
+}
+
+/// Amounts of each ICE token type.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IceAmounts {
+    pub ice: i128,
+    pub govern_ice: i128,
+    pub upvote_ice: i128,
+    pub downvote_ice: i128,
+}
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct IceLockAuthorization {
+    pub lock_id: u64,
+    pub aqua_amount: i128,
+    pub duration_years: u64,
+    pub authorized_at: u64,
+    pub executed: bool,               // true once the AQUA was transferred
+    pub state: IceLockState,
+    pub reserved_aqua: i128,          // AQUA held out of pending_aqua_for_ice while Authorized
+    pub transferred_at: u64,
+    pub unlocks_at: u64,              // transferred_at + duration_years; 0 until transferred
+    pub confirmed_at: u64,
+    pub ice_received: IceAmounts,     // Attributed by confirm_ice_lock out of synced ICE
+    pub closed_at: u64,               // When the lock became Expired or Unlocked
+}
+
+/// An open ICE lock in the maturity ledger, bucketed by unlock day
+/// (`unlocks_at / 86400`).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IceMaturity {
+    pub lock_id: u64,
+    pub aqua_amount: i128,            // AQUA released on Stellar Classic at maturity
+    pub unlocks_at: u64,
+}
+
+/// How much upvoteICE an Aquarius pool gets: a fixed amount, or bps of the
+/// contract's `upvote_ice_balance` at the time of reading.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IceVoteWeight {
+    Amount(i128),
+    Bps(u32),
+}
+
+/// One entry of the ICE vote allocation registry.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IceVoteTarget {
+    pub pool: Address,                // Aquarius pool (POL or vault pool) to upvote
+    pub weight: IceVoteWeight,
+}
+
+/// An `IceVoteTarget` resolved against the current upvoteICE balance.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IceVoteAllocation {
+    pub pool: Address,
+    pub weight: IceVoteWeight,
+    pub ice_amount: i128,
+}
+
+/// The ICE vote allocation registry resolved against the current balance.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IceVoteReport {
+    pub allocations: Vec<IceVoteAllocation>,
+    pub total_allocated: i128,
+    pub upvote_ice_balance: i128,     // GlobalState.upvote_ice_balance at the last sync
+    pub within_balance: bool,         // total_allocated <= upvote_ice_balance
+}
+
+/// Length of a BLUB-staker ICE voting epoch (7 days).
+pub const ICE_VOTE_EPOCH_SECONDS: u64 = 604800;
+
+/// Most pools stakers can vote on at once.
+pub const ICE_VOTE_MAX_POOLS: u32 = 20;
+
+/// A staker's vote for one whitelisted pool, in bps of their weight.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolVote {
+    pub pool: Address,
+    pub bps: u32,
+}
+
+/// A staker's ballot for an epoch.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserIceVote {
+    pub weight: i128,                 // Lowest staked BLUB (UserRewardState.staked_balance) since cast
+    pub votes: Vec<PoolVote>,
+    pub cast_at: u64,
+}
+
+/// Staked-BLUB weight voted for a pool in an epoch.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolVoteTally {
+    pub pool: Address,
+    pub weight: i128,
+}
+
+/// How `renew_ice_lock` re-authorizes matured AQUA.
+/// `ladder_bps[i]` is the share locked for `i + 1` years; the five entries sum
+/// to 10000. A single 10000 entry renews everything for one preferred duration.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IceRenewalPolicy {
+    pub ladder_bps: Vec<u32>,
+}
+
+/// ICE lock authorization layout before v1.9.0, decoded on read.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyIceLockAuthorization {
     pub lock_id: u64,
     pub aqua_amount: i128,
     pub duration_years: u64,
@@ -355,22 +932,73 @@ pub struct IceLockAuthorization {
 // Vault Structures (Request 2)
 // ============================================================================
 
+/// Vault shares minted to no one on a pool's first deposit. They keep
+/// `VaultTotalShares` from ever returning to zero, so the share price cannot be
+/// inflated from an empty pool to round later deposits down to nothing.
+pub const VAULT_DEAD_SHARES: i128 = 1_000;
+
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PoolInfo {
     pub pool_id: u32,
     pub pool_address: Address, // Aquarius pool contract
-    pub token_a: Address,
-    pub token_b: Address,
+    pub token_a: Address,      // tokens[0]
+    pub token_b: Address,      // tokens[1]
+    pub tokens: Vec<Address>,  // All pool tokens in pool order (2 for constant product, up to 4 for stableswap)
     pub share_token: Address, // LP token address
     pub total_lp_tokens: i128, // Contract's total LP in this pool
-    pub active: bool,
+    pub active: bool,          // Deposits and compounding allowed (withdrawals always are)
     pub added_at: u64,
+    pub deprecated: bool,      // Retired for good (v1.9.0+): withdraw-only, cannot be re-activated
+    pub strategy: PoolStrategy, // AMM adapter the pool's LP is managed through
+    pub verified: bool,        // token_a/token_b are in pool order and share_token matches share_id
 }
 
+/// AMM adapter a vault pool runs on. Each kind maps to one `VaultStrategy` implementation.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StrategyKind {
+    AquariusConstantProduct,
+    AquariusStable,
+}
+
+/// A pool's strategy: the adapter kind plus settings only that adapter reads.
+/// Aquarius adapters take no settings and require `params` to be empty.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
-pub struct UserVaultPosition {
+pub struct PoolStrategy {
+    pub kind: StrategyKind,
+    pub params: Bytes,
+}
+
+/// PoolInfo layout as stored before v1.9.0. Decoded by `load_pool` and
+/// upgraded to the current layout on the next write.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyPoolInfo {
+    pub pool_id: u32,
+    pub pool_address: Address,
+    pub token_a: Address,
+    pub token_b: Address,
+    pub share_token: Address,
+    pub total_lp_tokens: i128,
+    pub active: bool,
+    pub added_at: u64,
+}
+
+/// Pool selection for `get_pools`.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PoolFilter {
+    All,
+    Active,     // Accepting deposits
+    Paused,     // Inactive but not deprecated
+    Deprecated, // Retired, withdraw-only
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UserVaultPosition {
     pub user: Address,
     pub pool_id: u32,
     pub share_ratio: i128, // Vault shares (v1.8.0+). User LP = share_ratio * total_lp / total_shares
@@ -388,6 +1016,230 @@ pub struct PoolCompoundStats {
     pub total_treasury_fees: i128,      // Total AQUA sent to treasury
     pub last_compound_time: u64,        // Timestamp of last compound
     pub compound_count: u32,            // Number of successful compounds
+    pub total_pol_fees: i128,           // Total AQUA performance fee sent to the POL recipient (v1.9.0+)
+    pub total_deposit_fee_lp: i128,     // Total LP taken as deposit fees (v1.9.0+)
+    pub total_withdraw_fee_lp: i128,    // Total LP taken as withdraw fees (v1.9.0+)
+    pub total_harvest_bounties: i128,   // Total AQUA paid to `harvest` callers (v1.9.0+)
+}
+
+/// PoolCompoundStats layout as stored before v1.9.0. Decoded by
+/// `read_compound_stats` and upgraded on the next write.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct LegacyPoolCompoundStats {
+    pub total_compounded_lp: i128,
+    pub total_rewards_claimed: i128,
+    pub total_treasury_fees: i128,
+    pub last_compound_time: u64,
+    pub compound_count: u32,
+}
+
+/// Per-pool fee settings (v1.9.0+). Pools without one use `FeeConfig`:
+/// `vault_fee_bps` as performance fee to `vault_treasury`, no deposit/withdraw fee.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolFeeConfig {
+    pub performance_fee_bps: u32,  // Cut of claimed AQUA rewards (max 5000)
+    pub deposit_fee_bps: u32,      // Cut of LP minted on deposit (max 1000)
+    pub withdraw_fee_bps: u32,     // Cut of LP released on withdrawal (max 1000)
+    pub treasury: Address,         // Receives the fee remainder
+    pub pol_recipient: Address,    // Receives `pol_share_bps` of every fee
+    pub pol_share_bps: u32,        // Share of each fee sent to pol_recipient (max 10000)
+}
+
+/// Per-pool settings for permissionless `harvest` (v1.9.0+).
+///
+/// Claimed AQUA is sold through `sell_pool` unless AQUA is itself a vault pool
+/// token, then zapped into the pool. Two-token pools only. Every swap must return at least the
+/// oracle value less `max_slippage_bps`; prices are compared 1:1 in raw units,
+/// so all tokens involved must share the same decimals (7 for Stellar assets).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HarvestConfig {
+    pub min_interval: u64,          // Seconds between compounds (1800 = 48 a day)
+    pub bounty_bps: u32,            // Caller bounty, share of claimed AQUA (max 500)
+    pub oracle: Address,            // SEP-40 price oracle
+    pub max_price_age: u64,         // Oldest oracle price accepted, in seconds
+    pub max_slippage_bps: u32,      // Max shortfall against the oracle on each swap (max 1000)
+    pub sell_pool: Option<Address>, // Aquarius pool of AQUA and one vault pool token
+}
+
+/// Scale of `PoolSnapshot.price_per_share` (LP per vault share).
+pub const PRICE_PER_SHARE_PRECISION: i128 = 1_000_000_000_000;
+
+/// A pool's state at its last snapshot of a day (day = timestamp / 86400).
+/// Written on every compound and by `snapshot_pool`; later snapshots on the
+/// same day overwrite earlier ones.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolSnapshot {
+    pub day: u64,
+    pub total_lp: i128,
+    pub total_shares: i128,
+    pub price_per_share: i128,        // total_lp / total_shares, scaled by PRICE_PER_SHARE_PRECISION
+    pub rewards_claimed: i128,        // Cumulative `total_rewards_claimed` at snapshot time
+    pub taken_at: u64,
+}
+
+/// Most days `get_pool_history` reads per call (one storage read per day).
+pub const POOL_HISTORY_MAX_DAYS: u64 = 30;
+
+/// Days `get_pool_trailing_apr` searches at each end of its window for a snapshot.
+pub const POOL_APR_PROBE_DAYS: u64 = 7;
+
+/// Trailing yield derived from two snapshots, returned by `get_pool_trailing_apr`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolApr {
+    pub from_day: u64,
+    pub to_day: u64,
+    pub start_price_per_share: i128,
+    pub end_price_per_share: i128,
+    pub apr_bps: i128,                // Price-per-share growth annualized linearly (no compounding)
+}
+
+/// Per-purpose balances of the BLUB and AQUA held by the contract (v1.9.0).
+/// Every flow that moves BLUB or AQUA in or out credits or debits its bucket;
+/// AQUA for ICE locking is tracked by `GlobalState.pending_aqua_for_ice`, and
+/// by `IceKey::AuthorizedAqua` once an ICE lock reserves it.
+/// Vault deposits, withdrawals and reward claims settle within a single call
+/// (claimed AQUA is paid out or swapped before it returns) and are not bucketed.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenLedger {
+    pub blub_principal: i128,      // Staked BLUB (locks and restakes)
+    pub blub_reward_reserve: i128, // BLUB held for staker rewards
+    pub blub_pol_in_transit: i128, // Minted POL BLUB not yet sent to the manager or the LP
+    pub blub_vault_rewards: i128,  // Funded vault incentives not yet claimed
+    pub blub_vault_idle: i128,     // BLUB in vault pools' idle residuals and emergency-exit idle
+    pub aqua_revenue: i128,        // AQUA from add_rewards_from_aqua and POL pool rewards
+    pub aqua_vault_idle: i128,     // AQUA in vault pools' idle residuals and emergency-exit idle
+}
+
+/// Token ledger buckets next to the contract's actual balances.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenLedgerReport {
+    pub ledger: TokenLedger,
+    pub aqua_pending_ice: i128,    // GlobalState.pending_aqua_for_ice
+    pub aqua_authorized_ice: i128, // AQUA reserved by Authorized ICE locks
+    pub blub_balance: i128,        // Contract's BLUB balance
+    pub aqua_balance: i128,        // Contract's AQUA balance
+    pub blub_unallocated: i128,    // blub_balance minus all BLUB buckets
+    pub aqua_unallocated: i128,    // aqua_balance minus all AQUA buckets
+}
+
+/// Result of reconciling a pool's tracked LP against its share-token balance.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolLpSync {
+    pub pool_id: u32,
+    pub tracked_lp: i128,          // PoolInfo.total_lp_tokens before the sync
+    pub pol_lp: i128,              // POL LP held in the same share token (0 if none)
+    pub actual_lp: i128,           // Contract's share_token balance
+    pub surplus_credited: i128,    // LP added to total_lp_tokens (raises price per share)
+    pub surplus_unallocated: i128, // Surplus left untracked because the pool has no shares
+    pub deficit: i128,             // LP missing from the balance (flagged, not written off)
+}
+
+/// Per-pool deposit caps set by the curator. Zero means no cap.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolLimits {
+    pub max_total_lp: i128,        // Cap on PoolInfo.total_lp_tokens after a deposit
+    pub max_user_lp: i128,         // Cap on a single user's LP after a deposit
+}
+
+/// Idle tokens of a pool whose LP was unwound by `emergency_exit_pool`.
+/// Withdrawals pay these out pro-rata to shares instead of touching Aquarius.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolExitState {
+    pub idle: Vec<i128>,           // Per-token amounts (pool order) still owed to shareholders
+    pub lp_unwound: i128,          // LP withdrawn from Aquarius at exit
+    pub exited_at: u64,
+}
+
+/// Remaining deposit room in a pool, returned by `get_pool_capacity`.
+/// Remaining amounts are `i128::MAX` when the matching cap is unset.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolCapacity {
+    pub pool_id: u32,
+    pub limits: PoolLimits,
+    pub total_lp: i128,            // PoolInfo.total_lp_tokens
+    pub user_lp: i128,             // User's LP at the current share price
+    pub remaining_total_lp: i128,
+    pub remaining_user_lp: i128,
+    pub remaining_lp: i128,        // min of the two: the most LP a deposit can add now
+}
+
+// ============================================================================
+// Invariant Checking (v1.9.0)
+// ============================================================================
+
+/// Most pools checked per `check_pool_invariants` call (each costs several reads).
+pub const INVARIANT_POOL_PAGE_LIMIT: u32 = 5;
+
+/// Most registry entries summed per `check_staker_invariants` /
+/// `check_vault_share_invariants` call (two reads each).
+pub const INVARIANT_USER_PAGE_LIMIT: u32 = 15;
+
+/// Per-pool vault accounting check returned by `check_pool_invariants`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PoolInvariantReport {
+    pub pool_id: u32,
+    pub tracked_lp: i128,        // PoolInfo.total_lp_tokens
+    pub pol_lp: i128,            // POL LP held in the same share token (0 if none)
+    pub actual_lp: i128,         // Contract's share_token balance
+    pub lp_ok: bool,             // actual_lp >= tracked_lp + pol_lp
+    pub total_shares: i128,      // VaultTotalShares
+    pub dead_shares: i128,       // Unowned shares locked on the first deposit
+    pub user_shares: i128,       // Running sum of registered depositors' shares
+    pub shares_ok: bool,         // Registry incomplete, or user_shares == total_shares - dead_shares
+    pub ok: bool,                // lp_ok && shares_ok
+    pub idle_residual: Vec<i128>, // Pool tokens held for the next compound, in pool order
+}
+
+/// Partial sum over one page of a user registry, returned by
+/// `check_staker_invariants` and `check_vault_share_invariants`.
+/// `ok` compares the running sum kept on every balance change; adding up
+/// `sum` over pages until `next == count` audits that running sum.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegistrySumPage {
+    pub start: u32,
+    pub next: u32,               // First index not covered by this page
+    pub count: u32,              // Registry size
+    pub complete: bool,          // False until the registry has been backfilled
+    pub sum: i128,               // Staked balances or vault shares of this page's users
+    pub tracked_sum: i128,       // Running sum over all registered users
+    pub expected: i128,          // total_staked, or VaultTotalShares minus dead shares
+    pub ok: bool,                // complete && tracked_sum == expected
+}
+
+/// Contract-wide accounting report returned by `check_invariants` and
+/// enforced by the `upgrade` guard. User sums are compared through running
+/// aggregates; LP backing is checked live for the first
+/// `INVARIANT_POOL_PAGE_LIMIT` pools and paged beyond that.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InvariantReport {
+    pub blub_balance: i128,      // Contract's BLUB balance
+    pub total_staked: i128,      // RewardState.total_staked
+    pub unclaimed_rewards: i128, // Reward reserve (BLUB held for rewards)
+    pub unclaimed_vault_rewards: i128, // Funded vault incentives not yet claimed
+    pub vault_idle_blub: i128,   // BLUB held as vault pools' idle residual or exit idle
+    pub blub_ok: bool,           // blub_balance >= total_staked + all amounts above
+    pub staked_sum: i128,        // Running sum of registered stakers' balances
+    pub stakers_ok: bool,        // Registry incomplete, or staked_sum == total_staked
+    pub share_drift_pools: u32,  // Pools whose depositor share sum != VaultTotalShares - dead shares
+    pub shares_ok: bool,         // share_drift_pools == 0
+    pub lp_pools_checked: u32,   // Pools whose LP backing was checked (IDs from 0)
+    pub lp_ok: bool,             // Every checked pool's lp_ok
+    pub ok: bool,                // All checks above passed
+    pub checked_at: u64,
 }
 
 // ============================================================================
@@ -397,7 +1249,7 @@ pub struct PoolCompoundStats {
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum DataKey {
-    Config,
+    Config, // Legacy monolithic LegacyConfig (<= v1.8.x), removed by migrate_v1_9_0
     UserLockByTxHash(Address, Bytes),
     UserLocks(Address), // Vector of all lock tx hashes for a user
     UserLpCount(Address),
@@ -441,6 +1293,82 @@ pub enum DataKey {
     VaultTotalShares(u32),
 }
 
+/// Storage keys for the individually keyed settings groups (v1.9.0+).
+/// Kept apart from `DataKey` so it stays under the contract spec's 50-variant limit.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigKey {
+    ConfigVersion,                    // u32, encoded as major * 10000 + minor * 100 + patch
+    TotalSupply,                      // i128
+    TokenConfig,                      // TokenConfig
+    IceTokenConfig,                   // IceTokens
+    FeeConfig,                        // FeeConfig
+    CooldownConfig,                   // CooldownConfig
+    PeriodConfig,                     // PeriodConfig
+    UpgradeInvariantCheck,            // bool, upgrade() refuses while invariants fail
+}
+
+/// Storage keys for accounting ledgers and user registries (v1.9.0+).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LedgerKey {
+    // Enumerable user registries used by the paged invariant checks
+    StakerCount,                      // u32
+    StakerByIndex(u32),               // Address
+    StakerRegistered(Address),        // bool
+    StakerRegistryComplete,           // bool, set once pre-v1.9.0 stakers are backfilled
+    VaultUserCount(u32),              // u32 per pool
+    VaultUserByIndex(u32, u32),       // (pool_id, index) -> Address
+    VaultUserRegistered(u32, Address),
+    VaultUserRegistryComplete(u32),   // bool per pool
+    StakedBalanceSum,                 // i128, staked balances of registered stakers
+    VaultShareSum(u32),               // i128 per pool, shares of registered depositors
+    ShareDrift(u32),                  // bool, set while a complete pool's share sum is off
+    ShareDriftCount,                  // u32, pools with ShareDrift set
+    // LP reconciliation
+    PoolLpDeficit(u32),               // i128, LP missing at the last sync_pool_lp
+    // Per-purpose BLUB/AQUA buckets
+    TokenLedger,                      // TokenLedger
+}
+
+/// Storage keys for vault pool governance (v1.9.0+).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PoolKey {
+    Curator,                          // Address allowed to set pool limits (falls back to manager)
+    PoolLimits(u32),                  // PoolLimits per pool
+    PoolFees(u32),                    // PoolFeeConfig per pool
+    DeadShares(u32),                  // i128, VAULT_DEAD_SHARES once the first deposit is made
+    Guardian,                         // Address allowed to trigger emergency_exit_pool besides admin
+    EmergencyExit(u32),               // PoolExitState once a pool's LP has been unwound
+    HarvestConfig(u32),               // HarvestConfig per pool; `harvest` is disabled without one
+    PoolSnapshot(u32, u64),           // PoolSnapshot per (pool_id, day)
+    RewardGauge,                      // Address allowed to fund vault rewards besides the manager
+    VaultRewardState(u32),            // RewardState per pool, keyed on vault shares
+    VaultUserReward(u32, Address),    // UserRewardState per (pool_id, user)
+    IdleResidual(u32),                // Vec<i128> of pool tokens left over by compounds, folded into the next one
+    VaultRewardStream(u32),           // VaultRewardStream per pool
+    VaultRewardUnstreamed(u32),       // i128, BLUB (scaled by REWARD_PRECISION) streamed while no shares earned, rolled into the next funding
+}
+
+/// Storage keys for ICE lock tracking (v1.9.0+).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IceKey {
+    AuthorizedAqua,                   // i128, AQUA reserved by Authorized locks
+    UnattributedIce,                  // IceAmounts synced in but not yet attributed to a lock
+    MaturityDay(u64),                 // Vec<IceMaturity> of open locks unlocking that day, soonest first
+    MaturityCount,                    // u32, open locks in the maturity ledger
+    EarliestMaturityDay,              // u64, no open lock unlocks before this day
+    RenewalPolicy,                    // IceRenewalPolicy; renew_ice_lock is disabled without one
+    VoteOperator,                     // Address managing vote allocations (falls back to manager)
+    VoteAllocations,                  // Vec<IceVoteTarget>
+    VotePools,                        // Vec<Address>, pools BLUB stakers can vote on
+    VoteTally(u64),                   // Vec<PoolVoteTally> per epoch
+    UserVote(u64, Address),           // UserIceVote per (epoch, user)
+    LastFinalizedEpoch,               // u64, last epoch applied to VoteAllocations
+}
+
 #[contracttype]
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum Error {
@@ -464,11 +1392,28 @@ pub enum Error {
     // Vault Errors
     PoolNotActive = 25,
     PoolNotFound = 26,
-    MaxPoolsReached = 27,
+    MaxPoolsReached = 27,             // Unused since v1.9.0 (no pool limit)
     PositionNotFound = 28,
     ClaimCooldownActive = 29,
     UnstakeCooldownActive = 30,
     NoRewardsToClaim = 31,
+    // Invariant Errors
+    InvariantViolation = 32,
+    InsufficientBucketBalance = 33,
+    PoolDeprecated = 34,
+    PoolMetadataMismatch = 35,
+    PoolNotVerified = 36,
+    SlippageExceeded = 37,
+    DepositCapExceeded = 38,
+    ZeroSharesMinted = 39,
+    PoolInEmergencyExit = 40,
+    HarvestTooSoon = 41,
+    HarvestNotConfigured = 42,
+    OraclePriceUnavailable = 43,
+    InvalidIceLockState = 44,
+    IceRenewalNotConfigured = 45,
+    IceVotesOverallocated = 46,
+    VoteEpochNotEnded = 47,
 }
 
 impl From<Error> for soroban_sdk::Error {
@@ -644,35 +1589,37 @@ impl StakingRegistry {
         vault_treasury: Address,
         vault_fee_bps: u32,
     ) -> Result<(), Error> {
-        if env.storage().instance().has(&DataKey::Config) {
+        if env.storage().instance().has(&DataKey::AdminAddress)
+            || env.storage().instance().has(&DataKey::Config)
+        {
             return Err(Error::AlreadyInitialized);
         }
         admin.require_auth();
 
-        let cfg = Config {
-            admin: admin.clone(),
-            version: 10200, // v1.2.0
-            total_supply: 0,
-            treasury_address,
-            reward_rate: 100, // 1% per period default
+        // Store admin in a stable, format-independent key so upgrade() always works
+        env.storage().instance().set(&DataKey::AdminAddress, &admin);
+
+        env.storage().instance().set(&ConfigKey::ConfigVersion, &10900u32); // v1.9.0
+        env.storage().instance().set(&ConfigKey::TotalSupply, &0i128);
+        Self::write_token_config(&env, &TokenConfig {
             aqua_token,
             blub_token,
             liquidity_contract,
-            ice_token: ice_tokens.ice_token,
-            govern_ice_token: ice_tokens.govern_ice_token,
-            upvote_ice_token: ice_tokens.upvote_ice_token,
-            downvote_ice_token: ice_tokens.downvote_ice_token,
-            period_unit_minutes: 1,
+        });
+        Self::write_ice_tokens(&env, &ice_tokens);
+        Self::write_fee_config(&env, &FeeConfig {
+            treasury_address,
             vault_treasury,
             vault_fee_bps,
-            // Default cooldowns: 10 days unstake, 7 days claim
-            unstake_cooldown_seconds: 864000,      // 10 days
-            claim_reward_cooldown_seconds: 604800, // 7 days
-        };
-        env.storage().instance().set(&DataKey::Config, &cfg);
-
-        // Store admin in a stable, format-independent key so upgrade() always works
-        env.storage().instance().set(&DataKey::AdminAddress, &admin);
+        });
+        Self::write_cooldown_config(&env, &CooldownConfig {
+            unstake_cooldown_seconds: DEFAULT_UNSTAKE_COOLDOWN_SECONDS,
+            claim_reward_cooldown_seconds: DEFAULT_CLAIM_COOLDOWN_SECONDS,
+        });
+        Self::write_period_config(&env, &PeriodConfig {
+            period_unit_minutes: 1,
+            reward_rate: 100, // 1% per period default
+        });
 
         // Initialize global state
         let global_state = GlobalState {
@@ -717,28 +1664,154 @@ impl StakingRegistry {
         };
         env.storage().instance().set(&DataKey::RewardStateV2, &reward_state);
 
+        Self::write_token_ledger(&env, &TokenLedger {
+            blub_principal: 0,
+            blub_reward_reserve: 0,
+            blub_pol_in_transit: 0,
+            blub_vault_rewards: 0,
+            blub_vault_idle: 0,
+            aqua_revenue: 0,
+            aqua_vault_idle: 0,
+        });
+
+        // Every staker is registered from the first stake onwards
+        env.storage().instance().set(&LedgerKey::StakerRegistryComplete, &true);
+
         Ok(())
     }
 
     /// Retrieves the current contract configuration.
     ///
+    /// Assembled from the individually keyed settings groups; nothing is
+    /// stored under a single Config entry any more (v1.9.0+).
+    ///
     /// # Returns
     /// * `Ok(Config)` - The contract configuration
-    /// * `Err(Error::NotInitialized)` if contract is not initialized
+    /// * `Err(Error::NotInitialized)` if contract is not initialized (or not yet migrated)
     pub fn get_config(env: Env) -> Result<Config, Error> {
+        let admin = Self::read_admin(&env)?;
+        let tokens = Self::read_token_config(&env)?;
+        let ice = Self::read_ice_tokens(&env)?;
+        let fees = Self::read_fee_config(&env)?;
+        let cooldowns = Self::read_cooldown_config(&env);
+        let periods = Self::read_period_config(&env);
+
+        Ok(Config {
+            admin,
+            version: env.storage().instance().get(&ConfigKey::ConfigVersion).unwrap_or(0),
+            total_supply: env.storage().instance().get(&ConfigKey::TotalSupply).unwrap_or(0),
+            treasury_address: fees.treasury_address,
+            reward_rate: periods.reward_rate,
+            aqua_token: tokens.aqua_token,
+            blub_token: tokens.blub_token,
+            liquidity_contract: tokens.liquidity_contract,
+            ice_token: ice.ice_token,
+            govern_ice_token: ice.govern_ice_token,
+            upvote_ice_token: ice.upvote_ice_token,
+            downvote_ice_token: ice.downvote_ice_token,
+            period_unit_minutes: periods.period_unit_minutes,
+            vault_treasury: fees.vault_treasury,
+            vault_fee_bps: fees.vault_fee_bps,
+            unstake_cooldown_seconds: cooldowns.unstake_cooldown_seconds,
+            claim_reward_cooldown_seconds: cooldowns.claim_reward_cooldown_seconds,
+        })
+    }
+
+    // ============================================================================
+    // Config accessors (v1.9.0)
+    // ============================================================================
+
+    /// Internal: Read the admin from the stable AdminAddress key
+    fn read_admin(env: &Env) -> Result<Address, Error> {
         env.storage()
             .instance()
-            .get(&DataKey::Config)
+            .get(&DataKey::AdminAddress)
+            .ok_or(Error::NotInitialized)
+    }
+
+    /// Internal: Require admin authorization against the stable AdminAddress key
+    fn require_admin_auth(env: &Env, admin: &Address) -> Result<(), Error> {
+        admin.require_auth();
+        let stored_admin = env.storage()
+            .instance()
+            .get::<DataKey, Address>(&DataKey::AdminAddress)
+            .ok_or(Error::Unauthorized)?;
+        if stored_admin != *admin {
+            return Err(Error::Unauthorized);
+        }
+        Ok(())
+    }
+
+    fn read_token_config(env: &Env) -> Result<TokenConfig, Error> {
+        env.storage()
+            .instance()
+            .get(&ConfigKey::TokenConfig)
+            .ok_or(Error::NotInitialized)
+    }
+
+    fn write_token_config(env: &Env, tokens: &TokenConfig) {
+        env.storage().instance().set(&ConfigKey::TokenConfig, tokens);
+    }
+
+    fn read_ice_tokens(env: &Env) -> Result<IceTokens, Error> {
+        env.storage()
+            .instance()
+            .get(&ConfigKey::IceTokenConfig)
             .ok_or(Error::NotInitialized)
     }
 
+    fn write_ice_tokens(env: &Env, ice_tokens: &IceTokens) {
+        env.storage().instance().set(&ConfigKey::IceTokenConfig, ice_tokens);
+    }
+
+    fn read_fee_config(env: &Env) -> Result<FeeConfig, Error> {
+        env.storage()
+            .instance()
+            .get(&ConfigKey::FeeConfig)
+            .ok_or(Error::NotInitialized)
+    }
+
+    fn write_fee_config(env: &Env, fees: &FeeConfig) {
+        env.storage().instance().set(&ConfigKey::FeeConfig, fees);
+    }
+
+    /// Internal: Cooldowns fall back to protocol defaults when never set
+    fn read_cooldown_config(env: &Env) -> CooldownConfig {
+        env.storage()
+            .instance()
+            .get(&ConfigKey::CooldownConfig)
+            .unwrap_or(CooldownConfig {
+                unstake_cooldown_seconds: DEFAULT_UNSTAKE_COOLDOWN_SECONDS,
+                claim_reward_cooldown_seconds: DEFAULT_CLAIM_COOLDOWN_SECONDS,
+            })
+    }
+
+    fn write_cooldown_config(env: &Env, cooldowns: &CooldownConfig) {
+        env.storage().instance().set(&ConfigKey::CooldownConfig, cooldowns);
+    }
+
+    /// Internal: Period settings fall back to 1-minute periods / 100 bps when never set
+    fn read_period_config(env: &Env) -> PeriodConfig {
+        env.storage()
+            .instance()
+            .get(&ConfigKey::PeriodConfig)
+            .unwrap_or(PeriodConfig {
+                period_unit_minutes: 1,
+                reward_rate: 100,
+            })
+    }
+
+    fn write_period_config(env: &Env, periods: &PeriodConfig) {
+        env.storage().instance().set(&ConfigKey::PeriodConfig, periods);
+    }
+
     // Staking/unstaking/restaking logic
 
     /// Helper function to deposit POL assets to AQUA-BLUB LP pool on Stellar network
     /// Uses the existing StableSwap pool interface
     fn deposit_pol_to_lp(
         env: &Env,
-        config: &Config,
+        config: &TokenConfig,
         aqua_amount: i128,
         blub_amount: i128,
     ) -> Result<(), Error> {
@@ -922,13 +1995,8 @@ impl StakingRegistry {
     /// # Authorization
     /// Requires authorization from the current `admin` address.
     pub fn update_sac_admin(env: Env, admin: Address, new_admin: Address) -> Result<(), Error> {
-        admin.require_auth();
-        
-        // Get config and verify admin authorization
-        let config = Self::get_config(env.clone())?;
-        if config.admin != admin {
-            return Err(Error::Unauthorized);
-        }
+        Self::require_admin_auth(&env, &admin)?;
+        let config = Self::read_token_config(&env)?;
 
         use soroban_sdk::token;
 
@@ -988,14 +2056,15 @@ impl StakingRegistry {
         env.storage().instance().set(&DataKey::GlobalState, &global_state);
         
         // Get config - only use whitelisted AQUA token
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
+        let periods = Self::read_period_config(&env);
         let contract_address = env.current_contract_address();
         let now = env.ledger().timestamp();
         
         // ===== EFFECTS: UPDATE ALL STATE FIRST =====
         
         // Calculate duration in minutes
-        let duration_minutes = duration_periods * config.period_unit_minutes;
+        let duration_minutes = duration_periods * periods.period_unit_minutes;
         let unlock_timestamp = now + (duration_minutes * 60);
 
         let pol_aqua = amount / 10;                // 10% AQUA for POL
@@ -1062,6 +2131,12 @@ impl StakingRegistry {
 
         // Update POL contribution with both AQUA and BLUB
         Self::update_pol_contribution(&env, pol_aqua, blub_to_lp)?;
+
+        // Minted BLUB: 1x is staked principal, 0.1x is POL in transit until sent
+        let mut ledger = Self::read_token_ledger(&env);
+        ledger.blub_principal = ledger.blub_principal.saturating_add(blub_staked);
+        ledger.blub_pol_in_transit = ledger.blub_pol_in_transit.saturating_add(blub_to_lp);
+        Self::write_token_ledger(&env, &ledger);
         
         // Update global state (but keep locked=true until end)
         global_state.total_locked = global_state.total_locked.saturating_add(amount);
@@ -1093,14 +2168,14 @@ impl StakingRegistry {
 
         // ===== SEND 10% AQUA + 0.1x BLUB TO MANAGER WALLET =====
         // Manager (blub-issuer-v2) will deposit these to AQUA/BLUB pool to get ICE boost.
-        // NOTE: Send to manager (single-sig backend), NOT config.admin (multisig cold wallet),
+        // NOTE: Send to manager (single-sig backend), NOT the admin (multisig cold wallet),
         // so the backend can auto-deposit without requiring 2-of-3 signatures.
         if pol_aqua > 0 && blub_to_lp > 0 {
             // Resolve manager address (falls back to admin if ManagerAddress not set)
             let pol_recipient = env.storage()
                 .instance()
                 .get::<DataKey, Address>(&DataKey::ManagerAddress)
-                .unwrap_or_else(|| config.admin.clone());
+                .map_or_else(|| Self::read_admin(&env), Ok)?;
 
             // Transfer AQUA to manager wallet
             let transfer_aqua_result = aqua_client.try_transfer(&contract_address, &pol_recipient, &pol_aqua);
@@ -1127,6 +2202,9 @@ impl StakingRegistry {
                 return Err(Error::InsufficientBalance);
             }
 
+            Self::debit_bucket(&mut ledger.blub_pol_in_transit, blub_to_lp)?;
+            Self::write_token_ledger(&env, &ledger);
+
             // Emit event for backend to deposit to AQUA/BLUB pool
             let pol_trigger_event = PolDepositTriggerEvent {
                 user: user.clone(),
@@ -1136,6 +2214,10 @@ impl StakingRegistry {
                 tx_hash: tx_hash_bytes.clone(),
             };
             env.events().publish((symbol_short!("pol_dep"),), pol_trigger_event);
+        } else if pol_aqua > 0 {
+            // Too small to pair with BLUB: the POL AQUA stays in the contract
+            ledger.aqua_revenue = ledger.aqua_revenue.saturating_add(pol_aqua);
+            Self::write_token_ledger(&env, &ledger);
         }
 
         // ===== RELEASE RE-ENTRANCY LOCK =====
@@ -1265,10 +2347,10 @@ impl StakingRegistry {
         global_state.locked = true;
         env.storage().instance().set(&DataKey::GlobalState, &global_state);
 
-        let config = Self::get_config(env.clone())?;
+        let periods = Self::read_period_config(&env);
         let now = env.ledger().timestamp();
 
-        let duration_minutes = duration_periods * config.period_unit_minutes;
+        let duration_minutes = duration_periods * periods.period_unit_minutes;
         let unlock_timestamp = now + (duration_minutes * 60);
 
         let reward_multiplier = Self::calculate_lock_multiplier(duration_minutes);
@@ -1394,7 +2476,7 @@ impl StakingRegistry {
         global_state.locked = true;
         env.storage().instance().set(&DataKey::GlobalState, &global_state);
 
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         let contract_address = env.current_contract_address();
 
         let now = env.ledger().timestamp();
@@ -1439,6 +2521,14 @@ impl StakingRegistry {
         // Transfer LOCKED BLUB from contract to user's wallet
         // Rewards are claimed separately via claim_rewards() (Synthetix system)
         if blub_locked > 0 {
+            let mut ledger = Self::read_token_ledger(&env);
+            if let Err(e) = Self::debit_bucket(&mut ledger.blub_principal, blub_locked) {
+                global_state.locked = false;
+                env.storage().instance().set(&DataKey::GlobalState, &global_state);
+                return Err(e);
+            }
+            Self::write_token_ledger(&env, &ledger);
+
             use soroban_sdk::token;
             let blub_client = token::Client::new(&env, &config.blub_token);
             let transfer_result = blub_client.try_transfer(&contract_address, &user, &blub_locked);
@@ -1514,11 +2604,12 @@ impl StakingRegistry {
         global_state.locked = true;
         env.storage().instance().set(&DataKey::GlobalState, &global_state);
 
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
+        let periods = Self::read_period_config(&env);
         let contract_address = env.current_contract_address();
         let now = env.ledger().timestamp();
         
-        let duration_minutes = duration_periods * config.period_unit_minutes;
+        let duration_minutes = duration_periods * periods.period_unit_minutes;
         let unlock_timestamp = now + (duration_minutes * 60);
         let reward_multiplier = Self::calculate_lock_multiplier(duration_minutes);
         
@@ -1581,6 +2672,10 @@ impl StakingRegistry {
         global_state.total_blub_supply = global_state.total_blub_supply.saturating_add(amount);
         global_state.last_reward_update = env.ledger().timestamp();
 
+        let mut ledger = Self::read_token_ledger(&env);
+        ledger.blub_principal = ledger.blub_principal.saturating_add(amount);
+        Self::write_token_ledger(&env, &ledger);
+
         // ===== INTERACTIONS: TRANSFER BLUB LAST =====
 
         use soroban_sdk::token;
@@ -2458,7 +3553,7 @@ impl StakingRegistry {
     /// * `Ok((i128, i128))` - A tuple of (aqua_reserve, blub_reserve)
     /// * `Err(Error::InvalidInput)` if the pool query fails
     pub fn get_pool_reserves(env: Env) -> Result<(i128, i128), Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         
         use soroban_sdk::IntoVal;
         
@@ -2490,7 +3585,7 @@ impl StakingRegistry {
     /// * `Ok(Address)` - The share token contract address
     /// * `Err(Error::InvalidInput)` if the pool query fails
     pub fn get_pool_share_token(env: Env) -> Result<Address, Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         
         use soroban_sdk::IntoVal;
         
@@ -2537,7 +3632,7 @@ impl StakingRegistry {
         min_aqua: i128,
         min_blub: i128,
     ) -> Result<(i128, i128), Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         admin.require_auth();
         let stored_admin = env.storage()
             .instance()
@@ -2577,6 +3672,12 @@ impl StakingRegistry {
                     let mut pol = Self::get_pol(&env);
                     pol.aqua_blub_lp_position = pol.aqua_blub_lp_position.saturating_sub(share_amount);
                     env.storage().instance().set(&DataKey::ProtocolOwnedLiquidity, &pol);
+
+                    // Withdrawn POL tokens return to the POL buckets
+                    let mut ledger = Self::read_token_ledger(&env);
+                    ledger.aqua_revenue = ledger.aqua_revenue.saturating_add(aqua_withdrawn);
+                    ledger.blub_pol_in_transit = ledger.blub_pol_in_transit.saturating_add(blub_withdrawn);
+                    Self::write_token_ledger(&env, &ledger);
                     
                     // Emit withdrawal event
                     env.events().publish(
@@ -2602,7 +3703,7 @@ impl StakingRegistry {
     /// * `Ok(i128)` - The virtual price
     /// * `Err(Error::InvalidInput)` if the pool query fails
     pub fn get_pool_virtual_price(env: Env) -> Result<i128, Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         
         use soroban_sdk::IntoVal;
         
@@ -2638,7 +3739,7 @@ impl StakingRegistry {
         env: Env,
         manager: Address,
     ) -> Result<i128, Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         Self::require_manager_auth(&env, &manager)?;
 
         let contract_address = env.current_contract_address();
@@ -2658,6 +3759,10 @@ impl StakingRegistry {
                 pol.total_pol_rewards_earned = pol.total_pol_rewards_earned.saturating_add(reward_amount as i128);
                 pol.last_reward_claim = env.ledger().timestamp();
                 env.storage().instance().set(&DataKey::ProtocolOwnedLiquidity, &pol);
+
+                let mut ledger = Self::read_token_ledger(&env);
+                ledger.aqua_revenue = ledger.aqua_revenue.saturating_add(reward_amount as i128);
+                Self::write_token_ledger(&env, &ledger);
                 
                 env.events().publish(
                     (symbol_short!("pool_clm"),),
@@ -2677,7 +3782,7 @@ impl StakingRegistry {
     /// * `Ok(i128)` - The amount of pending rewards
     /// * `Err(Error::InvalidInput)` if the pool query fails
     pub fn get_pool_pending_rewards(env: Env) -> Result<i128, Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         let contract_address = env.current_contract_address();
         
         use soroban_sdk::IntoVal;
@@ -2710,13 +3815,12 @@ impl StakingRegistry {
     /// # Authorization
     /// Requires authorization from the `admin` address.
     pub fn update_reward_rate(env: Env, admin: Address, new_rate: i128) -> Result<(), Error> {
-        let mut cfg = Self::get_config(env.clone())?;
-        admin.require_auth();
-        if cfg.admin != admin { return Err(Error::Unauthorized); }
+        Self::require_admin_auth(&env, &admin)?;
         if new_rate > 1000 { return Err(Error::InvalidInput); }
 
-        cfg.reward_rate = new_rate;
-        env.storage().instance().set(&DataKey::Config, &cfg);
+        let mut periods = Self::read_period_config(&env);
+        periods.reward_rate = new_rate;
+        Self::write_period_config(&env, &periods);
         Ok(())
     }
 
@@ -2745,7 +3849,7 @@ impl StakingRegistry {
         aqua_amount: i128,
         blub_amount: i128,
     ) -> Result<(), Error> {
-        let cfg = Self::get_config(env.clone())?;
+        let cfg = Self::read_token_config(&env)?;
         Self::require_manager_auth(&env, &manager)?;
 
         if aqua_amount <= 0 || blub_amount <= 0 {
@@ -2769,6 +3873,12 @@ impl StakingRegistry {
             return Err(Error::InsufficientBalance);
         }
 
+        // POL is funded from AQUA revenue and BLUB POL in transit only
+        let mut ledger = Self::read_token_ledger(&env);
+        Self::debit_bucket(&mut ledger.aqua_revenue, aqua_amount)?;
+        Self::debit_bucket(&mut ledger.blub_pol_in_transit, blub_amount)?;
+        Self::write_token_ledger(&env, &ledger);
+
         // Deposit to LP
         Self::deposit_pol_to_lp(&env, &cfg, aqua_amount, blub_amount)?;
 
@@ -2794,12 +3904,11 @@ impl StakingRegistry {
     /// # Authorization
     /// Requires authorization from the `admin` address.
     pub fn update_liquidity_contract(env: Env, admin: Address, new_liquidity_contract: Address) -> Result<(), Error> {
-        let mut cfg = Self::get_config(env.clone())?;
-        admin.require_auth();
-        if cfg.admin != admin { return Err(Error::Unauthorized); }
+        Self::require_admin_auth(&env, &admin)?;
 
-        cfg.liquidity_contract = new_liquidity_contract.clone();
-        env.storage().instance().set(&DataKey::Config, &cfg);
+        let mut tokens = Self::read_token_config(&env)?;
+        tokens.liquidity_contract = new_liquidity_contract.clone();
+        Self::write_token_config(&env, &tokens);
         
         env.events().publish(
             (symbol_short!("liq_upd"),),
@@ -2826,14 +3935,11 @@ impl StakingRegistry {
         admin: Address,
         new_blub_token: Address,
     ) -> Result<(), Error> {
-        let mut cfg = Self::get_config(env.clone())?;
-        admin.require_auth();
-        if cfg.admin != admin {
-            return Err(Error::Unauthorized);
-        }
+        Self::require_admin_auth(&env, &admin)?;
 
-        cfg.blub_token = new_blub_token.clone();
-        env.storage().instance().set(&DataKey::Config, &cfg);
+        let mut tokens = Self::read_token_config(&env)?;
+        tokens.blub_token = new_blub_token.clone();
+        Self::write_token_config(&env, &tokens);
 
         env.events().publish(
             (symbol_short!("blub_upd"),),
@@ -2845,12 +3951,11 @@ impl StakingRegistry {
 
     /// Updates vault treasury address (admin-only).
     pub fn update_vault_treasury(env: Env, admin: Address, new_treasury: Address) -> Result<(), Error> {
-        let mut cfg = Self::get_config(env.clone())?;
-        admin.require_auth();
-        if cfg.admin != admin { return Err(Error::Unauthorized); }
+        Self::require_admin_auth(&env, &admin)?;
 
-        cfg.vault_treasury = new_treasury.clone();
-        env.storage().instance().set(&DataKey::Config, &cfg);
+        let mut fees = Self::read_fee_config(&env)?;
+        fees.vault_treasury = new_treasury.clone();
+        Self::write_fee_config(&env, &fees);
 
         env.events().publish(
             (symbol_short!("vtrs_upd"),),
@@ -2863,13 +3968,12 @@ impl StakingRegistry {
     /// Updates vault fee in basis points (admin-only).
     /// Max 5000 (50%).
     pub fn update_vault_fee_bps(env: Env, admin: Address, new_fee_bps: u32) -> Result<(), Error> {
-        let mut cfg = Self::get_config(env.clone())?;
-        admin.require_auth();
-        if cfg.admin != admin { return Err(Error::Unauthorized); }
+        Self::require_admin_auth(&env, &admin)?;
         if new_fee_bps > 5000 { return Err(Error::InvalidInput); }
 
-        cfg.vault_fee_bps = new_fee_bps;
-        env.storage().instance().set(&DataKey::Config, &cfg);
+        let mut fees = Self::read_fee_config(&env)?;
+        fees.vault_fee_bps = new_fee_bps;
+        Self::write_fee_config(&env, &fees);
 
         env.events().publish(
             (symbol_short!("vfee_upd"),),
@@ -2888,15 +3992,14 @@ impl StakingRegistry {
         upvote_ice_token: Address,
         downvote_ice_token: Address,
     ) -> Result<(), Error> {
-        let mut cfg = Self::get_config(env.clone())?;
-        admin.require_auth();
-        if cfg.admin != admin { return Err(Error::Unauthorized); }
+        Self::require_admin_auth(&env, &admin)?;
 
-        cfg.ice_token = ice_token.clone();
-        cfg.govern_ice_token = govern_ice_token.clone();
-        cfg.upvote_ice_token = upvote_ice_token.clone();
-        cfg.downvote_ice_token = downvote_ice_token.clone();
-        env.storage().instance().set(&DataKey::Config, &cfg);
+        Self::write_ice_tokens(&env, &IceTokens {
+            ice_token: ice_token.clone(),
+            govern_ice_token,
+            upvote_ice_token,
+            downvote_ice_token,
+        });
 
         env.events().publish(
             (symbol_short!("ice_upd"),),
@@ -2912,6 +4015,11 @@ impl StakingRegistry {
 
     /// Upgrades the contract to a new WASM hash (admin-only).
     ///
+    /// When the upgrade guard is enabled (see `set_upgrade_invariant_check`),
+    /// the upgrade is refused with `Error::InvariantViolation` if
+    /// `check_invariants` does not pass. Only the constant-cost aggregates are
+    /// checked, so the guard never outgrows a transaction's read limits.
+    ///
     /// # Arguments
     /// * `admin` - The admin address authorizing this operation
     /// * `new_wasm_hash` - The hash of the new WASM to upgrade to
@@ -2919,32 +4027,20 @@ impl StakingRegistry {
     /// # Authorization
     /// Requires authorization from the `admin` address.
     pub fn upgrade(env: Env, admin: Address, new_wasm_hash: soroban_sdk::BytesN<32>) -> Result<(), Error> {
-        admin.require_auth();
-
-        // STEP 1: Verify admin using the stable AdminAddress key first.
-        // This key stores just an Address and will never break due to
-        // Config struct changes, preventing the contract from being bricked.
-        let is_admin = if let Some(stored_admin) = env.storage().instance().get::<DataKey, Address>(&DataKey::AdminAddress) {
-            stored_admin == admin
-        } else if let Ok(cfg) = Self::get_config(env.clone()) {
-            cfg.admin == admin
-        } else if let Some(cfg_v1_1) = env.storage().instance().get::<DataKey, ConfigV1_1>(&DataKey::Config) {
-            cfg_v1_1.admin == admin
-        } else {
-            let old_cfg: OldConfig = env.storage().instance()
-                .get(&DataKey::Config)
-                .ok_or(Error::NotInitialized)?;
-            old_cfg.admin == admin
-        };
-
-        if !is_admin {
-            return Err(Error::Unauthorized);
-        }
-
-        // STEP 2: Ensure AdminAddress key exists for future upgrades.
-        // This handles contracts initialized before this key was added.
-        if !env.storage().instance().has(&DataKey::AdminAddress) {
-            env.storage().instance().set(&DataKey::AdminAddress, &admin);
+        // Verified against the stable AdminAddress key only. Configuration is
+        // stored as independently keyed groups (v1.9.0+), so no struct layout
+        // change can ever make this check undecodable.
+        Self::require_admin_auth(&env, &admin)?;
+
+        // The new WASM only replaces the code once this invocation completes
+        // and does not touch storage, so the state checked here is exactly
+        // the state the new code starts from. Failing reverts the whole call.
+        let guarded: bool = env.storage()
+            .instance()
+            .get(&ConfigKey::UpgradeInvariantCheck)
+            .unwrap_or(false);
+        if guarded && !Self::build_invariant_report(&env)?.ok {
+            return Err(Error::InvariantViolation);
         }
 
         env.deployer().update_current_contract_wasm(new_wasm_hash);
@@ -2957,88 +4053,89 @@ impl StakingRegistry {
         Ok(())
     }
 
-    /// Migrate contract from v1.1.0 to v1.2.0
-    /// - Adds cooldown fields to Config
-    /// - Initializes RewardStateV2 with correct total_staked
+    /// Migrate contract from the monolithic Config struct to keyed settings (v1.9.0).
+    ///
+    /// Reads the legacy `DataKey::Config` entry once, writes each settings
+    /// group (tokens, ICE tokens, fees, cooldowns, periods) under its own key
+    /// and removes the legacy entry. New parameters can afterwards be added as
+    /// new keys without another storage migration.
     ///
     /// # Arguments
     /// * `admin` - Admin address for authorization
     ///
     /// # Returns
     /// * `Ok(())` on success
-    /// * `Err(Error::AlreadyInitialized)` if already v1.2.0
+    /// * `Err(Error::AlreadyInitialized)` if already migrated
     /// * `Err(Error::Unauthorized)` if not admin
-    pub fn migrate_v1_2_0(env: Env, admin: Address) -> Result<(), Error> {
-        admin.require_auth();
-
-        // Try to read as v1.1.0 config (15 fields, no cooldowns)
-        let old_config: ConfigV1_1 = env.storage().instance()
-            .get(&DataKey::Config)
-            .ok_or(Error::NotInitialized)?;
-
-        // Check admin authorization
-        if old_config.admin != admin {
-            return Err(Error::Unauthorized);
-        }
+    ///
+    /// Must be called ONCE after upgrading to v1.9.0 WASM.
+    pub fn migrate_v1_9_0(env: Env, admin: Address) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
 
-        // Check if already migrated to v1.2.0
-        if old_config.version >= 10200 {
+        if env.storage().instance().has(&ConfigKey::TokenConfig) {
             return Err(Error::AlreadyInitialized);
         }
 
-        // Create new Config with cooldown fields
-        let new_config = Config {
-            admin: old_config.admin,
-            version: 10200, // v1.2.0
-            total_supply: old_config.total_supply,
-            treasury_address: old_config.treasury_address,
-            reward_rate: old_config.reward_rate,
-            aqua_token: old_config.aqua_token,
-            blub_token: old_config.blub_token,
-            liquidity_contract: old_config.liquidity_contract,
-            ice_token: old_config.ice_token,
-            govern_ice_token: old_config.govern_ice_token,
-            upvote_ice_token: old_config.upvote_ice_token,
-            downvote_ice_token: old_config.downvote_ice_token,
-            period_unit_minutes: old_config.period_unit_minutes,
-            vault_treasury: old_config.vault_treasury,
-            vault_fee_bps: old_config.vault_fee_bps,
-            // New cooldown fields with defaults
-            unstake_cooldown_seconds: 864000,      // 10 days
-            claim_reward_cooldown_seconds: 604800, // 7 days
-        };
+        let legacy: LegacyConfig = env.storage().instance()
+            .get(&DataKey::Config)
+            .ok_or(Error::NotInitialized)?;
 
-        // Save new config
-        env.storage().instance().set(&DataKey::Config, &new_config);
+        env.storage().instance().set(&ConfigKey::TotalSupply, &legacy.total_supply);
+        Self::write_token_config(&env, &TokenConfig {
+            aqua_token: legacy.aqua_token,
+            blub_token: legacy.blub_token,
+            liquidity_contract: legacy.liquidity_contract,
+        });
+        Self::write_ice_tokens(&env, &IceTokens {
+            ice_token: legacy.ice_token,
+            govern_ice_token: legacy.govern_ice_token,
+            upvote_ice_token: legacy.upvote_ice_token,
+            downvote_ice_token: legacy.downvote_ice_token,
+        });
+        Self::write_fee_config(&env, &FeeConfig {
+            treasury_address: legacy.treasury_address,
+            vault_treasury: legacy.vault_treasury,
+            vault_fee_bps: legacy.vault_fee_bps,
+        });
+        Self::write_cooldown_config(&env, &CooldownConfig {
+            unstake_cooldown_seconds: legacy.unstake_cooldown_seconds,
+            claim_reward_cooldown_seconds: legacy.claim_reward_cooldown_seconds,
+        });
+        Self::write_period_config(&env, &PeriodConfig {
+            period_unit_minutes: legacy.period_unit_minutes,
+            reward_rate: legacy.reward_rate,
+        });
+        env.storage().instance().set(&ConfigKey::ConfigVersion, &10900u32);
 
-        // Get total staked from GlobalState
-        // Use total_locked (AQUA) which equals staked BLUB (1:1 ratio)
-        // total_blub_supply includes 0.1x that goes to LP, not staked
+        // Seed the token ledger from live balances. Staked principal comes
+        // first, then outstanding rewards; AQUA beyond the ICE allocation is
+        // protocol revenue (vault flows never leave tokens behind).
+        use soroban_sdk::token;
+        let tokens = Self::read_token_config(&env)?;
+        let contract_address = env.current_contract_address();
+        let blub_balance = token::Client::new(&env, &tokens.blub_token).balance(&contract_address);
+        let aqua_balance = token::Client::new(&env, &tokens.aqua_token).balance(&contract_address);
+        let reward_state = Self::get_reward_state(&env);
         let global_state = Self::get_global_state(env.clone())?;
-        let total_staked = global_state.total_locked;
-
-        // Initialize RewardStateV2 if not exists
-        let reward_state_exists: bool = env.storage()
-            .instance()
-            .has(&DataKey::RewardStateV2);
-
-        if !reward_state_exists {
-            let reward_state = RewardState {
-                reward_per_token_stored: 0,
-                last_update_time: env.ledger().timestamp(),
-                total_staked,
-                total_rewards_added: 0,
-                total_rewards_claimed: 0,
-            };
-            env.storage().instance().set(&DataKey::RewardStateV2, &reward_state);
-        }
+        let owed_rewards = reward_state
+            .total_rewards_added
+            .saturating_sub(reward_state.total_rewards_claimed)
+            .max(0);
+        Self::write_token_ledger(&env, &TokenLedger {
+            blub_principal: reward_state.total_staked,
+            blub_reward_reserve: owed_rewards
+                .min(blub_balance.saturating_sub(reward_state.total_staked))
+                .max(0),
+            blub_pol_in_transit: 0,
+            blub_vault_rewards: 0,
+            blub_vault_idle: 0,
+            aqua_revenue: aqua_balance.saturating_sub(global_state.pending_aqua_for_ice).max(0),
+            aqua_vault_idle: 0,
+        });
 
-        // Emit migration event
-        env.events().publish(
-            (symbol_short!("migrated"),),
-            10200u32,
-        );
+        env.storage().instance().remove(&DataKey::Config);
 
+        env.events().publish((symbol_short!("migrated"),), 10900u32);
         Ok(())
     }
 
@@ -3069,24 +4166,81 @@ impl StakingRegistry {
         env.storage().instance().get(&DataKey::ManagerAddress)
     }
 
-    /// Migration from v1.2.0 to v1.4.0: sets ManagerAddress = current admin.
+    /// Sets the curator address (admin-only).
     ///
-    /// After this migration:
-    /// 1. Transfer admin to the new multisig cold wallet via `set_admin` (or re-initialize).
-    /// 2. The existing blub-issuer-v2 will continue as manager unchanged.
+    /// The curator sets per-pool deposit caps. Until a curator is set the
+    /// manager (or admin) acts as curator.
     ///
     /// # Authorization
-    /// Requires current admin authorization.
-    pub fn migrate_v1_4_0(env: Env, admin: Address) -> Result<(), Error> {
-        admin.require_auth();
-        let stored_admin = env.storage()
-            .instance()
-            .get::<DataKey, Address>(&DataKey::AdminAddress)
-            .ok_or(Error::Unauthorized)?;
-        if stored_admin != admin {
-            return Err(Error::Unauthorized);
-        }
-        // Only set if not already configured — idempotent
+    /// Requires admin authorization
+    pub fn set_curator(env: Env, admin: Address, curator: Address) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+        env.storage().instance().set(&PoolKey::Curator, &curator);
+        env.events().publish((symbol_short!("set_cur"),), curator);
+        Ok(())
+    }
+
+    /// Returns the curator address, or None if not set.
+    pub fn get_curator_address(env: Env) -> Option<Address> {
+        env.storage().instance().get(&PoolKey::Curator)
+    }
+
+    /// Sets the guardian address (admin-only).
+    ///
+    /// The guardian can trigger `emergency_exit_pool` alongside the admin, so a
+    /// hot wallet can react without waiting on the multisig.
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn set_guardian(env: Env, admin: Address, guardian: Address) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+        env.storage().instance().set(&PoolKey::Guardian, &guardian);
+        env.events().publish((symbol_short!("set_grd"),), guardian);
+        Ok(())
+    }
+
+    /// Returns the guardian address, or None if not set.
+    pub fn get_guardian_address(env: Env) -> Option<Address> {
+        env.storage().instance().get(&PoolKey::Guardian)
+    }
+
+    /// Sets the reward gauge address (admin-only).
+    ///
+    /// The gauge can fund vault pool rewards with `fund_vault_rewards`
+    /// alongside the manager.
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn set_reward_gauge(env: Env, admin: Address, gauge: Address) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+        env.storage().instance().set(&PoolKey::RewardGauge, &gauge);
+        env.events().publish((symbol_short!("set_gauge"),), gauge);
+        Ok(())
+    }
+
+    /// Returns the reward gauge address, or None if not set.
+    pub fn get_reward_gauge_address(env: Env) -> Option<Address> {
+        env.storage().instance().get(&PoolKey::RewardGauge)
+    }
+
+    /// Migration from v1.2.0 to v1.4.0: sets ManagerAddress = current admin.
+    ///
+    /// After this migration:
+    /// 1. Transfer admin to the new multisig cold wallet via `set_admin` (or re-initialize).
+    /// 2. The existing blub-issuer-v2 will continue as manager unchanged.
+    ///
+    /// # Authorization
+    /// Requires current admin authorization.
+    pub fn migrate_v1_4_0(env: Env, admin: Address) -> Result<(), Error> {
+        admin.require_auth();
+        let stored_admin = env.storage()
+            .instance()
+            .get::<DataKey, Address>(&DataKey::AdminAddress)
+            .ok_or(Error::Unauthorized)?;
+        if stored_admin != admin {
+            return Err(Error::Unauthorized);
+        }
+        // Only set if not already configured — idempotent
         if !env.storage().instance().has(&DataKey::ManagerAddress) {
             env.storage().instance().set(&DataKey::ManagerAddress, &admin);
         }
@@ -3124,11 +4278,7 @@ impl StakingRegistry {
         }
 
         let pool_id: u32 = 0;
-        let mut pool_info: PoolInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
 
         let old_total_lp = pool_info.total_lp_tokens;
 
@@ -3178,13 +4328,17 @@ impl StakingRegistry {
         let new_total_shares = primary_fair_lp.saturating_add(affected_fair_lp);
 
         // Update primary user position: shares = fair LP
+        let mut primary_delta = 0;
         if let Some(mut p_pos) = primary_pos {
+            primary_delta = primary_fair_lp.saturating_sub(p_pos.share_ratio);
             p_pos.share_ratio = primary_fair_lp;
             env.storage().persistent().set(&primary_key, &p_pos);
         }
 
         // Update affected user position: shares = fair LP
+        let mut affected_delta = 0;
         if let Some(mut a_pos) = affected_pos {
+            affected_delta = affected_fair_lp.saturating_sub(a_pos.share_ratio);
             a_pos.share_ratio = affected_fair_lp;
             env.storage().persistent().set(&affected_key, &a_pos);
         }
@@ -3193,6 +4347,8 @@ impl StakingRegistry {
         env.storage()
             .persistent()
             .set(&DataKey::VaultTotalShares(pool_id), &new_total_shares);
+        Self::track_vault_shares(&env, pool_id, &primary_user, primary_delta);
+        Self::track_vault_shares(&env, pool_id, &affected_user, affected_delta);
         env.storage()
             .persistent()
             .set(&DataKey::PoolInfo(pool_id), &pool_info);
@@ -3226,11 +4382,7 @@ impl StakingRegistry {
             return Err(Error::Unauthorized);
         }
 
-        let mut pool_info: PoolInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
 
         let pos_key = DataKey::UserVaultPosition(user.clone(), pool_id);
         let mut user_position: UserVaultPosition = env
@@ -3239,6 +4391,7 @@ impl StakingRegistry {
             .get(&pos_key)
             .ok_or(Error::PositionNotFound)?;
 
+        Self::checkpoint_vault_rewards(&env, &user, pool_id);
         let old_shares = user_position.share_ratio;
         let share_delta = new_shares - old_shares;
 
@@ -3262,6 +4415,7 @@ impl StakingRegistry {
         env.storage()
             .persistent()
             .set(&DataKey::VaultTotalShares(pool_id), &new_total_shares);
+        Self::track_vault_shares(&env, pool_id, &user, share_delta);
 
         env.events().publish(
             (symbol_short!("adj_pos"), user, pool_id),
@@ -3274,7 +4428,7 @@ impl StakingRegistry {
     /// Returns the current config version.
     /// Transfers admin role to a new address (e.g. multisig cold wallet).
     ///
-    /// Updates both `AdminAddress` (upgrade key) and `Config.admin`.
+    /// Updates the stable `AdminAddress` key, which `get_config` reports as `admin`.
     /// After this call, the old admin has no special authority.
     ///
     /// # Authorization
@@ -3289,33 +4443,25 @@ impl StakingRegistry {
             return Err(Error::Unauthorized);
         }
 
-        // Update stable AdminAddress key
+        // AdminAddress is the single source of truth for the admin (v1.9.0+)
         env.storage().instance().set(&DataKey::AdminAddress, &new_admin);
 
-        // Update Config.admin
-        if let Ok(mut cfg) = Self::get_config(env.clone()) {
-            cfg.admin = new_admin.clone();
-            env.storage().instance().set(&DataKey::Config, &cfg);
-        }
-
         env.events().publish((symbol_short!("adm_xfer"),), new_admin);
         Ok(())
     }
 
-    /// For old config: returns the old version number
-    /// For new config: returns encoded version (10100 = v1.1.0)
+    /// Returns the encoded config version (10900 = v1.9.0).
+    /// Before `migrate_v1_9_0` has run, reports the version from the legacy Config entry.
     pub fn get_version(env: Env) -> Result<u32, Error> {
-        // Try new config first
-        if let Ok(cfg) = Self::get_config(env.clone()) {
-            return Ok(cfg.version);
+        if let Some(version) = env.storage().instance().get::<ConfigKey, u32>(&ConfigKey::ConfigVersion) {
+            return Ok(version);
         }
 
-        // Fall back to old config
-        let old_cfg: OldConfig = env.storage().instance()
+        let legacy: LegacyConfig = env.storage().instance()
             .get(&DataKey::Config)
             .ok_or(Error::NotInitialized)?;
 
-        Ok(old_cfg.version)
+        Ok(legacy.version)
     }
 
     /// Test function to validate staking calculations without executing transactions.
@@ -3354,7 +4500,7 @@ impl StakingRegistry {
     /// * `Ok((i128, i128))` - A tuple of (available_aqua, available_blub)
     /// * `Err(Error)` if unable to retrieve state
     pub fn get_available_pol_balance(env: Env) -> Result<(i128, i128), Error> {
-        let cfg = Self::get_config(env.clone())?;
+        let cfg = Self::read_token_config(&env)?;
         let contract_address = env.current_contract_address();
         
         use soroban_sdk::token;
@@ -3472,20 +4618,15 @@ impl StakingRegistry {
         admin: Address,
         period_unit_minutes: u64,
     ) -> Result<(), Error> {
-        admin.require_auth();
-
-        let mut config = Self::get_config(env.clone())?;
-        
-        if config.admin != admin {
-            return Err(Error::Unauthorized);
-        }
+        Self::require_admin_auth(&env, &admin)?;
 
         if period_unit_minutes == 0 {
             return Err(Error::InvalidPeriod);
         }
 
-        config.period_unit_minutes = period_unit_minutes;
-        env.storage().instance().set(&DataKey::Config, &config);
+        let mut periods = Self::read_period_config(&env);
+        periods.period_unit_minutes = period_unit_minutes;
+        Self::write_period_config(&env, &periods);
 
         env.events().publish(
             (symbol_short!("period_up"),),
@@ -3603,7 +4744,8 @@ impl StakingRegistry {
 
         let now = env.ledger().timestamp();
         let contract_address = env.current_contract_address();
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
+        let cooldowns = Self::read_cooldown_config(&env);
 
         let user_locks: Vec<Bytes> = env
             .storage()
@@ -3630,7 +4772,7 @@ impl StakingRegistry {
             if let Some(tx_hash) = user_locks.get(i) {
                 if let Some(mut entry) = env.storage().persistent().get::<DataKey, LockEntry>(&DataKey::UserLockByTxHash(user.clone(), tx_hash.clone())) {
                     // Check cooldown: lock_timestamp + unstake_cooldown_seconds <= now
-                    let cooldown_end = entry.lock_timestamp.saturating_add(config.unstake_cooldown_seconds);
+                    let cooldown_end = entry.lock_timestamp.saturating_add(cooldowns.unstake_cooldown_seconds);
                     let cooldown_passed = now >= cooldown_end;
 
                     if entry.blub_locked > 0 && cooldown_passed {
@@ -3671,6 +4813,14 @@ impl StakingRegistry {
         // Transfer unstaked BLUB to user
         // Rewards are claimed separately via claim_rewards() (Synthetix system)
         if total_blub_unstaked > 0 {
+            let mut ledger = Self::read_token_ledger(&env);
+            if let Err(e) = Self::debit_bucket(&mut ledger.blub_principal, total_blub_unstaked) {
+                global_state.locked = false;
+                env.storage().instance().set(&DataKey::GlobalState, &global_state);
+                return Err(e);
+            }
+            Self::write_token_ledger(&env, &ledger);
+
             use soroban_sdk::token;
             let blub_client = token::Client::new(&env, &config.blub_token);
             let transfer_result = blub_client.try_transfer(&contract_address, &user, &total_blub_unstaked);
@@ -3798,7 +4948,9 @@ impl StakingRegistry {
             .saturating_mul(reward_delta)
             / REWARD_PRECISION;
 
-        user_state.rewards_earned.saturating_add(new_rewards)
+        // reward_delta is negative only after an emergency reset lowered the
+        // accumulator below the user's checkpoint; never report negative rewards.
+        user_state.rewards_earned.saturating_add(new_rewards).max(0)
     }
 
     /// Internal: Checkpoint a user's rewards before any balance change
@@ -3836,6 +4988,7 @@ impl StakingRegistry {
         old_balance: i128,
     ) {
         let mut user_state = Self::get_user_reward_state(env, user);
+        let stored_balance = user_state.staked_balance;
 
         // Update global total_staked
         reward_state.total_staked = reward_state
@@ -3845,6 +4998,9 @@ impl StakingRegistry {
 
         // Update user's staked balance
         user_state.staked_balance = new_balance;
+        if new_balance < old_balance {
+            Self::cap_ice_vote(env, user, new_balance);
+        }
 
         // Save both states
         env.storage()
@@ -3853,6 +5009,45 @@ impl StakingRegistry {
         env.storage()
             .persistent()
             .set(&DataKey::UserRewardStateV2(user.clone()), &user_state);
+        Self::track_staked_balance(env, user, new_balance.saturating_sub(stored_balance));
+    }
+
+    /// Internal: Get the per-purpose token ledger.
+    /// Before migrate_v1_9_0 seeds it, principal and reward reserve are
+    /// estimated from the reward state and the other buckets are empty.
+    fn read_token_ledger(env: &Env) -> TokenLedger {
+        env.storage()
+            .instance()
+            .get(&LedgerKey::TokenLedger)
+            .unwrap_or_else(|| {
+                let reward_state = Self::get_reward_state(env);
+                TokenLedger {
+                    blub_principal: reward_state.total_staked,
+                    blub_reward_reserve: reward_state
+                        .total_rewards_added
+                        .saturating_sub(reward_state.total_rewards_claimed)
+                        .max(0),
+                    blub_pol_in_transit: 0,
+                    blub_vault_rewards: 0,
+                    blub_vault_idle: 0,
+                    aqua_revenue: 0,
+                    aqua_vault_idle: 0,
+                }
+            })
+    }
+
+    /// Internal: Store the per-purpose token ledger
+    fn write_token_ledger(env: &Env, ledger: &TokenLedger) {
+        env.storage().instance().set(&LedgerKey::TokenLedger, ledger);
+    }
+
+    /// Internal: Take `amount` out of a ledger bucket, failing if it holds less
+    fn debit_bucket(bucket: &mut i128, amount: i128) -> Result<(), Error> {
+        if amount > *bucket {
+            return Err(Error::InsufficientBucketBalance);
+        }
+        *bucket -= amount;
+        Ok(())
     }
 
     /// Backend calls this to add BLUB rewards to the pool
@@ -3865,7 +5060,7 @@ impl StakingRegistry {
     /// # Authorization
     /// Requires admin authorization
     pub fn add_rewards(env: Env, manager: Address, amount: i128) -> Result<(), Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         Self::require_manager_auth(&env, &manager)?;
 
         if amount <= 0 {
@@ -3902,6 +5097,10 @@ impl StakingRegistry {
                 .saturating_add(reward_per_token_increase);
         }
 
+        let mut ledger = Self::read_token_ledger(&env);
+        ledger.blub_reward_reserve = ledger.blub_reward_reserve.saturating_add(amount);
+        Self::write_token_ledger(&env, &ledger);
+
         reward_state.total_rewards_added = reward_state
             .total_rewards_added
             .saturating_add(amount);
@@ -3927,13 +5126,18 @@ impl StakingRegistry {
     ///
     /// Call this when `add_rewards` was accidentally called with a bad amount
     /// (e.g. the BLUB issuer's sentinel balance i64::MAX). The function:
-    ///   1. Transfers all BLUB currently held by the contract back to `admin`.
+    ///   1. Burns the part of the reward reserve that is no longer owed once
+    ///      `total_rewards_added` is corrected. Staked principal is never touched:
+    ///      the burn is capped at the BLUB balance above `total_staked`.
     ///   2. Resets `reward_per_token_stored` to `correct_reward_per_token`.
     ///   3. Resets `total_rewards_added` to `correct_total_rewards_added`.
     ///
-    /// Per-user states (reward_per_token_paid, rewards_earned) are NOT modified.
-    /// Users who had already checkpointed against the corrupted accumulator will
-    /// see 0 pending rewards until new legitimate rewards are added (safe outcome).
+    /// Per-user states are corrected by `recheckpoint_rewards`; until then any
+    /// user who checkpointed against the corrupted accumulator is credited
+    /// lazily at their next checkpoint (never below zero).
+    ///
+    /// # Returns
+    /// * `Ok(i128)` - Amount of BLUB burned from the reward reserve
     ///
     /// # Authorization
     /// Requires admin (multisig) auth.
@@ -3953,21 +5157,42 @@ impl StakingRegistry {
             return Err(Error::Unauthorized);
         }
 
-        let config = Self::get_config(env.clone())?;
+        if correct_reward_per_token < 0 || correct_total_rewards_added < 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let config = Self::read_token_config(&env)?;
         let contract_address = env.current_contract_address();
+        let mut reward_state = Self::get_reward_state(&env);
 
         use soroban_sdk::token;
         let blub_client = token::Client::new(&env, &config.blub_token);
         let blub_balance = blub_client.balance(&contract_address);
 
+        // Rewards still owed to users after the correction stay in the reserve
+        let mut ledger = Self::read_token_ledger(&env);
+        let reserve = ledger.blub_reward_reserve;
+        let still_owed = correct_total_rewards_added
+            .saturating_sub(reward_state.total_rewards_claimed)
+            .max(0);
+        // Only BLUB no other bucket claims can leave the contract
+        let free_blub = blub_balance
+            .saturating_sub(ledger.blub_principal.max(reward_state.total_staked))
+            .saturating_sub(ledger.blub_pol_in_transit)
+            .saturating_sub(ledger.blub_vault_rewards)
+            .saturating_sub(ledger.blub_vault_idle)
+            .max(0);
+        let burn_amount = reserve.saturating_sub(still_owed).max(0).min(free_blub);
+
         // Burn BLUB directly from the contract — no trustline required on any account.
         // The contract self-authorizes the burn since it is the token holder.
-        if blub_balance > 0 {
-            blub_client.burn(&contract_address, &blub_balance);
+        if burn_amount > 0 {
+            blub_client.burn(&contract_address, &burn_amount);
         }
+        Self::debit_bucket(&mut ledger.blub_reward_reserve, burn_amount)?;
+        Self::write_token_ledger(&env, &ledger);
 
         // Reset the global reward accumulator to the correct pre-corruption values
-        let mut reward_state = Self::get_reward_state(&env);
         reward_state.reward_per_token_stored = correct_reward_per_token;
         reward_state.total_rewards_added = correct_total_rewards_added;
         reward_state.last_update_time = env.ledger().timestamp();
@@ -3978,10 +5203,55 @@ impl StakingRegistry {
 
         env.events().publish(
             (symbol_short!("emrg_rst"),),
-            blub_balance,
+            (burn_amount, reserve, still_owed),
+        );
+
+        Ok(burn_amount)
+    }
+
+    /// Re-checkpoints a batch of registered stakers against the current accumulator.
+    ///
+    /// Run over the whole staker registry after `admin_emergency_reset_rewards`.
+    /// For each user, `rewards_earned` is recomputed (removing anything accrued
+    /// above the corrected accumulator, floored at zero) and
+    /// `reward_per_token_paid` is set to the current `reward_per_token_stored`.
+    /// Idempotent, so batches can be retried safely.
+    ///
+    /// # Arguments
+    /// * `manager` - Manager address for authorization
+    /// * `start` - First staker registry index
+    /// * `limit` - Maximum number of stakers to process
+    ///
+    /// # Returns
+    /// * `Ok(u32)` - Number of stakers re-checkpointed
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn recheckpoint_rewards(env: Env, manager: Address, start: u32, limit: u32) -> Result<u32, Error> {
+        Self::require_manager_auth(&env, &manager)?;
+
+        let reward_state = Self::get_reward_state(&env);
+        let count: u32 = env.storage().instance().get(&LedgerKey::StakerCount).unwrap_or(0);
+        let end = start.saturating_add(limit).min(count);
+
+        let mut processed: u32 = 0;
+        for i in start..end {
+            if let Some(user) = env
+                .storage()
+                .persistent()
+                .get::<LedgerKey, Address>(&LedgerKey::StakerByIndex(i))
+            {
+                Self::checkpoint_user_internal(&env, &user, &reward_state);
+                processed += 1;
+            }
+        }
+
+        env.events().publish(
+            (symbol_short!("rwd_rchk"),),
+            (start, processed, reward_state.reward_per_token_stored),
         );
 
-        Ok(blub_balance)
+        Ok(processed)
     }
 
     /// Accepts AQUA protocol revenue and mints equivalent BLUB as staker rewards.
@@ -4007,7 +5277,7 @@ impl StakingRegistry {
         aqua_amount: i128,
         blub_reward_amount: i128,
     ) -> Result<(), Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         Self::require_manager_auth(&env, &manager)?;
 
         if aqua_amount <= 0 || blub_reward_amount <= 0 {
@@ -4054,6 +5324,11 @@ impl StakingRegistry {
                 .saturating_add(reward_per_token_increase);
         }
 
+        let mut ledger = Self::read_token_ledger(&env);
+        ledger.blub_reward_reserve = ledger.blub_reward_reserve.saturating_add(blub_reward_amount);
+        ledger.aqua_revenue = ledger.aqua_revenue.saturating_add(aqua_amount);
+        Self::write_token_ledger(&env, &ledger);
+
         reward_state.total_rewards_added = reward_state
             .total_rewards_added
             .saturating_add(blub_reward_amount);
@@ -4097,7 +5372,8 @@ impl StakingRegistry {
     pub fn claim_rewards(env: Env, user: Address) -> Result<i128, Error> {
         user.require_auth();
 
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
+        let cooldowns = Self::read_cooldown_config(&env);
         let now = env.ledger().timestamp();
 
         // Get current states (with migration fix for pre-migration stakers)
@@ -4107,7 +5383,7 @@ impl StakingRegistry {
         // Check cooldown
         let cooldown_end = user_state
             .last_claim_time
-            .saturating_add(config.claim_reward_cooldown_seconds);
+            .saturating_add(cooldowns.claim_reward_cooldown_seconds);
         if now < cooldown_end && user_state.last_claim_time > 0 {
             return Err(Error::ClaimCooldownActive);
         }
@@ -4125,6 +5401,11 @@ impl StakingRegistry {
         user_state.last_claim_time = now;
         user_state.total_claimed = user_state.total_claimed.saturating_add(pending);
 
+        // Claims are paid from the reward reserve only, never from principal
+        let mut ledger = Self::read_token_ledger(&env);
+        Self::debit_bucket(&mut ledger.blub_reward_reserve, pending)?;
+        Self::write_token_ledger(&env, &ledger);
+
         // Update global claimed amount
         let mut reward_state_mut = reward_state.clone();
         reward_state_mut.total_rewards_claimed = reward_state_mut
@@ -4173,6 +5454,54 @@ impl StakingRegistry {
         Self::calculate_user_pending_rewards(&reward_state, &user_state)
     }
 
+    /// View function: Get the BLUB reward ledger
+    ///
+    /// # Returns
+    /// (staked_principal, reward_reserve)
+    pub fn get_reward_ledger(env: Env) -> (i128, i128) {
+        let ledger = Self::read_token_ledger(&env);
+        (ledger.blub_principal, ledger.blub_reward_reserve)
+    }
+
+    /// View function: Get every BLUB/AQUA bucket next to the actual balances
+    ///
+    /// # Returns
+    /// TokenLedgerReport; a negative unallocated amount means the buckets
+    /// claim more than the contract holds.
+    pub fn get_token_ledger(env: Env) -> Result<TokenLedgerReport, Error> {
+        let config = Self::read_token_config(&env)?;
+        let global_state = Self::get_global_state(env.clone())?;
+        let ledger = Self::read_token_ledger(&env);
+        let contract_address = env.current_contract_address();
+
+        use soroban_sdk::token;
+        let blub_balance = token::Client::new(&env, &config.blub_token).balance(&contract_address);
+        let aqua_balance = token::Client::new(&env, &config.aqua_token).balance(&contract_address);
+
+        let blub_allocated = ledger
+            .blub_principal
+            .saturating_add(ledger.blub_reward_reserve)
+            .saturating_add(ledger.blub_pol_in_transit)
+            .saturating_add(ledger.blub_vault_rewards)
+            .saturating_add(ledger.blub_vault_idle);
+        let aqua_authorized_ice = Self::read_authorized_ice_aqua(&env);
+        let aqua_allocated = global_state
+            .pending_aqua_for_ice
+            .saturating_add(aqua_authorized_ice)
+            .saturating_add(ledger.aqua_revenue)
+            .saturating_add(ledger.aqua_vault_idle);
+
+        Ok(TokenLedgerReport {
+            ledger,
+            aqua_pending_ice: global_state.pending_aqua_for_ice,
+            aqua_authorized_ice,
+            blub_balance,
+            aqua_balance,
+            blub_unallocated: blub_balance.saturating_sub(blub_allocated),
+            aqua_unallocated: aqua_balance.saturating_sub(aqua_allocated),
+        })
+    }
+
     /// View function: Get comprehensive reward info for a user
     ///
     /// # Arguments
@@ -4181,25 +5510,7 @@ impl StakingRegistry {
     /// # Returns
     /// UserRewardInfo with pending, claimed, balance, and cooldown status
     pub fn get_user_reward_info(env: Env, user: Address) -> UserRewardInfo {
-        let config = Self::get_config(env.clone()).unwrap_or(Config {
-            admin: user.clone(),
-            version: 0,
-            total_supply: 0,
-            treasury_address: user.clone(),
-            reward_rate: 0,
-            aqua_token: user.clone(),
-            blub_token: user.clone(),
-            liquidity_contract: user.clone(),
-            ice_token: user.clone(),
-            govern_ice_token: user.clone(),
-            upvote_ice_token: user.clone(),
-            downvote_ice_token: user.clone(),
-            period_unit_minutes: 1,
-            vault_treasury: user.clone(),
-            vault_fee_bps: 0,
-            unstake_cooldown_seconds: 864000,
-            claim_reward_cooldown_seconds: 604800,
-        });
+        let cooldowns = Self::read_cooldown_config(&env);
 
         let now = env.ledger().timestamp();
         let reward_state = Self::get_reward_state(&env);
@@ -4208,7 +5519,7 @@ impl StakingRegistry {
         let pending = Self::calculate_user_pending_rewards(&reward_state, &user_state);
         let claim_available_at = user_state
             .last_claim_time
-            .saturating_add(config.claim_reward_cooldown_seconds);
+            .saturating_add(cooldowns.claim_reward_cooldown_seconds);
         let can_claim = now >= claim_available_at || user_state.last_claim_time == 0;
 
         UserRewardInfo {
@@ -4230,25 +5541,7 @@ impl StakingRegistry {
     /// # Returns
     /// UnstakeStatus with availability info
     pub fn get_unstake_status(env: Env, user: Address, lock_index: u32) -> UnstakeStatus {
-        let config = Self::get_config(env.clone()).unwrap_or(Config {
-            admin: user.clone(),
-            version: 0,
-            total_supply: 0,
-            treasury_address: user.clone(),
-            reward_rate: 0,
-            aqua_token: user.clone(),
-            blub_token: user.clone(),
-            liquidity_contract: user.clone(),
-            ice_token: user.clone(),
-            govern_ice_token: user.clone(),
-            upvote_ice_token: user.clone(),
-            downvote_ice_token: user.clone(),
-            period_unit_minutes: 1,
-            vault_treasury: user.clone(),
-            vault_fee_bps: 0,
-            unstake_cooldown_seconds: 864000,
-            claim_reward_cooldown_seconds: 604800,
-        });
+        let cooldowns = Self::read_cooldown_config(&env);
 
         let now = env.ledger().timestamp();
 
@@ -4266,7 +5559,7 @@ impl StakingRegistry {
             {
                 let unstake_available_at = entry
                     .lock_timestamp
-                    .saturating_add(config.unstake_cooldown_seconds);
+                    .saturating_add(cooldowns.unstake_cooldown_seconds);
                 let can_unstake = now >= unstake_available_at && entry.blub_locked > 0;
 
                 return UnstakeStatus {
@@ -4299,15 +5592,11 @@ impl StakingRegistry {
         admin: Address,
         cooldown_seconds: u64,
     ) -> Result<(), Error> {
-        admin.require_auth();
-
-        let mut config = Self::get_config(env.clone())?;
-        if config.admin != admin {
-            return Err(Error::Unauthorized);
-        }
+        Self::require_admin_auth(&env, &admin)?;
 
-        config.unstake_cooldown_seconds = cooldown_seconds;
-        env.storage().instance().set(&DataKey::Config, &config);
+        let mut cooldowns = Self::read_cooldown_config(&env);
+        cooldowns.unstake_cooldown_seconds = cooldown_seconds;
+        Self::write_cooldown_config(&env, &cooldowns);
 
         env.events().publish(
             (symbol_short!("cfg_upd"),),
@@ -4327,15 +5616,11 @@ impl StakingRegistry {
         admin: Address,
         cooldown_seconds: u64,
     ) -> Result<(), Error> {
-        admin.require_auth();
-
-        let mut config = Self::get_config(env.clone())?;
-        if config.admin != admin {
-            return Err(Error::Unauthorized);
-        }
+        Self::require_admin_auth(&env, &admin)?;
 
-        config.claim_reward_cooldown_seconds = cooldown_seconds;
-        env.storage().instance().set(&DataKey::Config, &config);
+        let mut cooldowns = Self::read_cooldown_config(&env);
+        cooldowns.claim_reward_cooldown_seconds = cooldown_seconds;
+        Self::write_cooldown_config(&env, &cooldowns);
 
         env.events().publish(
             (symbol_short!("cfg_upd"),),
@@ -4389,7 +5674,7 @@ impl StakingRegistry {
     /// # Authorization
     /// Requires admin authorization
     pub fn setup_ice_trustlines(env: Env, manager: Address) -> Result<(), Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_ice_tokens(&env)?;
         Self::require_manager_auth(&env, &manager)?;
 
         use soroban_sdk::token;
@@ -4417,9 +5702,69 @@ impl StakingRegistry {
         Ok(())
     }
 
+    /// Internal: Load an ICE lock, decoding the pre-v1.9.0 layout if needed.
+    /// Older locks have no AQUA reserved; transferred ones count as Confirmed
+    /// with no ICE attributed, since their ICE was synced before states existed.
+    fn load_ice_lock(env: &Env, lock_id: u64) -> Result<IceLockAuthorization, Error> {
+        use soroban_sdk::{Map, TryFromVal, Val};
+
+        let raw: Map<Symbol, Val> = env
+            .storage()
+            .persistent()
+            .get(&DataKey::IceLockAuth(lock_id))
+            .ok_or(Error::NotFound)?;
+
+        if raw.contains_key(Symbol::new(env, "state")) {
+            return IceLockAuthorization::try_from_val(env, &raw.to_val()).map_err(|_| Error::InvalidInput);
+        }
+
+        let legacy = LegacyIceLockAuthorization::try_from_val(env, &raw.to_val()).map_err(|_| Error::InvalidInput)?;
+        Ok(IceLockAuthorization {
+            lock_id: legacy.lock_id,
+            aqua_amount: legacy.aqua_amount,
+            duration_years: legacy.duration_years,
+            authorized_at: legacy.authorized_at,
+            executed: legacy.executed,
+            state: if legacy.executed { IceLockState::Confirmed } else { IceLockState::Authorized },
+            reserved_aqua: 0,
+            transferred_at: 0,
+            // The transfer time was not recorded; authorization time is the closest estimate
+            unlocks_at: if legacy.executed {
+                legacy.authorized_at.saturating_add(legacy.duration_years.saturating_mul(ICE_LOCK_YEAR_SECONDS))
+            } else {
+                0
+            },
+            confirmed_at: 0,
+            ice_received: Self::no_ice(),
+            closed_at: 0,
+        })
+    }
+
+    /// Internal: Zero ICE amounts
+    fn no_ice() -> IceAmounts {
+        IceAmounts {
+            ice: 0,
+            govern_ice: 0,
+            upvote_ice: 0,
+            downvote_ice: 0,
+        }
+    }
+
+    /// Internal: AQUA reserved by Authorized ICE locks
+    fn read_authorized_ice_aqua(env: &Env) -> i128 {
+        env.storage().instance().get(&IceKey::AuthorizedAqua).unwrap_or(0)
+    }
+
+    fn write_authorized_ice_aqua(env: &Env, amount: i128) {
+        env.storage().instance().set(&IceKey::AuthorizedAqua, &amount);
+    }
+
     /// Authorizes an ICE lock for a specific amount and duration.
     /// Backend cron will execute the actual locking on Stellar Classic.
     ///
+    /// The amount is reserved out of `pending_aqua_for_ice` until the lock is
+    /// transferred or cancelled.
+    ///
     /// # Arguments
     /// * `aqua_amount` - Amount of AQUA to lock for ICE
     /// * `duration_years` - Lock duration (1-5 years)
@@ -4435,7 +5780,7 @@ impl StakingRegistry {
         aqua_amount: i128,
         duration_years: u64,
     ) -> Result<u64, Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_token_config(&env)?;
         Self::require_manager_auth(&env, &manager)?;
 
         if aqua_amount <= 0 || duration_years == 0 || duration_years > 5 {
@@ -4452,8 +5797,23 @@ impl StakingRegistry {
             return Err(Error::InsufficientPendingAqua);
         }
 
+        global_state.pending_aqua_for_ice -= aqua_amount;
+        let lock_id = Self::create_ice_lock(&env, &mut global_state, aqua_amount, duration_years);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::GlobalState, &global_state);
+
+        Ok(lock_id)
+    }
+
+    /// Internal: Store a new Authorized ICE lock reserving `aqua_amount`, which
+    /// the caller has already taken out of `pending_aqua_for_ice` or received.
+    /// The caller saves `global_state`.
+    fn create_ice_lock(env: &Env, global_state: &mut GlobalState, aqua_amount: i128, duration_years: u64) -> u64 {
         let lock_id = global_state.ice_lock_counter;
         global_state.ice_lock_counter += 1;
+        Self::write_authorized_ice_aqua(env, Self::read_authorized_ice_aqua(env).saturating_add(aqua_amount));
 
         let authorization = IceLockAuthorization {
             lock_id,
@@ -4461,46 +5821,111 @@ impl StakingRegistry {
             duration_years,
             authorized_at: env.ledger().timestamp(),
             executed: false,
+            state: IceLockState::Authorized,
+            reserved_aqua: aqua_amount,
+            transferred_at: 0,
+            unlocks_at: 0,
+            confirmed_at: 0,
+            ice_received: Self::no_ice(),
+            closed_at: 0,
         };
 
         env.storage()
             .persistent()
             .set(&DataKey::IceLockAuth(lock_id), &authorization);
 
-        env.storage()
-            .instance()
-            .set(&DataKey::GlobalState, &global_state);
-
         env.events().publish(
             (symbol_short!("ice_auth"), lock_id),
             (aqua_amount, duration_years),
         );
 
-        Ok(lock_id)
+        lock_id
+    }
+
+    /// Internal: Open ICE locks unlocking on `day`, soonest first
+    fn read_ice_maturity_day(env: &Env, day: u64) -> Vec<IceMaturity> {
+        env.storage()
+            .persistent()
+            .get(&IceKey::MaturityDay(day))
+            .unwrap_or(Vec::new(env))
+    }
+
+    /// Internal: Number of open locks in the maturity ledger
+    fn read_ice_maturity_count(env: &Env) -> u32 {
+        env.storage().instance().get(&IceKey::MaturityCount).unwrap_or(0)
+    }
+
+    /// Internal: Add a transferred lock to its unlock day's bucket (no-op if present)
+    fn add_ice_maturity(env: &Env, lock: &IceLockAuthorization) {
+        let day = lock.unlocks_at / 86400;
+        let mut maturities = Self::read_ice_maturity_day(env, day);
+        if maturities.iter().any(|m| m.lock_id == lock.lock_id) {
+            return;
+        }
+        let at = maturities
+            .iter()
+            .position(|m| m.unlocks_at > lock.unlocks_at)
+            .unwrap_or(maturities.len() as usize) as u32;
+        maturities.insert(
+            at,
+            IceMaturity {
+                lock_id: lock.lock_id,
+                aqua_amount: lock.aqua_amount,
+                unlocks_at: lock.unlocks_at,
+            },
+        );
+        env.storage().persistent().set(&IceKey::MaturityDay(day), &maturities);
+
+        env.storage()
+            .instance()
+            .set(&IceKey::MaturityCount, &Self::read_ice_maturity_count(env).saturating_add(1));
+        let earliest: u64 = env.storage().instance().get(&IceKey::EarliestMaturityDay).unwrap_or(u64::MAX);
+        if day < earliest {
+            env.storage().instance().set(&IceKey::EarliestMaturityDay, &day);
+        }
+    }
+
+    /// Internal: Drop a closed lock from its unlock day's bucket
+    fn remove_ice_maturity(env: &Env, lock: &IceLockAuthorization) {
+        let day = lock.unlocks_at / 86400;
+        let mut maturities = Self::read_ice_maturity_day(env, day);
+        if let Some(at) = maturities.iter().position(|m| m.lock_id == lock.lock_id) {
+            maturities.remove(at as u32);
+            if maturities.is_empty() {
+                env.storage().persistent().remove(&IceKey::MaturityDay(day));
+            } else {
+                env.storage().persistent().set(&IceKey::MaturityDay(day), &maturities);
+            }
+            env.storage()
+                .instance()
+                .set(&IceKey::MaturityCount, &Self::read_ice_maturity_count(env).saturating_sub(1));
+        }
     }
 
     /// Transfers authorized AQUA from contract to admin for ICE locking.
     /// Backend calls this after authorization to move AQUA to admin wallet,
     /// then creates claimable balance on Stellar Classic.
     ///
+    /// The lock moves to Transferred; once its ICE has arrived and been
+    /// synced, the manager confirms it with `confirm_ice_lock`.
+    ///
     /// # Arguments
     /// * `lock_id` - The authorization ID
     ///
     /// # Authorization
     /// Requires admin authorization
     pub fn transfer_authorized_aqua(env: Env, manager: Address, lock_id: u64) -> Result<(), Error> {
-        let config = Self::get_config(env.clone())?; // needed for aqua_token address
+        let config = Self::read_token_config(&env)?; // needed for aqua_token address
         Self::require_manager_auth(&env, &manager)?;
 
-        let mut authorization: IceLockAuthorization = env
-            .storage()
-            .persistent()
-            .get(&DataKey::IceLockAuth(lock_id))
-            .ok_or(Error::NotFound)?;
+        let mut authorization = Self::load_ice_lock(&env, lock_id)?;
 
         if authorization.executed {
             return Err(Error::AlreadyExecuted);
         }
+        if authorization.state != IceLockState::Authorized {
+            return Err(Error::InvalidIceLockState);
+        }
 
         let mut global_state: GlobalState = env
             .storage()
@@ -4508,7 +5933,8 @@ impl StakingRegistry {
             .get(&DataKey::GlobalState)
             .ok_or(Error::NotInitialized)?;
 
-        if global_state.pending_aqua_for_ice < authorization.aqua_amount {
+        // Locks authorized before v1.9.0 reserved nothing and still draw on pending AQUA
+        if authorization.reserved_aqua == 0 && global_state.pending_aqua_for_ice < authorization.aqua_amount {
             return Err(Error::InsufficientPendingAqua);
         }
 
@@ -4524,10 +5950,24 @@ impl StakingRegistry {
         );
 
         // Update state
-        global_state.pending_aqua_for_ice = global_state
-            .pending_aqua_for_ice
-            .saturating_sub(authorization.aqua_amount);
+        if authorization.reserved_aqua > 0 {
+            Self::write_authorized_ice_aqua(
+                &env,
+                Self::read_authorized_ice_aqua(&env).saturating_sub(authorization.reserved_aqua),
+            );
+        } else {
+            global_state.pending_aqua_for_ice = global_state
+                .pending_aqua_for_ice
+                .saturating_sub(authorization.aqua_amount);
+        }
         authorization.executed = true;
+        authorization.state = IceLockState::Transferred;
+        authorization.reserved_aqua = 0;
+        authorization.transferred_at = env.ledger().timestamp();
+        authorization.unlocks_at = authorization
+            .transferred_at
+            .saturating_add(authorization.duration_years.saturating_mul(ICE_LOCK_YEAR_SECONDS));
+        Self::add_ice_maturity(&env, &authorization);
 
         env.storage()
             .persistent()
@@ -4547,10 +5987,13 @@ impl StakingRegistry {
     /// Syncs all ICE token balances from SAC contracts.
     /// Backend calls this after ICE tokens are received.
     ///
+    /// Any growth since the last sync is added to the unattributed ICE, which
+    /// `confirm_ice_lock` then assigns to the Transferred locks it came from.
+    ///
     /// # Authorization
     /// Requires admin authorization
     pub fn sync_all_ice_balances(env: Env, manager: Address) -> Result<(), Error> {
-        let config = Self::get_config(env.clone())?;
+        let config = Self::read_ice_tokens(&env)?;
         Self::require_manager_auth(&env, &manager)?;
 
         use soroban_sdk::token;
@@ -4574,6 +6017,13 @@ impl StakingRegistry {
             .get(&DataKey::GlobalState)
             .ok_or(Error::NotInitialized)?;
 
+        let received = IceAmounts {
+            ice: ice_balance.saturating_sub(global_state.ice_balance).max(0),
+            govern_ice: govern_ice_balance.saturating_sub(global_state.govern_ice_balance).max(0),
+            upvote_ice: upvote_ice_balance.saturating_sub(global_state.upvote_ice_balance).max(0),
+            downvote_ice: downvote_ice_balance.saturating_sub(global_state.downvote_ice_balance).max(0),
+        };
+
         global_state.ice_balance = ice_balance;
         global_state.govern_ice_balance = govern_ice_balance;
         global_state.upvote_ice_balance = upvote_ice_balance;
@@ -4583,6 +6033,24 @@ impl StakingRegistry {
             .instance()
             .set(&DataKey::GlobalState, &global_state);
 
+        if received != Self::no_ice() {
+            let mut unattributed = Self::read_unattributed_ice(&env);
+            unattributed.ice = unattributed.ice.saturating_add(received.ice);
+            unattributed.govern_ice = unattributed.govern_ice.saturating_add(received.govern_ice);
+            unattributed.upvote_ice = unattributed.upvote_ice.saturating_add(received.upvote_ice);
+            unattributed.downvote_ice = unattributed.downvote_ice.saturating_add(received.downvote_ice);
+            env.storage().instance().set(&IceKey::UnattributedIce, &unattributed);
+        }
+
+        // Fixed vote amounts may no longer fit after upvoteICE went down
+        let votes = Self::resolve_vote_targets(&env, &Self::read_vote_targets(&env), upvote_ice_balance);
+        if !votes.within_balance {
+            env.events().publish(
+                (symbol_short!("vote_ovr"),),
+                (votes.total_allocated, upvote_ice_balance),
+            );
+        }
+
         env.events().publish(
             (symbol_short!("ice_sync"),),
             (ice_balance, govern_ice_balance, upvote_ice_balance, downvote_ice_balance),
@@ -4591,1077 +6059,4672 @@ impl StakingRegistry {
         Ok(())
     }
 
-    // ============================================================================
-    // VAULT FUNCTIONS (Request 2 - Boost Farming)
-    // ============================================================================
+    /// Internal: ICE synced in but not yet attributed to a lock
+    fn read_unattributed_ice(env: &Env) -> IceAmounts {
+        env.storage()
+            .instance()
+            .get(&IceKey::UnattributedIce)
+            .unwrap_or(Self::no_ice())
+    }
 
-    /// Adds a new pool to the vault (max 10 pools).
+    /// Confirms a Transferred ICE lock with the ICE its locking produced.
+    ///
+    /// Aquarius mints ICE by lock duration, so only the manager, who made the
+    /// Classic lock, knows each lock's amounts. They must already be synced in
+    /// by `sync_all_ice_balances` and are taken out of the unattributed ICE,
+    /// so no more ICE can be attributed than has arrived.
     ///
     /// # Arguments
-    /// * `pool_address` - Aquarius pool contract address
-    /// * `token_a` - First token in the pair
-    /// * `token_b` - Second token in the pair
-    /// * `share_token` - LP token address
+    /// * `manager` - Manager address
+    /// * `lock_id` - A Transferred lock
+    /// * `received` - ICE of each type the lock produced (at least one non-zero)
+    ///
+    /// # Returns
+    /// * `Err(InvalidIceLockState)` if the lock is not Transferred
+    /// * `Err(InsufficientBalance)` if any amount exceeds the unattributed ICE
     ///
     /// # Authorization
-    /// Requires admin authorization
-    pub fn add_pool(
-        env: Env,
-        manager: Address,
-        pool_address: Address,
-        token_a: Address,
-        token_b: Address,
-        share_token: Address,
-    ) -> Result<u32, Error> {
+    /// Requires manager authorization
+    pub fn confirm_ice_lock(env: Env, manager: Address, lock_id: u64, received: IceAmounts) -> Result<(), Error> {
         Self::require_manager_auth(&env, &manager)?;
 
-        let mut global_state: GlobalState = env
-            .storage()
-            .instance()
-            .get(&DataKey::GlobalState)
-            .ok_or(Error::NotInitialized)?;
-
-        if global_state.pool_count >= 10 {
-            return Err(Error::MaxPoolsReached);
+        let mut lock = Self::load_ice_lock(&env, lock_id)?;
+        if lock.state != IceLockState::Transferred {
+            return Err(Error::InvalidIceLockState);
+        }
+        if received.ice < 0
+            || received.govern_ice < 0
+            || received.upvote_ice < 0
+            || received.downvote_ice < 0
+            || received == Self::no_ice()
+        {
+            return Err(Error::InvalidInput);
         }
 
-        let pool_id = global_state.pool_count;
-        global_state.pool_count += 1;
-
-        let pool_info = PoolInfo {
-            pool_id,
-            pool_address: pool_address.clone(),
-            token_a: token_a.clone(),
-            token_b: token_b.clone(),
-            share_token: share_token.clone(),
-            total_lp_tokens: 0,
-            active: true,
-            added_at: env.ledger().timestamp(),
-        };
+        let mut unattributed = Self::read_unattributed_ice(&env);
+        if received.ice > unattributed.ice
+            || received.govern_ice > unattributed.govern_ice
+            || received.upvote_ice > unattributed.upvote_ice
+            || received.downvote_ice > unattributed.downvote_ice
+        {
+            return Err(Error::InsufficientBalance);
+        }
+        unattributed.ice -= received.ice;
+        unattributed.govern_ice -= received.govern_ice;
+        unattributed.upvote_ice -= received.upvote_ice;
+        unattributed.downvote_ice -= received.downvote_ice;
+        if unattributed == Self::no_ice() {
+            env.storage().instance().remove(&IceKey::UnattributedIce);
+        } else {
+            env.storage().instance().set(&IceKey::UnattributedIce, &unattributed);
+        }
 
+        lock.state = IceLockState::Confirmed;
+        lock.confirmed_at = env.ledger().timestamp();
+        lock.ice_received = received;
         env.storage()
             .persistent()
-            .set(&DataKey::PoolInfo(pool_id), &pool_info);
-
-        env.storage()
-            .instance()
-            .set(&DataKey::GlobalState, &global_state);
+            .set(&DataKey::IceLockAuth(lock_id), &lock);
 
         env.events().publish(
-            (symbol_short!("pool_add"), pool_id),
-            (pool_address, token_a, token_b),
+            (symbol_short!("ice_conf"), lock_id),
+            lock.ice_received,
         );
 
-        Ok(pool_id)
+        Ok(())
     }
 
-    /// Updates a pool's active status.
+    /// Cancels an ICE lock that was authorized but never transferred, returning
+    /// its reserved AQUA to `pending_aqua_for_ice`. The lock becomes Expired.
     ///
-    /// # Authorization
-    /// Requires admin authorization
-    pub fn update_pool_status(env: Env, pool_id: u32, active: bool) -> Result<(), Error> {
-        let config = Self::get_config(env.clone())?;
-        config.admin.require_auth();
+    /// The manager can cancel at any time; anyone can once the authorization
+    /// is older than `ICE_AUTH_STALE_SECONDS`.
+    ///
+    /// # Arguments
+    /// * `caller` - Manager, or anyone for a stale authorization
+    /// * `lock_id` - The authorization ID
+    ///
+    /// # Returns
+    /// * `Ok(i128)` - AQUA returned to `pending_aqua_for_ice`
+    /// * `Err(InvalidIceLockState)` if the lock is no longer Authorized
+    pub fn cancel_ice_lock(env: Env, caller: Address, lock_id: u64) -> Result<i128, Error> {
+        let mut authorization = Self::load_ice_lock(&env, lock_id)?;
+        let now = env.ledger().timestamp();
+        if now >= authorization.authorized_at.saturating_add(ICE_AUTH_STALE_SECONDS) {
+            caller.require_auth();
+        } else {
+            Self::require_manager_auth(&env, &caller)?;
+        }
+
+        if authorization.state != IceLockState::Authorized {
+            return Err(Error::InvalidIceLockState);
+        }
 
-        let mut pool_info: PoolInfo = env
+        let mut global_state: GlobalState = env
             .storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
 
-        pool_info.active = active;
+        let returned = authorization.reserved_aqua;
+        global_state.pending_aqua_for_ice = global_state.pending_aqua_for_ice.saturating_add(returned);
+        Self::write_authorized_ice_aqua(&env, Self::read_authorized_ice_aqua(&env).saturating_sub(returned));
+
+        authorization.state = IceLockState::Expired;
+        authorization.reserved_aqua = 0;
+        authorization.closed_at = now;
 
         env.storage()
             .persistent()
-            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+            .set(&DataKey::IceLockAuth(lock_id), &authorization);
+        env.storage()
+            .instance()
+            .set(&DataKey::GlobalState, &global_state);
 
         env.events().publish(
-            (symbol_short!("pool_upd"), pool_id),
-            active,
+            (symbol_short!("ice_cncl"), lock_id),
+            (caller, returned),
         );
 
-        Ok(())
+        Ok(returned)
     }
 
-    /// Deposits tokens to a vault pool.
-    /// User deposits token_a + token_b, contract adds liquidity to Aquarius pool.
+    /// Records that a Confirmed ICE lock's term has ended and its AQUA was
+    /// released on Stellar Classic. The lock becomes Unlocked.
     ///
     /// # Arguments
-    /// * `user` - User address
-    /// * `pool_id` - Pool ID
-    /// * `desired_a` - Amount of token_a to deposit
-    /// * `desired_b` - Amount of token_b to deposit
-    /// * `min_shares` - Minimum LP shares to receive (slippage protection)
+    /// * `manager` - Manager address for authorization
+    /// * `lock_id` - The authorization ID
+    ///
+    /// The released AQUA stays with the manager; use `renew_ice_lock` instead
+    /// to lock it again.
+    ///
+    /// # Returns
+    /// * `Err(InvalidIceLockState)` if the lock is not Confirmed
+    /// * `Err(UnlockNotReady)` before the lock's `unlocks_at`
     ///
     /// # Authorization
-    /// Requires user authorization
-    pub fn vault_deposit(
-        env: Env,
-        user: Address,
-        pool_id: u32,
-        desired_a: i128,
-        desired_b: i128,
-        min_shares: u128,
-    ) -> Result<(), Error> {
-        user.require_auth();
+    /// Requires manager authorization
+    pub fn record_ice_unlock(env: Env, manager: Address, lock_id: u64) -> Result<(), Error> {
+        Self::require_manager_auth(&env, &manager)?;
 
-        if desired_a <= 0 || desired_b <= 0 {
-            return Err(Error::InvalidInput);
+        let authorization = Self::close_matured_ice_lock(&env, lock_id)?;
+
+        env.events().publish(
+            (symbol_short!("ice_unlk"), lock_id),
+            authorization.aqua_amount,
+        );
+
+        Ok(())
+    }
+
+    /// Internal: Mark a matured Confirmed lock Unlocked and drop it from the
+    /// maturity ledger
+    fn close_matured_ice_lock(env: &Env, lock_id: u64) -> Result<IceLockAuthorization, Error> {
+        let mut authorization = Self::load_ice_lock(env, lock_id)?;
+        if authorization.state != IceLockState::Confirmed {
+            return Err(Error::InvalidIceLockState);
         }
 
-        let mut pool_info: PoolInfo = env
-            .storage()
+        let now = env.ledger().timestamp();
+        if now < authorization.unlocks_at {
+            return Err(Error::UnlockNotReady);
+        }
+
+        authorization.state = IceLockState::Unlocked;
+        authorization.closed_at = now;
+        env.storage()
             .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
+            .set(&DataKey::IceLockAuth(lock_id), &authorization);
+        Self::remove_ice_maturity(env, &authorization);
 
-        if !pool_info.active {
-            return Err(Error::PoolNotActive);
+        Ok(authorization)
+    }
+
+    /// Sets how `renew_ice_lock` splits matured AQUA across lock durations.
+    ///
+    /// # Arguments
+    /// * `admin` - Admin address for authorization
+    /// * `policy` - Five bps entries for 1-5 year locks, summing to 10000
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn set_ice_renewal_policy(env: Env, admin: Address, policy: IceRenewalPolicy) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+
+        // Each rung is at most 10000, so the sum of five cannot overflow
+        if policy.ladder_bps.len() != 5
+            || policy.ladder_bps.iter().any(|bps| bps > 10000)
+            || policy.ladder_bps.iter().sum::<u32>() != 10000
+        {
+            return Err(Error::InvalidInput);
         }
 
-        let contract_address = env.current_contract_address();
+        env.storage().instance().set(&IceKey::RenewalPolicy, &policy);
+        env.events().publish((symbol_short!("ice_pol"),), policy.ladder_bps);
+        Ok(())
+    }
+
+    /// Renews a matured ICE lock. The manager returns the AQUA released on
+    /// Stellar Classic to the contract, the lock becomes Unlocked and the AQUA
+    /// is re-authorized into new locks split by the renewal policy. The new
+    /// locks follow the usual transfer and confirmation flow.
+    ///
+    /// # Arguments
+    /// * `manager` - Manager address; pays back the lock's `aqua_amount`
+    /// * `lock_id` - The matured lock
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u64>)` - IDs of the new locks, shortest duration first
+    /// * `Err(IceRenewalNotConfigured)` without a renewal policy
+    /// * `Err(UnlockNotReady)` before the lock's `unlocks_at`
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn renew_ice_lock(env: Env, manager: Address, lock_id: u64) -> Result<Vec<u64>, Error> {
+        let config = Self::read_token_config(&env)?;
+        Self::require_manager_auth(&env, &manager)?;
+
+        let policy: IceRenewalPolicy = env
+            .storage()
+            .instance()
+            .get(&IceKey::RenewalPolicy)
+            .ok_or(Error::IceRenewalNotConfigured)?;
+
+        let authorization = Self::close_matured_ice_lock(&env, lock_id)?;
+        let amount = authorization.aqua_amount;
 
-        // STEP 1: Transfer full desired amounts from user to contract.
-        // We transfer the exact function parameters so auth entries are
-        // deterministic and won't break if pool reserves shift between
-        // simulation and execution.
         use soroban_sdk::token;
-        let token_a_client = token::Client::new(&env, &pool_info.token_a);
-        let token_b_client = token::Client::new(&env, &pool_info.token_b);
+        if token::Client::new(&env, &config.aqua_token)
+            .try_transfer(&manager, &env.current_contract_address(), &amount)
+            .is_err()
+        {
+            return Err(Error::InsufficientBalance);
+        }
+
+        let mut global_state: GlobalState = env
+            .storage()
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
 
-        token_a_client.transfer(&user, &contract_address, &desired_a);
-        token_b_client.transfer(&user, &contract_address, &desired_b);
+        // Split by the ladder; the longest funded rung takes the rounding remainder
+        let longest = policy.ladder_bps.iter().rposition(|bps| bps > 0).unwrap_or(0) as u32;
+        let mut left = amount;
+        let mut new_locks = Vec::new(&env);
+        for (i, bps) in policy.ladder_bps.iter().enumerate() {
+            let rung = if i as u32 == longest {
+                left
+            } else {
+                amount.checked_mul(bps as i128).unwrap_or(0) / 10000
+            };
+            if rung > 0 {
+                left -= rung;
+                new_locks.push_back(Self::create_ice_lock(&env, &mut global_state, rung, i as u64 + 1));
+            }
+            if i as u32 == longest {
+                break;
+            }
+        }
 
-        // STEP 2: Query pool reserves and token order from Aquarius pool.
-        let aquarius_pool = AquariusPoolClient::new(&env, &pool_info.pool_address);
-        let reserves = aquarius_pool.get_reserves();
+        env.storage()
+            .instance()
+            .set(&DataKey::GlobalState, &global_state);
 
-        // Determine Aquarius pool token ordering so reserves, auth entries, and
-        // deposit amounts all match what the pool expects.
-        // token_a_is_idx0 = true  → Aquarius token[0]=token_a, token[1]=token_b
-        // token_a_is_idx0 = false → Aquarius token[0]=token_b, token[1]=token_a
-        let pool_tokens_result = env.try_invoke_contract::<Vec<Address>, soroban_sdk::Error>(
-            &pool_info.pool_address,
-            &Symbol::new(&env, "get_tokens"),
-            ().into_val(&env),
+        env.events().publish(
+            (symbol_short!("ice_renew"), lock_id),
+            (amount, new_locks.clone()),
         );
-        let token_a_is_idx0 = match pool_tokens_result {
-            Ok(Ok(ref tokens)) if tokens.len() >= 2 => {
-                tokens.get(0).unwrap() == pool_info.token_a
-            }
-            _ => true, // fallback: assume aligned
-        };
 
-        // Map reserves to token_a / token_b using Aquarius ordering.
-        let (r_a, r_b): (u128, u128) = if reserves.len() >= 2 {
-            let r0 = reserves.get(0).unwrap();
-            let r1 = reserves.get(1).unwrap();
-            if token_a_is_idx0 { (r0, r1) } else { (r1, r0) }
-        } else {
-            (0, 0)
-        };
+        Ok(new_locks)
+    }
 
-        let (deposit_a, deposit_b): (i128, i128) = if r_a > 0 && r_b > 0 {
-            // Pool has liquidity – calculate proportional amounts.
-            let optimal_b = (desired_a as u128)
-                .checked_mul(r_b)
-                .unwrap_or(0)
-                .checked_div(r_a)
-                .unwrap_or(0);
+    /// Adds locks transferred before v1.9.0 to the maturity ledger; later
+    /// locks are added automatically on transfer. Locks that are not
+    /// Transferred or Confirmed are skipped.
+    ///
+    /// # Returns
+    /// * `Ok(u32)` - Number of locks in the maturity ledger
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn register_ice_maturities(env: Env, manager: Address, lock_ids: Vec<u64>) -> Result<u32, Error> {
+        Self::require_manager_auth(&env, &manager)?;
 
-            if optimal_b <= desired_b as u128 {
-                (desired_a, optimal_b as i128)
-            } else {
-                let optimal_a = (desired_b as u128)
-                    .checked_mul(r_a)
-                    .unwrap_or(0)
-                    .checked_div(r_b)
-                    .unwrap_or(0);
-                (optimal_a as i128, desired_b)
+        for lock_id in lock_ids.iter() {
+            let lock = Self::load_ice_lock(&env, lock_id)?;
+            if lock.state == IceLockState::Transferred || lock.state == IceLockState::Confirmed {
+                Self::add_ice_maturity(&env, &lock);
             }
-        } else {
-            (desired_a, desired_b)
-        };
+        }
 
-        if deposit_a <= 0 || deposit_b <= 0 {
-            // Refund everything before failing
-            token_a_client.transfer(&contract_address, &user, &desired_a);
-            token_b_client.transfer(&contract_address, &user, &desired_b);
-            return Err(Error::InvalidInput);
+        let count = Self::read_ice_maturity_count(&env);
+        env.events().publish((symbol_short!("ice_mreg"),), count);
+        Ok(count)
+    }
+
+    // ============================================================================
+    // ICE VOTE ALLOCATION
+    // ============================================================================
+
+    /// Sets the vote operator address (admin-only).
+    ///
+    /// The vote operator maintains the ICE vote allocation registry. Until an
+    /// operator is set the manager (or admin) acts as operator.
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn set_vote_operator(env: Env, admin: Address, operator: Address) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+        env.storage().instance().set(&IceKey::VoteOperator, &operator);
+        env.events().publish((symbol_short!("set_vop"),), operator);
+        Ok(())
+    }
+
+    /// Returns the vote operator address, or None if not set.
+    pub fn get_vote_operator_address(env: Env) -> Option<Address> {
+        env.storage().instance().get(&IceKey::VoteOperator)
+    }
+
+    /// Internal: Require vote operator authorization (manager or admin if no operator is set)
+    fn require_vote_operator_auth(env: &Env, operator: &Address) -> Result<(), Error> {
+        match env.storage().instance().get::<IceKey, Address>(&IceKey::VoteOperator) {
+            Some(stored) => {
+                operator.require_auth();
+                if stored != *operator {
+                    return Err(Error::Unauthorized);
+                }
+                Ok(())
+            }
+            None => Self::require_manager_auth(env, operator),
         }
+    }
 
-        // STEP 3: Authorize token transfers in the order Aquarius will call them.
-        let (auth_token_0, auth_amount_0, auth_token_1, auth_amount_1) = if token_a_is_idx0 {
-            (pool_info.token_a.clone(), deposit_a, pool_info.token_b.clone(), deposit_b)
-        } else {
-            (pool_info.token_b.clone(), deposit_b, pool_info.token_a.clone(), deposit_a)
-        };
-        let auth_entries = soroban_sdk::vec![
-            &env,
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: auth_token_0,
-                    fn_name: Symbol::new(&env, "transfer"),
-                    args: (
-                        contract_address.clone(),
-                        pool_info.pool_address.clone(),
-                        auth_amount_0,
-                    ).into_val(&env),
-                },
-                sub_invocations: soroban_sdk::vec![&env],
-            }),
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: auth_token_1,
-                    fn_name: Symbol::new(&env, "transfer"),
-                    args: (
-                        contract_address.clone(),
-                        pool_info.pool_address.clone(),
-                        auth_amount_1,
-                    ).into_val(&env),
-                },
-                sub_invocations: soroban_sdk::vec![&env],
-            }),
-        ];
-        env.authorize_as_current_contract(auth_entries);
+    /// Internal: The stored vote allocation registry
+    fn read_vote_targets(env: &Env) -> Vec<IceVoteTarget> {
+        env.storage()
+            .instance()
+            .get(&IceKey::VoteAllocations)
+            .unwrap_or(Vec::new(env))
+    }
 
-        // STEP 4: Deposit tokens to Aquarius pool in Aquarius token order.
-        let mut deposit_amounts = Vec::new(&env);
-        if token_a_is_idx0 {
-            deposit_amounts.push_back(deposit_a as u128);
-            deposit_amounts.push_back(deposit_b as u128);
-        } else {
-            deposit_amounts.push_back(deposit_b as u128);
-            deposit_amounts.push_back(deposit_a as u128);
+    /// Internal: Resolve vote targets into upvoteICE amounts
+    fn resolve_vote_targets(env: &Env, targets: &Vec<IceVoteTarget>, upvote_ice_balance: i128) -> IceVoteReport {
+        let mut allocations = Vec::new(env);
+        let mut total_allocated = 0i128;
+        for target in targets.iter() {
+            let ice_amount = match target.weight {
+                IceVoteWeight::Amount(amount) => amount,
+                IceVoteWeight::Bps(bps) => upvote_ice_balance.checked_mul(bps as i128).unwrap_or(0) / 10000,
+            };
+            total_allocated = total_allocated.saturating_add(ice_amount);
+            allocations.push_back(IceVoteAllocation {
+                pool: target.pool,
+                weight: target.weight,
+                ice_amount,
+            });
         }
 
-        let (_actual_amounts, lp_shares_minted) = aquarius_pool.deposit(
-            &contract_address,
-            &deposit_amounts,
-            &min_shares,
-        );
+        IceVoteReport {
+            allocations,
+            total_allocated,
+            upvote_ice_balance,
+            within_balance: total_allocated <= upvote_ice_balance,
+        }
+    }
 
-        // STEP 5: Refund excess tokens back to user
-        let refund_a = desired_a - deposit_a;
-        let refund_b = desired_b - deposit_b;
-        if refund_a > 0 {
-            token_a_client.transfer(&contract_address, &user, &refund_a);
+    /// Internal: Validate and store the vote allocation registry.
+    /// Each pool appears once with a positive weight, bps total at most 10000,
+    /// and the resolved total must fit the synced `upvote_ice_balance`.
+    fn write_vote_targets(env: &Env, targets: &Vec<IceVoteTarget>) -> Result<IceVoteReport, Error> {
+        let mut total_bps = 0u32;
+        for (i, target) in targets.iter().enumerate() {
+            match target.weight {
+                IceVoteWeight::Amount(amount) if amount > 0 => {}
+                IceVoteWeight::Bps(bps) if bps > 0 && bps <= 10000 => total_bps += bps,
+                _ => return Err(Error::InvalidInput),
+            }
+            if targets.iter().skip(i + 1).any(|other| other.pool == target.pool) {
+                return Err(Error::InvalidInput);
+            }
         }
-        if refund_b > 0 {
-            token_b_client.transfer(&contract_address, &user, &refund_b);
+        if total_bps > 10000 {
+            return Err(Error::IceVotesOverallocated);
         }
 
-        // STEP 7: Mint vault shares proportional to LP deposited.
-        // Vault share model: shares represent proportional ownership.
-        // When compounds add LP, total_lp grows but shares stay → each share worth more.
-        let lp_minted = lp_shares_minted as i128;
-        let old_total_lp = pool_info.total_lp_tokens;
-        let total_shares: i128 = env
+        let global_state: GlobalState = env
             .storage()
-            .persistent()
-            .get(&DataKey::VaultTotalShares(pool_id))
-            .unwrap_or(0);
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
+        let report = Self::resolve_vote_targets(env, targets, global_state.upvote_ice_balance);
+        if !report.within_balance {
+            return Err(Error::IceVotesOverallocated);
+        }
 
-        let shares_to_mint = if old_total_lp == 0 || total_shares == 0 {
-            // First deposit: 1 share = 1 LP
-            lp_minted
-        } else {
-            // Proportional: new_shares = lp_minted * total_shares / total_lp
-            lp_minted
-                .checked_mul(total_shares)
-                .unwrap_or(0)
-                .checked_div(old_total_lp)
-                .unwrap_or(0)
-        };
+        env.storage().instance().set(&IceKey::VoteAllocations, targets);
+        Ok(report)
+    }
 
-        pool_info.total_lp_tokens = old_total_lp.saturating_add(lp_minted);
+    /// Replaces the ICE vote allocation registry: how the contract's upvoteICE
+    /// is split across Aquarius pools. The backend casts votes from this
+    /// registry.
+    ///
+    /// # Arguments
+    /// * `operator` - Vote operator address for authorization
+    /// * `targets` - Pool and weight (fixed amount or bps of upvoteICE) per pool;
+    ///   an empty list clears the registry
+    ///
+    /// # Returns
+    /// * `Ok(IceVoteReport)` - The registry resolved against `upvote_ice_balance`
+    /// * `Err(IceVotesOverallocated)` if the total exceeds the synced upvoteICE balance
+    ///
+    /// # Authorization
+    /// Requires vote operator authorization
+    pub fn set_vote_allocations(env: Env, operator: Address, targets: Vec<IceVoteTarget>) -> Result<IceVoteReport, Error> {
+        Self::require_vote_operator_auth(&env, &operator)?;
 
-        // STEP 8: Get or create user position
-        let mut user_position: UserVaultPosition = env
-            .storage()
-            .persistent()
-            .get(&DataKey::UserVaultPosition(user.clone(), pool_id))
-            .unwrap_or(UserVaultPosition {
-                user: user.clone(),
-                pool_id,
-                share_ratio: 0,
-                deposited_at: env.ledger().timestamp(),
-                active: true,
-            });
+        let report = Self::write_vote_targets(&env, &targets)?;
 
-        // STEP 9: Add minted shares to user and global total
-        user_position.share_ratio = user_position.share_ratio.saturating_add(shares_to_mint);
-        user_position.active = true;
+        env.events().publish(
+            (symbol_short!("vote_set"),),
+            (operator, report.allocations.clone(), report.total_allocated, report.upvote_ice_balance),
+        );
 
-        let new_total_shares = total_shares.saturating_add(shares_to_mint);
+        Ok(report)
+    }
 
+    /// Sets the Aquarius pools BLUB stakers can direct ICE votes to.
+    ///
+    /// # Arguments
+    /// * `operator` - Vote operator address for authorization
+    /// * `pools` - Whitelisted pools (at most `ICE_VOTE_MAX_POOLS`, no duplicates)
+    ///
+    /// # Authorization
+    /// Requires vote operator authorization
+    pub fn set_vote_pools(env: Env, operator: Address, pools: Vec<Address>) -> Result<(), Error> {
+        Self::require_vote_operator_auth(&env, &operator)?;
+
+        if pools.len() > ICE_VOTE_MAX_POOLS {
+            return Err(Error::InvalidInput);
+        }
+        for (i, pool) in pools.iter().enumerate() {
+            if pools.iter().skip(i + 1).any(|other| other == pool) {
+                return Err(Error::InvalidInput);
+            }
+        }
+
+        env.storage().instance().set(&IceKey::VotePools, &pools);
+        env.events().publish((symbol_short!("vote_pool"),), pools);
+        Ok(())
+    }
+
+    /// Internal: Pools BLUB stakers can vote on
+    fn read_vote_pools(env: &Env) -> Vec<Address> {
         env.storage()
-            .persistent()
-            .set(&DataKey::PoolInfo(pool_id), &pool_info);
-        env.storage()
-            .persistent()
-            .set(&DataKey::UserVaultPosition(user.clone(), pool_id), &user_position);
-        env.storage()
-            .persistent()
-            .set(&DataKey::VaultTotalShares(pool_id), &new_total_shares);
+            .instance()
+            .get(&IceKey::VotePools)
+            .unwrap_or(Vec::new(env))
+    }
 
-        // Track user's deposited LP (excludes compound gains)
-        let prev_deposited: i128 = env
-            .storage()
-            .persistent()
-            .get(&DataKey::UserDepositedLp(user.clone(), pool_id))
-            .unwrap_or(0);
+    /// Internal: An epoch's tally
+    fn read_vote_tally(env: &Env, epoch: u64) -> Vec<PoolVoteTally> {
         env.storage()
             .persistent()
-            .set(&DataKey::UserDepositedLp(user.clone(), pool_id), &prev_deposited.saturating_add(lp_minted));
+            .get(&IceKey::VoteTally(epoch))
+            .unwrap_or(Vec::new(env))
+    }
+
+    /// Internal: Add `sign * weight * bps / 10000` to each voted pool's tally
+    fn apply_ice_votes(tally: &mut Vec<PoolVoteTally>, ballot: &UserIceVote, sign: i128) {
+        for vote in ballot.votes.iter() {
+            let delta = ballot.weight.checked_mul(vote.bps as i128).unwrap_or(0) / 10000 * sign;
+            match tally.iter().position(|entry| entry.pool == vote.pool) {
+                Some(at) => {
+                    let mut entry = tally.get(at as u32).unwrap();
+                    entry.weight = entry.weight.saturating_add(delta).max(0);
+                    tally.set(at as u32, entry);
+                }
+                None if delta > 0 => tally.push_back(PoolVoteTally {
+                    pool: vote.pool.clone(),
+                    weight: delta,
+                }),
+                None => {}
+            }
+        }
+    }
+
+    /// Internal: Lower a staker's current-epoch ballot to `staked_balance`
+    /// after their stake drops, so unstaked BLUB cannot be voted again from
+    /// another account in the same epoch. A ballot left without weight is removed.
+    fn cap_ice_vote(env: &Env, user: &Address, staked_balance: i128) {
+        let epoch = env.ledger().timestamp() / ICE_VOTE_EPOCH_SECONDS;
+        let ballot_key = IceKey::UserVote(epoch, user.clone());
+        let mut ballot = match env.storage().persistent().get::<IceKey, UserIceVote>(&ballot_key) {
+            Some(ballot) if ballot.weight > staked_balance => ballot,
+            _ => return,
+        };
+
+        let mut tally = Self::read_vote_tally(env, epoch);
+        Self::apply_ice_votes(&mut tally, &ballot, -1);
+        ballot.weight = staked_balance.max(0);
+        if ballot.weight > 0 {
+            Self::apply_ice_votes(&mut tally, &ballot, 1);
+            env.storage().persistent().set(&ballot_key, &ballot);
+        } else {
+            env.storage().persistent().remove(&ballot_key);
+        }
+        env.storage().persistent().set(&IceKey::VoteTally(epoch), &tally);
 
         env.events().publish(
-            (symbol_short!("vault_dep"), user.clone(), pool_id),
-            (deposit_a, deposit_b, lp_shares_minted, user_position.share_ratio),
+            (symbol_short!("vote_cap"), user.clone(), epoch),
+            ballot.weight,
         );
-
-        Ok(())
     }
 
-    /// Single-asset vault deposit.
-    /// Deposits a single token into an Aquarius pool. The AMM handles the
-    /// internal swap to balance the deposit across both pool tokens.
+    /// Casts (or replaces) a BLUB staker's ICE vote for the current epoch.
+    ///
+    /// The staker's weight is their staked BLUB (`UserRewardState.staked_balance`)
+    /// when the vote is cast, lowered for the rest of the epoch whenever their
+    /// stake drops below it (staking more does not raise it; vote again to use
+    /// the new balance). Casting again in the same epoch replaces the ballot.
     ///
     /// # Arguments
-    /// * `user` - User address
-    /// * `pool_id` - Pool ID
-    /// * `token_in` - Address of the token being deposited (must be token_a or token_b of the pool)
-    /// * `amount_in` - Amount of token_in to deposit (in raw units, 7 decimals)
-    /// * `min_shares` - Minimum LP shares to receive (slippage protection)
-    pub fn vault_deposit_single(
-        env: Env,
-        user: Address,
-        pool_id: u32,
-        token_in: Address,
-        amount_in: i128,
-        min_shares: u128,
-    ) -> Result<(), Error> {
+    /// * `user` - Staker address
+    /// * `votes` - Whitelisted pools and bps of the staker's weight each (total at most 10000)
+    ///
+    /// # Returns
+    /// * `Ok(u64)` - The epoch voted in
+    /// * `Err(InsufficientBalance)` if the user has no staked BLUB
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn cast_ice_vote(env: Env, user: Address, votes: Vec<PoolVote>) -> Result<u64, Error> {
         user.require_auth();
 
-        if amount_in <= 0 {
+        let pools = Self::read_vote_pools(&env);
+        let mut total_bps = 0u32;
+        for (i, vote) in votes.iter().enumerate() {
+            if vote.bps == 0 || !pools.contains(&vote.pool) {
+                return Err(Error::InvalidInput);
+            }
+            if votes.iter().skip(i + 1).any(|other| other.pool == vote.pool) {
+                return Err(Error::InvalidInput);
+            }
+            total_bps = total_bps.saturating_add(vote.bps);
+        }
+        if votes.is_empty() || total_bps > 10000 {
             return Err(Error::InvalidInput);
         }
 
-        let mut pool_info: PoolInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
-
-        if !pool_info.active {
-            return Err(Error::PoolNotActive);
+        let weight = Self::get_user_reward_state(&env, &user).staked_balance;
+        if weight <= 0 {
+            return Err(Error::InsufficientBalance);
         }
 
-        // Verify token_in is one of the pool tokens
-        let is_token_a = token_in == pool_info.token_a;
-        let is_token_b = token_in == pool_info.token_b;
-        if !is_token_a && !is_token_b {
-            return Err(Error::InvalidInput);
+        let epoch = env.ledger().timestamp() / ICE_VOTE_EPOCH_SECONDS;
+        let ballot_key = IceKey::UserVote(epoch, user.clone());
+        let mut tally = Self::read_vote_tally(&env, epoch);
+        if let Some(previous) = env.storage().persistent().get::<IceKey, UserIceVote>(&ballot_key) {
+            Self::apply_ice_votes(&mut tally, &previous, -1);
         }
 
-        let contract_address = env.current_contract_address();
+        let ballot = UserIceVote {
+            weight,
+            votes,
+            cast_at: env.ledger().timestamp(),
+        };
+        Self::apply_ice_votes(&mut tally, &ballot, 1);
 
-        // STEP 1: Transfer input token from user to contract
-        use soroban_sdk::token;
-        let token_client = token::Client::new(&env, &token_in);
-        token_client.transfer(&user, &contract_address, &amount_in);
+        env.storage().persistent().set(&ballot_key, &ballot);
+        env.storage().persistent().set(&IceKey::VoteTally(epoch), &tally);
 
-        // STEP 2: Determine Aquarius pool token ordering
-        let pool_tokens_result = env.try_invoke_contract::<Vec<Address>, soroban_sdk::Error>(
-            &pool_info.pool_address,
-            &Symbol::new(&env, "get_tokens"),
-            ().into_val(&env),
+        env.events().publish(
+            (symbol_short!("ice_vote"), user, epoch),
+            (weight, ballot.votes),
         );
-        let token_a_is_idx0 = match pool_tokens_result {
-            Ok(Ok(ref tokens)) if tokens.len() >= 2 => {
-                tokens.get(0).unwrap() == pool_info.token_a
-            }
-            _ => true,
-        };
 
-        // STEP 3: Build deposit amounts — input token gets amount, other is 0
-        let (amount_a, amount_b): (u128, u128) = if is_token_a {
-            (amount_in as u128, 0u128)
-        } else {
-            (0u128, amount_in as u128)
-        };
+        Ok(epoch)
+    }
 
-        // STEP 4: Authorize only the non-zero token transfer to the pool
-        let auth_entries = soroban_sdk::vec![
-            &env,
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: token_in.clone(),
-                    fn_name: Symbol::new(&env, "transfer"),
-                    args: (
-                        contract_address.clone(),
-                        pool_info.pool_address.clone(),
-                        amount_in,
-                    ).into_val(&env),
-                },
-                sub_invocations: soroban_sdk::vec![&env],
-            }),
-        ];
-        env.authorize_as_current_contract(auth_entries);
+    /// Turns an ended epoch's staker tally into the ICE vote allocation
+    /// registry. Callable by anyone. Each pool still whitelisted gets bps of
+    /// upvoteICE in proportion to its voted weight (the heaviest takes the
+    /// rounding remainder), replacing the operator's registry. An epoch
+    /// without votes leaves the registry unchanged.
+    ///
+    /// Epochs are applied in order: an epoch at or before the last finalized
+    /// one cannot be finalized.
+    ///
+    /// # Arguments
+    /// * `epoch` - Epoch to finalize (timestamp / `ICE_VOTE_EPOCH_SECONDS`)
+    ///
+    /// # Returns
+    /// * `Ok(Vec<IceVoteTarget>)` - The allocation applied (empty if none)
+    /// * `Err(VoteEpochNotEnded)` if the epoch is still running
+    /// * `Err(AlreadyExecuted)` if a later or the same epoch was finalized
+    pub fn finalize_ice_vote_epoch(env: Env, epoch: u64) -> Result<Vec<IceVoteTarget>, Error> {
+        if epoch >= env.ledger().timestamp() / ICE_VOTE_EPOCH_SECONDS {
+            return Err(Error::VoteEpochNotEnded);
+        }
+        if let Some(last) = env.storage().instance().get::<IceKey, u64>(&IceKey::LastFinalizedEpoch) {
+            if epoch <= last {
+                return Err(Error::AlreadyExecuted);
+            }
+        }
+        env.storage().instance().set(&IceKey::LastFinalizedEpoch, &epoch);
+
+        let pools = Self::read_vote_pools(&env);
+        let mut tally = Vec::new(&env);
+        let mut total_weight = 0i128;
+        for entry in Self::read_vote_tally(&env, epoch).iter() {
+            if entry.weight > 0 && pools.contains(&entry.pool) {
+                total_weight = total_weight.saturating_add(entry.weight);
+                tally.push_back(entry);
+            }
+        }
 
-        // STEP 5: Deposit to Aquarius pool (single-asset — AMM handles internal swap)
-        let aquarius_pool = AquariusPoolClient::new(&env, &pool_info.pool_address);
-        let mut deposit_amounts = Vec::new(&env);
-        if token_a_is_idx0 {
-            deposit_amounts.push_back(amount_a);
-            deposit_amounts.push_back(amount_b);
-        } else {
-            deposit_amounts.push_back(amount_b);
-            deposit_amounts.push_back(amount_a);
+        let mut targets = Vec::new(&env);
+        if total_weight > 0 {
+            let mut heaviest = 0u32;
+            let mut bps_left = 10000u32;
+            let mut shares = Vec::new(&env);
+            for (i, entry) in tally.iter().enumerate() {
+                let bps = (entry.weight.checked_mul(10000).unwrap_or(0) / total_weight) as u32;
+                bps_left -= bps;
+                shares.push_back(bps);
+                if entry.weight > tally.get(heaviest).unwrap().weight {
+                    heaviest = i as u32;
+                }
+            }
+            shares.set(heaviest, shares.get(heaviest).unwrap() + bps_left);
+
+            for (entry, bps) in tally.iter().zip(shares.iter()) {
+                if bps > 0 {
+                    targets.push_back(IceVoteTarget {
+                        pool: entry.pool,
+                        weight: IceVoteWeight::Bps(bps),
+                    });
+                }
+            }
+            Self::write_vote_targets(&env, &targets)?;
         }
 
-        let (_actual_amounts, lp_shares_minted) = aquarius_pool.deposit(
-            &contract_address,
-            &deposit_amounts,
-            &min_shares,
+        env.events().publish(
+            (symbol_short!("vote_fin"), epoch),
+            (total_weight, targets.clone()),
         );
 
-        // STEP 6: Mint vault shares proportional to LP deposited
-        let lp_minted = lp_shares_minted as i128;
-        let old_total_lp = pool_info.total_lp_tokens;
-        let total_shares: i128 = env
+        Ok(targets)
+    }
+
+    // ============================================================================
+    // VAULT FUNCTIONS (Request 2 - Boost Farming)
+    // ============================================================================
+
+    /// Internal: Load a vault pool, decoding the pre-v1.9.0 layout if needed.
+    fn load_pool(env: &Env, pool_id: u32) -> Result<PoolInfo, Error> {
+        use soroban_sdk::{Map, TryFromVal, Val};
+
+        let raw: Map<Symbol, Val> = env
             .storage()
             .persistent()
-            .get(&DataKey::VaultTotalShares(pool_id))
-            .unwrap_or(0);
+            .get(&DataKey::PoolInfo(pool_id))
+            .ok_or(Error::PoolNotFound)?;
 
-        let shares_to_mint = if old_total_lp == 0 || total_shares == 0 {
-            lp_minted
-        } else {
-            lp_minted
-                .checked_mul(total_shares)
-                .unwrap_or(0)
-                .checked_div(old_total_lp)
-                .unwrap_or(0)
+        if raw.contains_key(Symbol::new(env, "deprecated")) {
+            return PoolInfo::try_from_val(env, &raw.to_val()).map_err(|_| Error::InvalidInput);
+        }
+
+        let legacy = LegacyPoolInfo::try_from_val(env, &raw.to_val()).map_err(|_| Error::InvalidInput)?;
+        Ok(PoolInfo {
+            pool_id: legacy.pool_id,
+            pool_address: legacy.pool_address,
+            tokens: soroban_sdk::vec![env, legacy.token_a.clone(), legacy.token_b.clone()],
+            token_a: legacy.token_a,
+            token_b: legacy.token_b,
+            share_token: legacy.share_token,
+            total_lp_tokens: legacy.total_lp_tokens,
+            active: legacy.active,
+            added_at: legacy.added_at,
+            deprecated: false,
+            strategy: PoolStrategy {
+                kind: StrategyKind::AquariusConstantProduct,
+                params: Bytes::new(env),
+            },
+            verified: false,
+        })
+    }
+
+    /// Internal: Require a pool to accept deposits and compounding
+    fn require_pool_open(pool_info: &PoolInfo) -> Result<(), Error> {
+        if pool_info.deprecated {
+            return Err(Error::PoolDeprecated);
+        }
+        if !pool_info.active {
+            return Err(Error::PoolNotActive);
+        }
+        Self::require_pool_verified(pool_info)
+    }
+
+    /// Internal: Require a pool's cached token order to have been checked
+    /// against the Aquarius pool (pools added before v1.9.0 need `verify_pool`)
+    fn require_pool_verified(pool_info: &PoolInfo) -> Result<(), Error> {
+        if !pool_info.verified {
+            return Err(Error::PoolNotVerified);
+        }
+        Ok(())
+    }
+
+    /// Internal: Check a pool's tokens and share token through its strategy
+    /// adapter, then store the tokens in the pool's own order and mark it verified.
+    ///
+    /// Reordering changes which token `token_a`/`amount_a`-style arguments
+    /// refer to, so it is announced with a `pool_ord` event carrying the old
+    /// and new order.
+    ///
+    /// # Returns
+    /// * `Err(Error::PoolMetadataMismatch)` if the pool does not report exactly
+    ///   `tokens` and `share_token`, or runs on a different curve
+    fn verify_pool_strategy(env: &Env, pool_info: &mut PoolInfo) -> Result<(), Error> {
+        let tokens = strategy_adapter(pool_info.strategy.kind).verify(env, pool_info)?;
+        if tokens != pool_info.tokens {
+            env.events().publish(
+                (symbol_short!("pool_ord"), pool_info.pool_id),
+                (pool_info.tokens.clone(), tokens.clone()),
+            );
+        }
+        pool_info.token_a = tokens.get(0).ok_or(Error::PoolMetadataMismatch)?;
+        pool_info.token_b = tokens.get(1).ok_or(Error::PoolMetadataMismatch)?;
+        pool_info.tokens = tokens;
+        pool_info.verified = true;
+        Ok(())
+    }
+
+    /// Internal: Require a two-token pool (zaps and migrations swap one leg into the other)
+    fn require_token_pair(pool_info: &PoolInfo) -> Result<(), Error> {
+        if pool_info.tokens.len() != 2 {
+            return Err(Error::InvalidInput);
+        }
+        Ok(())
+    }
+
+    /// Internal: Re-read the curve of an Aquarius pool, which may have changed
+    /// since the pool was stored (or was never recorded, for pre-v1.9.0 pools)
+    fn refresh_strategy_kind(env: &Env, pool_info: &mut PoolInfo) -> Result<(), Error> {
+        match pool_info.strategy.kind {
+            StrategyKind::AquariusConstantProduct | StrategyKind::AquariusStable => {
+                pool_info.strategy.kind = AquariusStrategy::detect_kind(env, &pool_info.pool_address)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds a new two-token Aquarius pool to the vault. There is no limit on the number of pools.
+    ///
+    /// The strategy (constant-product or stableswap) follows the pool's own
+    /// `pool_type`; see `add_strategy_pool` for the checks applied and for
+    /// pools of more than two tokens.
+    ///
+    /// # Arguments
+    /// * `pool_address` - Aquarius pool contract address
+    /// * `token_a` - One token of the pair (either order)
+    /// * `token_b` - The other token of the pair
+    /// * `share_token` - LP token address
+    ///
+    /// # Returns
+    /// * `Ok(pool_id)` - ID of the new pool
+    /// * `Err(Error::PoolMetadataMismatch)` if the pool does not report these tokens
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn add_pool(
+        env: Env,
+        manager: Address,
+        pool_address: Address,
+        token_a: Address,
+        token_b: Address,
+        share_token: Address,
+    ) -> Result<u32, Error> {
+        let strategy = PoolStrategy {
+            kind: AquariusStrategy::detect_kind(&env, &pool_address)?,
+            params: Bytes::new(&env),
         };
+        let tokens = soroban_sdk::vec![&env, token_a, token_b];
+        Self::add_strategy_pool(env, manager, pool_address, tokens, share_token, strategy)
+    }
 
-        pool_info.total_lp_tokens = old_total_lp.saturating_add(lp_minted);
+    /// Adds a new pool to the vault, managed through the given strategy adapter.
+    ///
+    /// The adapter checks the tokens and share token against the pool itself
+    /// (for Aquarius: `get_tokens`, `share_id` and `pool_type`), and the tokens
+    /// are stored in the pool's own order. Aquarius stableswap pools may hold
+    /// three or four tokens.
+    ///
+    /// # Arguments
+    /// * `pool_address` - AMM pool contract address
+    /// * `tokens` - All pool tokens (any order)
+    /// * `share_token` - LP token address
+    /// * `strategy` - Adapter kind and its settings
+    ///
+    /// # Returns
+    /// * `Ok(pool_id)` - ID of the new pool
+    /// * `Err(Error::PoolMetadataMismatch)` if the pool does not match
+    /// * `Err(Error::InvalidInput)` if the adapter rejects `strategy.params`
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn add_strategy_pool(
+        env: Env,
+        manager: Address,
+        pool_address: Address,
+        tokens: Vec<Address>,
+        share_token: Address,
+        strategy: PoolStrategy,
+    ) -> Result<u32, Error> {
+        Self::require_manager_auth(&env, &manager)?;
 
-        // STEP 7: Get or create user position
-        let mut user_position: UserVaultPosition = env
+        let mut global_state: GlobalState = env
             .storage()
-            .persistent()
-            .get(&DataKey::UserVaultPosition(user.clone(), pool_id))
-            .unwrap_or(UserVaultPosition {
-                user: user.clone(),
-                pool_id,
-                share_ratio: 0,
-                deposited_at: env.ledger().timestamp(),
-                active: true,
-            });
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
 
-        // STEP 8: Add minted shares to user and global total
-        user_position.share_ratio = user_position.share_ratio.saturating_add(shares_to_mint);
-        user_position.active = true;
+        let pool_id = global_state.pool_count;
+
+        let mut pool_info = PoolInfo {
+            pool_id,
+            pool_address: pool_address.clone(),
+            token_a: tokens.get(0).ok_or(Error::InvalidInput)?,
+            token_b: tokens.get(1).ok_or(Error::InvalidInput)?,
+            tokens,
+            share_token,
+            total_lp_tokens: 0,
+            active: true,
+            added_at: env.ledger().timestamp(),
+            deprecated: false,
+            strategy,
+            verified: false,
+        };
+        Self::verify_pool_strategy(&env, &mut pool_info)?;
 
-        let new_total_shares = total_shares.saturating_add(shares_to_mint);
+        global_state.pool_count += 1;
 
         env.storage()
             .persistent()
             .set(&DataKey::PoolInfo(pool_id), &pool_info);
         env.storage()
             .persistent()
-            .set(&DataKey::UserVaultPosition(user.clone(), pool_id), &user_position);
+            .set(&LedgerKey::VaultUserRegistryComplete(pool_id), &true);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::GlobalState, &global_state);
+
+        env.events().publish(
+            (symbol_short!("pool_add"), pool_id),
+            (pool_address, pool_info.tokens, pool_info.strategy.kind),
+        );
+
+        Ok(pool_id)
+    }
+
+    /// Updates a pool's active status.
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn update_pool_status(env: Env, pool_id: u32, active: bool) -> Result<(), Error> {
+        let admin = Self::read_admin(&env)?;
+        admin.require_auth();
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+
+        if active && pool_info.deprecated {
+            return Err(Error::PoolDeprecated);
+        }
+        pool_info.active = active;
+
         env.storage()
             .persistent()
-            .set(&DataKey::VaultTotalShares(pool_id), &new_total_shares);
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        env.events().publish(
+            (symbol_short!("pool_upd"), pool_id),
+            active,
+        );
+
+        Ok(())
+    }
+
+    /// Permanently retires a pool.
+    ///
+    /// A deprecated pool blocks deposits and compounding but still allows
+    /// withdrawals, and can never be re-activated.
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn deprecate_pool(env: Env, admin: Address, pool_id: u32) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+        if pool_info.deprecated {
+            return Err(Error::PoolDeprecated);
+        }
+
+        pool_info.deprecated = true;
+        pool_info.active = false;
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        env.events().publish((symbol_short!("pool_dep"), pool_id), env.ledger().timestamp());
+
+        Ok(())
+    }
+
+    /// Checks a pool's cached metadata against the pool through its strategy
+    /// adapter and records the pool's token order (and, for Aquarius, its curve).
+    ///
+    /// Pools added before v1.9.0 were stored in caller-supplied order and must
+    /// be verified once before deposits or compounding resume; withdrawals work
+    /// either way. `token_a`/`token_b` may swap to match the pool's order, in
+    /// which case a `pool_ord` event lists the old and new order.
+    ///
+    /// # Arguments
+    /// * `manager` - Manager address for authorization
+    /// * `pool_id` - Pool ID
+    ///
+    /// # Returns
+    /// * `Ok(PoolInfo)` - The updated pool
+    /// * `Err(Error::PoolMetadataMismatch)` if the pool no longer reports the stored tokens
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn verify_pool(env: Env, manager: Address, pool_id: u32) -> Result<PoolInfo, Error> {
+        Self::require_manager_auth(&env, &manager)?;
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+        Self::refresh_strategy_kind(&env, &mut pool_info)?;
+        Self::verify_pool_strategy(&env, &mut pool_info)?;
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        env.events().publish(
+            (symbol_short!("pool_vrf"), pool_id),
+            (pool_info.tokens.clone(), pool_info.strategy.kind),
+        );
+
+        Ok(pool_info)
+    }
+
+    /// Pulls all of a pool's vault LP out of Aquarius and switches the pool to
+    /// emergency exit mode.
+    ///
+    /// The withdrawn pool tokens stay in the contract as idle balances.
+    /// From then on `vault_withdraw` and `vault_redeem` pay out the pro-rata
+    /// share of those balances over the owned (non-dead) shares without
+    /// calling Aquarius; LP-based exits are refused. The pool is deprecated
+    /// and cannot be re-activated.
+    ///
+    /// Works on pools that were never verified: the LP burn does not depend on
+    /// token order, and the amounts received are measured per cached token.
+    ///
+    /// # Arguments
+    /// * `caller` - Admin or guardian address
+    /// * `pool_id` - Pool ID
+    /// * `min_amounts` - Minimum amount of each pool token to receive, in
+    ///   `PoolInfo.tokens` order; empty for no bound
+    ///
+    /// # Returns
+    /// * `Ok(PoolExitState)` - Idle balances now owed to shareholders
+    /// * `Err(Error::PoolInEmergencyExit)` if the pool has already exited
+    /// * `Err(Error::SlippageExceeded)` if a token comes back below its minimum
+    ///
+    /// # Authorization
+    /// Requires admin or guardian authorization
+    pub fn emergency_exit_pool(
+        env: Env,
+        caller: Address,
+        pool_id: u32,
+        min_amounts: Vec<u128>,
+    ) -> Result<PoolExitState, Error> {
+        Self::require_guardian_auth(&env, &caller)?;
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+        if Self::read_pool_exit(&env, pool_id).is_some() {
+            return Err(Error::PoolInEmergencyExit);
+        }
+
+        let lp_unwound = pool_info.total_lp_tokens;
+        let no_minimums = Self::no_minimums(&env, &pool_info);
+        let min_amounts = if min_amounts.is_empty() { no_minimums.clone() } else { min_amounts };
+        let withdrawn = if lp_unwound > 0 {
+            Self::unwind_vault_lp(&env, &pool_info, lp_unwound, &min_amounts)?
+        } else {
+            no_minimums
+        };
+        // The idle residual of earlier compounds is paid out with the LP
+        let residual = Self::read_idle_residual(&env, &pool_info);
+        let mut idle = Vec::new(&env);
+        for (amount, residual_amount) in withdrawn.iter().zip(residual.iter()) {
+            idle.push_back((amount as i128).saturating_add(residual_amount));
+        }
+        Self::write_idle_residual(&env, &pool_info, &Vec::new(&env));
+
+        pool_info.total_lp_tokens = 0;
+        pool_info.active = false;
+        pool_info.deprecated = true;
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        Self::track_vault_idle(&env, &pool_info.tokens, &Vec::new(&env), &idle);
+        let exit = PoolExitState {
+            idle,
+            lp_unwound,
+            exited_at: env.ledger().timestamp(),
+        };
+        env.storage()
+            .persistent()
+            .set(&PoolKey::EmergencyExit(pool_id), &exit);
+
+        env.events().publish(
+            (symbol_short!("pool_exit"), pool_id),
+            (caller, lp_unwound, exit.idle.clone()),
+        );
+
+        Ok(exit)
+    }
+
+    /// Sets a pool's deposit caps, checked on every vault deposit.
+    ///
+    /// Caps only limit new deposits; compounding may grow LP past them.
+    ///
+    /// # Arguments
+    /// * `curator` - Curator address for authorization
+    /// * `pool_id` - Pool ID
+    /// * `max_total_lp` - Max `total_lp_tokens` after a deposit (0 = no cap)
+    /// * `max_user_lp` - Max LP a single user may hold after a deposit (0 = no cap)
+    ///
+    /// # Authorization
+    /// Requires curator authorization
+    pub fn set_pool_limits(
+        env: Env,
+        curator: Address,
+        pool_id: u32,
+        max_total_lp: i128,
+        max_user_lp: i128,
+    ) -> Result<(), Error> {
+        Self::require_curator_auth(&env, &curator)?;
+
+        if max_total_lp < 0 || max_user_lp < 0 {
+            return Err(Error::InvalidInput);
+        }
+        Self::load_pool(&env, pool_id)?;
+
+        env.storage().persistent().set(
+            &PoolKey::PoolLimits(pool_id),
+            &PoolLimits {
+                max_total_lp,
+                max_user_lp,
+            },
+        );
+
+        env.events().publish(
+            (symbol_short!("pool_cap"), pool_id),
+            (max_total_lp, max_user_lp),
+        );
+
+        Ok(())
+    }
+
+    /// Sets a pool's fees and fee recipients.
+    ///
+    /// # Arguments
+    /// * `admin` - Admin address for authorization
+    /// * `pool_id` - Pool ID
+    /// * `fees` - Performance (max 5000 bps), deposit and withdraw (max 1000 bps each)
+    ///   fees, and the treasury / POL recipient split
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn set_pool_fees(env: Env, admin: Address, pool_id: u32, fees: PoolFeeConfig) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+
+        if fees.performance_fee_bps > 5000
+            || fees.deposit_fee_bps > 1000
+            || fees.withdraw_fee_bps > 1000
+            || fees.pol_share_bps > 10000
+        {
+            return Err(Error::InvalidInput);
+        }
+        Self::load_pool(&env, pool_id)?;
+
+        env.storage().persistent().set(&PoolKey::PoolFees(pool_id), &fees);
+
+        env.events().publish(
+            (symbol_short!("pool_fee"), pool_id),
+            (fees.performance_fee_bps, fees.deposit_fee_bps, fees.withdraw_fee_bps, fees.pol_share_bps),
+        );
+
+        Ok(())
+    }
+
+    /// Enables permissionless `harvest` on a two-token pool, or updates its settings.
+    ///
+    /// # Arguments
+    /// * `admin` - Admin address for authorization
+    /// * `pool_id` - Pool ID
+    /// * `config` - Minimum interval, caller bounty (max 500 bps), oracle and
+    ///   its max price age, max slippage per swap (max 1000 bps) and sell route
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn set_harvest_config(env: Env, admin: Address, pool_id: u32, config: HarvestConfig) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+
+        if config.min_interval == 0
+            || config.max_price_age == 0
+            || config.bounty_bps > 500
+            || config.max_slippage_bps > 1000
+        {
+            return Err(Error::InvalidInput);
+        }
+        if Self::load_pool(&env, pool_id)?.tokens.len() != 2 {
+            return Err(Error::InvalidInput);
+        }
+
+        env.storage().persistent().set(&PoolKey::HarvestConfig(pool_id), &config);
+
+        env.events().publish(
+            (symbol_short!("hrv_cfg"), pool_id),
+            (config.min_interval, config.bounty_bps, config.max_slippage_bps),
+        );
+
+        Ok(())
+    }
+
+    /// Points a pool at a new Aquarius pool contract after an Aquarius migration.
+    ///
+    /// The new pool must report the same tokens and `new_share_token` as its
+    /// `share_id`. The vault's LP still held in the old share token is
+    /// withdrawn from the old pool and deposited into the new one in the same
+    /// call; tokens the new pool does not take join the pool's idle residual.
+    /// `total_lp_tokens` is then reset to the vault's balance of the new share
+    /// token. Positions and shares are untouched.
+    ///
+    /// # Arguments
+    /// * `admin` - Admin address for authorization
+    /// * `pool_id` - Pool ID
+    /// * `new_pool_address` - New Aquarius pool contract address
+    /// * `new_share_token` - LP token of the new pool
+    /// * `min_lp_out` - Minimum LP the re-deposit into the new pool must mint
+    ///
+    /// # Returns
+    /// * `Ok(total_lp)` - The pool's `total_lp_tokens` after the migration
+    /// * `Err(Error::PoolInEmergencyExit)` if the pool's LP was already unwound
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    pub fn update_pool_address(
+        env: Env,
+        admin: Address,
+        pool_id: u32,
+        new_pool_address: Address,
+        new_share_token: Address,
+        min_lp_out: u128,
+    ) -> Result<i128, Error> {
+        Self::require_admin_auth(&env, &admin)?;
+
+        let old_info = Self::load_pool(&env, pool_id)?;
+        if Self::read_pool_exit(&env, pool_id).is_some() {
+            return Err(Error::PoolInEmergencyExit);
+        }
+
+        let mut pool_info = old_info.clone();
+        pool_info.pool_address = new_pool_address.clone();
+        pool_info.share_token = new_share_token.clone();
+        Self::refresh_strategy_kind(&env, &mut pool_info)?;
+        Self::verify_pool_strategy(&env, &mut pool_info)?;
+
+        use soroban_sdk::token;
+        let contract_address = env.current_contract_address();
+
+        // Move the vault's LP (not POL LP) from the old pool into the new one
+        let mut lp_migrated: i128 = 0;
+        if old_info.share_token != new_share_token {
+            let old_lp = token::Client::new(&env, &old_info.share_token)
+                .balance(&contract_address)
+                .saturating_sub(Self::pool_pol_lp(&env, &old_info)?);
+            if old_lp > 0 {
+                // Withdrawn amounts are measured in the new pool's token order
+                let mut balances_before = Vec::new(&env);
+                for token in pool_info.tokens.iter() {
+                    balances_before.push_back(token::Client::new(&env, &token).balance(&contract_address));
+                }
+                strategy_adapter(old_info.strategy.kind).withdraw(
+                    &env,
+                    &old_info,
+                    old_lp,
+                    &Self::no_minimums(&env, &old_info),
+                );
+                let mut withdrawn = Vec::new(&env);
+                for (i, token) in pool_info.tokens.iter().enumerate() {
+                    let received = token::Client::new(&env, &token).balance(&contract_address)
+                        - balances_before.get(i as u32).unwrap_or(0);
+                    withdrawn.push_back(received.max(0));
+                }
+
+                let (used, lp_minted) = Self::deposit_to_pool(&env, &pool_info, &withdrawn, min_lp_out)?;
+
+                // Carry the old residual over by token, plus whatever the new pool left
+                let old_residual = Self::read_idle_residual(&env, &old_info);
+                let mut residual = Vec::new(&env);
+                for (i, token) in pool_info.tokens.iter().enumerate() {
+                    let carried = old_info
+                        .tokens
+                        .first_index_of(&token)
+                        .and_then(|j| old_residual.get(j))
+                        .unwrap_or(0);
+                    let left = withdrawn.get(i as u32).unwrap_or(0) - used.get(i as u32).unwrap_or(0);
+                    residual.push_back(carried.saturating_add(left));
+                }
+                Self::write_idle_residual(&env, &old_info, &Vec::new(&env));
+                Self::write_idle_residual(&env, &pool_info, &residual);
+
+                lp_migrated = lp_minted as i128;
+            }
+        }
+
+        // Resync the tracked LP against the new share token
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0);
+        pool_info.total_lp_tokens = if total_shares > 0 {
+            token::Client::new(&env, &new_share_token)
+                .balance(&contract_address)
+                .saturating_sub(Self::pool_pol_lp(&env, &pool_info)?)
+                .max(0)
+        } else {
+            0
+        };
+        env.storage().persistent().remove(&LedgerKey::PoolLpDeficit(pool_id));
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        env.events().publish(
+            (symbol_short!("pool_mig"), pool_id),
+            (old_info.pool_address, new_pool_address, new_share_token, lp_migrated, pool_info.total_lp_tokens),
+        );
+
+        Ok(pool_info.total_lp_tokens)
+    }
+
+    /// Reconciles a pool's tracked LP with the contract's actual share-token balance.
+    ///
+    /// Any surplus (donations, Aquarius migrations, past compensation) is added to
+    /// `total_lp_tokens` without minting shares, so it accrues to existing
+    /// shareholders as a higher price per share. A deficit is never written off:
+    /// it is recorded under `PoolLpDeficit` and emitted so it can be investigated.
+    /// POL LP held in the same share token is excluded from both.
+    ///
+    /// # Arguments
+    /// * `manager` - Manager address for authorization
+    /// * `pool_id` - Pool ID
+    ///
+    /// # Returns
+    /// * `Ok(PoolLpSync)` - Balances before the sync and the adjustment made
+    /// * `Err(Error::PoolNotFound)` if the pool does not exist
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn sync_pool_lp(env: Env, manager: Address, pool_id: u32) -> Result<PoolLpSync, Error> {
+        Self::require_manager_auth(&env, &manager)?;
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+        if Self::read_pool_exit(&env, pool_id).is_some() {
+            return Err(Error::PoolInEmergencyExit);
+        }
+
+        use soroban_sdk::token;
+        let actual_lp = token::Client::new(&env, &pool_info.share_token)
+            .balance(&env.current_contract_address());
+        let pol_lp = Self::pool_pol_lp(&env, &pool_info)?;
+
+        let tracked_lp = pool_info.total_lp_tokens;
+        let vault_lp = actual_lp.saturating_sub(pol_lp);
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0);
+
+        let mut result = PoolLpSync {
+            pool_id,
+            tracked_lp,
+            pol_lp,
+            actual_lp,
+            surplus_credited: 0,
+            surplus_unallocated: 0,
+            deficit: 0,
+        };
+
+        if vault_lp > tracked_lp {
+            let surplus = vault_lp - tracked_lp;
+            if total_shares > 0 {
+                // Shares unchanged, LP grows → each share is worth more
+                pool_info.total_lp_tokens = vault_lp;
+                env.storage()
+                    .persistent()
+                    .set(&DataKey::PoolInfo(pool_id), &pool_info);
+                result.surplus_credited = surplus;
+            } else {
+                // Crediting an empty pool would hand the surplus to the next depositor
+                result.surplus_unallocated = surplus;
+            }
+        } else if vault_lp < tracked_lp {
+            result.deficit = tracked_lp - vault_lp;
+        }
+
+        if result.deficit > 0 {
+            env.storage()
+                .persistent()
+                .set(&LedgerKey::PoolLpDeficit(pool_id), &result.deficit);
+            env.events().publish(
+                (symbol_short!("lp_defct"), pool_id),
+                (tracked_lp, vault_lp, result.deficit),
+            );
+        } else {
+            env.storage().persistent().remove(&LedgerKey::PoolLpDeficit(pool_id));
+        }
+
+        env.events().publish(
+            (symbol_short!("lp_sync"), pool_id),
+            result.clone(),
+        );
+
+        Ok(result)
+    }
+
+    /// Internal: Mint vault shares for `lp_minted` LP just added to the pool and
+    /// credit them to `user`. Persists the pool, position and share total.
+    ///
+    /// Vault share model: shares represent proportional ownership. When compounds
+    /// add LP, total_lp grows but shares stay → each share worth more.
+    ///
+    /// The pool's deposit fee is taken from `lp_minted` first, and its
+    /// `PoolLimits` are enforced on the resulting pool and user LP.
+    ///
+    /// # Returns
+    /// * `Ok((lp_credited, share_balance))` - LP credited after fees and the user's share balance
+    /// * `Err(Error::ZeroSharesMinted)` if the deposit is worth no shares
+    /// * `Err(Error::SlippageExceeded)` if fewer than `min_vault_shares` would be minted
+    /// * `Err(Error::DepositCapExceeded)` if the deposit breaches a cap
+    fn mint_vault_shares(
+        env: &Env,
+        pool_info: &mut PoolInfo,
+        user: &Address,
+        lp_minted: i128,
+        min_vault_shares: i128,
+    ) -> Result<(i128, i128), Error> {
+        let pool_id = pool_info.pool_id;
+
+        let fees = Self::read_pool_fees(env, pool_id)?;
+        let fee_lp = Self::take_lp_fee(env, pool_info, &fees, fees.deposit_fee_bps, lp_minted);
+        if fee_lp > 0 {
+            let mut stats = Self::read_compound_stats(env, pool_id);
+            stats.total_deposit_fee_lp = stats.total_deposit_fee_lp.saturating_add(fee_lp);
+            Self::write_compound_stats(env, pool_id, &stats);
+        }
+        let lp_minted = lp_minted - fee_lp;
+        let old_total_lp = pool_info.total_lp_tokens;
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0);
+
+        let mut dead_shares = 0;
+        let shares_to_mint = if total_shares == 0 {
+            // First deposit: 1 share = 1 LP, with VAULT_DEAD_SHARES locked forever
+            dead_shares = VAULT_DEAD_SHARES;
+            lp_minted.saturating_sub(VAULT_DEAD_SHARES)
+        } else if old_total_lp == 0 {
+            lp_minted
+        } else {
+            // Proportional: new_shares = lp_minted * total_shares / total_lp
+            lp_minted
+                .checked_mul(total_shares)
+                .unwrap_or(0)
+                .checked_div(old_total_lp)
+                .unwrap_or(0)
+        };
+
+        if shares_to_mint <= 0 {
+            return Err(Error::ZeroSharesMinted);
+        }
+        if shares_to_mint < min_vault_shares {
+            return Err(Error::SlippageExceeded);
+        }
+
+        pool_info.total_lp_tokens = old_total_lp.saturating_add(lp_minted);
+
+        Self::checkpoint_vault_rewards(env, user, pool_id);
+        let mut user_position: UserVaultPosition = env
+            .storage()
+            .persistent()
+            .get(&DataKey::UserVaultPosition(user.clone(), pool_id))
+            .unwrap_or(UserVaultPosition {
+                user: user.clone(),
+                pool_id,
+                share_ratio: 0,
+                deposited_at: env.ledger().timestamp(),
+                active: true,
+            });
+
+        user_position.share_ratio = user_position.share_ratio.saturating_add(shares_to_mint);
+        user_position.active = true;
+
+        let new_total_shares = total_shares
+            .saturating_add(shares_to_mint)
+            .saturating_add(dead_shares);
+        if dead_shares > 0 {
+            env.storage()
+                .persistent()
+                .set(&PoolKey::DeadShares(pool_id), &dead_shares);
+        }
+
+        let limits = Self::read_pool_limits(env, pool_id);
+        if limits.max_total_lp > 0 && pool_info.total_lp_tokens > limits.max_total_lp {
+            return Err(Error::DepositCapExceeded);
+        }
+        if limits.max_user_lp > 0 {
+            let user_lp = user_position
+                .share_ratio
+                .checked_mul(pool_info.total_lp_tokens)
+                .unwrap_or(i128::MAX)
+                .checked_div(new_total_shares)
+                .unwrap_or(0);
+            if user_lp > limits.max_user_lp {
+                return Err(Error::DepositCapExceeded);
+            }
+        }
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), pool_info);
+        env.storage()
+            .persistent()
+            .set(&DataKey::UserVaultPosition(user.clone(), pool_id), &user_position);
+        env.storage()
+            .persistent()
+            .set(&DataKey::VaultTotalShares(pool_id), &new_total_shares);
+        Self::track_vault_shares(env, pool_id, user, shares_to_mint);
 
         // Track user's deposited LP (excludes compound gains)
         let prev_deposited: i128 = env
             .storage()
             .persistent()
-            .get(&DataKey::UserDepositedLp(user.clone(), pool_id))
+            .get(&DataKey::UserDepositedLp(user.clone(), pool_id))
+            .unwrap_or(0);
+        env.storage()
+            .persistent()
+            .set(&DataKey::UserDepositedLp(user.clone(), pool_id), &prev_deposited.saturating_add(lp_minted));
+
+        Ok((lp_minted, user_position.share_ratio))
+    }
+
+    /// Internal: Read a pool's deposit caps (none if never set)
+    fn read_pool_limits(env: &Env, pool_id: u32) -> PoolLimits {
+        env.storage()
+            .persistent()
+            .get(&PoolKey::PoolLimits(pool_id))
+            .unwrap_or(PoolLimits {
+                max_total_lp: 0,
+                max_user_lp: 0,
+            })
+    }
+
+    /// Internal: Read a pool's fee settings, defaulting to the global vault fee
+    fn read_pool_fees(env: &Env, pool_id: u32) -> Result<PoolFeeConfig, Error> {
+        if let Some(fees) = env.storage().persistent().get(&PoolKey::PoolFees(pool_id)) {
+            return Ok(fees);
+        }
+        let fees = Self::read_fee_config(env)?;
+        Ok(PoolFeeConfig {
+            performance_fee_bps: fees.vault_fee_bps,
+            deposit_fee_bps: 0,
+            withdraw_fee_bps: 0,
+            treasury: fees.vault_treasury.clone(),
+            pol_recipient: fees.vault_treasury,
+            pol_share_bps: 0,
+        })
+    }
+
+    /// Internal: Split `fee` into (treasury, pol) per the pool's `pol_share_bps`
+    fn split_pool_fee(fees: &PoolFeeConfig, fee: i128) -> (i128, i128) {
+        let pol = fee
+            .checked_mul(fees.pol_share_bps as i128)
+            .unwrap_or(0)
+            .checked_div(10000)
+            .unwrap_or(0);
+        (fee.saturating_sub(pol), pol)
+    }
+
+    /// Internal: Take `fee_bps` of `lp_amount` LP held by this contract and send
+    /// it to the pool's fee recipients as share tokens.
+    ///
+    /// # Returns
+    /// The LP taken
+    fn take_lp_fee(env: &Env, pool_info: &PoolInfo, fees: &PoolFeeConfig, fee_bps: u32, lp_amount: i128) -> i128 {
+        let fee = lp_amount
+            .checked_mul(fee_bps as i128)
+            .unwrap_or(0)
+            .checked_div(10000)
+            .unwrap_or(0);
+        if fee <= 0 {
+            return 0;
+        }
+
+        use soroban_sdk::token;
+        let share_client = token::Client::new(env, &pool_info.share_token);
+        let contract_address = env.current_contract_address();
+        let (to_treasury, to_pol) = Self::split_pool_fee(fees, fee);
+        if to_treasury > 0 {
+            share_client.transfer(&contract_address, &fees.treasury, &to_treasury);
+        }
+        if to_pol > 0 {
+            share_client.transfer(&contract_address, &fees.pol_recipient, &to_pol);
+        }
+        fee
+    }
+
+    /// Internal: Read a pool's compound stats, decoding the pre-v1.9.0 layout if needed
+    fn read_compound_stats(env: &Env, pool_id: u32) -> PoolCompoundStats {
+        use soroban_sdk::{Map, TryFromVal, Val};
+
+        let empty = PoolCompoundStats {
+            total_compounded_lp: 0,
+            total_rewards_claimed: 0,
+            total_treasury_fees: 0,
+            last_compound_time: 0,
+            compound_count: 0,
+            total_pol_fees: 0,
+            total_deposit_fee_lp: 0,
+            total_withdraw_fee_lp: 0,
+            total_harvest_bounties: 0,
+        };
+        let raw: Map<Symbol, Val> = match env
+            .storage()
+            .persistent()
+            .get(&DataKey::PoolCompoundStats(pool_id))
+        {
+            Some(raw) => raw,
+            None => return empty,
+        };
+
+        if raw.contains_key(Symbol::new(env, "total_deposit_fee_lp")) {
+            return PoolCompoundStats::try_from_val(env, &raw.to_val()).unwrap_or(empty);
+        }
+        match LegacyPoolCompoundStats::try_from_val(env, &raw.to_val()) {
+            Ok(legacy) => PoolCompoundStats {
+                total_compounded_lp: legacy.total_compounded_lp,
+                total_rewards_claimed: legacy.total_rewards_claimed,
+                total_treasury_fees: legacy.total_treasury_fees,
+                last_compound_time: legacy.last_compound_time,
+                compound_count: legacy.compound_count,
+                ..empty
+            },
+            Err(_) => empty,
+        }
+    }
+
+    fn write_compound_stats(env: &Env, pool_id: u32, stats: &PoolCompoundStats) {
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolCompoundStats(pool_id), stats);
+    }
+
+    /// Internal: Require admin or guardian authorization
+    fn require_guardian_auth(env: &Env, caller: &Address) -> Result<(), Error> {
+        caller.require_auth();
+        if *caller == Self::read_admin(env)? {
+            return Ok(());
+        }
+        match env.storage().instance().get::<PoolKey, Address>(&PoolKey::Guardian) {
+            Some(guardian) if guardian == *caller => Ok(()),
+            _ => Err(Error::Unauthorized),
+        }
+    }
+
+    /// Internal: Require curator authorization (manager or admin if no curator is set)
+    fn require_curator_auth(env: &Env, curator: &Address) -> Result<(), Error> {
+        match env.storage().instance().get::<PoolKey, Address>(&PoolKey::Curator) {
+            Some(stored) => {
+                curator.require_auth();
+                if stored != *curator {
+                    return Err(Error::Unauthorized);
+                }
+                Ok(())
+            }
+            None => Self::require_manager_auth(env, curator),
+        }
+    }
+
+    /// Internal: Require reward gauge or manager authorization
+    fn require_gauge_auth(env: &Env, funder: &Address) -> Result<(), Error> {
+        match env.storage().instance().get::<PoolKey, Address>(&PoolKey::RewardGauge) {
+            Some(gauge) if gauge == *funder => {
+                funder.require_auth();
+                Ok(())
+            }
+            _ => Self::require_manager_auth(env, funder),
+        }
+    }
+
+    /// Internal: Deposit tokens held by this contract into the pool through
+    /// its strategy adapter. `amounts` follows the pool's token order.
+    ///
+    /// Token usage and LP minted are measured from this contract's balances
+    /// before and after the call, not taken from the pool's return values.
+    ///
+    /// # Returns
+    /// `(used_amounts, lp_minted)` — the amounts the pool actually took
+    fn deposit_to_pool(
+        env: &Env,
+        pool_info: &PoolInfo,
+        amounts: &Vec<i128>,
+        min_shares: u128,
+    ) -> Result<(Vec<i128>, u128), Error> {
+        if amounts.len() != pool_info.tokens.len() {
+            return Err(Error::InvalidInput);
+        }
+
+        use soroban_sdk::token;
+        let contract_address = env.current_contract_address();
+        let share_client = token::Client::new(env, &pool_info.share_token);
+        let mut balances_before = Vec::new(env);
+        for token in pool_info.tokens.iter() {
+            balances_before.push_back(token::Client::new(env, &token).balance(&contract_address));
+        }
+        let lp_before = share_client.balance(&contract_address);
+
+        strategy_adapter(pool_info.strategy.kind).deposit(env, pool_info, amounts, min_shares);
+
+        let mut used = Vec::new(env);
+        for (i, token) in pool_info.tokens.iter().enumerate() {
+            let taken = balances_before.get(i as u32).unwrap_or(0)
+                - token::Client::new(env, &token).balance(&contract_address);
+            if taken < 0 || taken > amounts.get(i as u32).unwrap_or(0) {
+                return Err(Error::InvalidInput);
+            }
+            used.push_back(taken);
+        }
+        let lp_minted = share_client.balance(&contract_address) - lp_before;
+        if lp_minted < 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        Ok((used, lp_minted as u128))
+    }
+
+    /// Internal: Pool tokens (pool token order) left over by earlier compounds
+    fn read_idle_residual(env: &Env, pool_info: &PoolInfo) -> Vec<i128> {
+        match env
+            .storage()
+            .persistent()
+            .get::<PoolKey, Vec<i128>>(&PoolKey::IdleResidual(pool_info.pool_id))
+        {
+            Some(residual) if residual.len() == pool_info.tokens.len() => residual,
+            _ => {
+                let mut zeros = Vec::new(env);
+                for _ in pool_info.tokens.iter() {
+                    zeros.push_back(0i128);
+                }
+                zeros
+            }
+        }
+    }
+
+    /// Internal: Store a pool's idle residual (removed once it is all zero)
+    fn write_idle_residual(env: &Env, pool_info: &PoolInfo, residual: &Vec<i128>) {
+        let old = Self::read_idle_residual(env, pool_info);
+        Self::track_vault_idle(env, &pool_info.tokens, &old, residual);
+
+        let key = PoolKey::IdleResidual(pool_info.pool_id);
+        if residual.iter().any(|amount| amount > 0) {
+            env.storage().persistent().set(&key, residual);
+        } else {
+            env.storage().persistent().remove(&key);
+        }
+    }
+
+    /// Internal: Move the BLUB and AQUA of a pool's idle tokens (residual or
+    /// emergency-exit idle, `tokens` order) from `old` to `new` amounts in the
+    /// token ledger's vault idle buckets
+    fn track_vault_idle(env: &Env, tokens: &Vec<Address>, old: &Vec<i128>, new: &Vec<i128>) {
+        let config = match Self::read_token_config(env) {
+            Ok(config) => config,
+            Err(_) => return,
+        };
+        let mut blub_delta: i128 = 0;
+        let mut aqua_delta: i128 = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            let delta = new.get(i as u32).unwrap_or(0) - old.get(i as u32).unwrap_or(0);
+            if token == config.blub_token {
+                blub_delta = blub_delta.saturating_add(delta);
+            } else if token == config.aqua_token {
+                aqua_delta = aqua_delta.saturating_add(delta);
+            }
+        }
+        if blub_delta == 0 && aqua_delta == 0 {
+            return;
+        }
+
+        let mut ledger = Self::read_token_ledger(env);
+        ledger.blub_vault_idle = ledger.blub_vault_idle.saturating_add(blub_delta).max(0);
+        ledger.aqua_vault_idle = ledger.aqua_vault_idle.saturating_add(aqua_delta).max(0);
+        Self::write_token_ledger(env, &ledger);
+    }
+
+    /// Internal: `deposit_to_pool` for a two-token pool.
+    ///
+    /// # Returns
+    /// `(used_a, used_b, lp_minted)` — the amounts the pool actually took
+    fn deposit_pair_to_pool(
+        env: &Env,
+        pool_info: &PoolInfo,
+        deposit_a: i128,
+        deposit_b: i128,
+        min_shares: u128,
+    ) -> Result<(i128, i128, u128), Error> {
+        let (used, lp_minted) =
+            Self::deposit_to_pool(env, pool_info, &soroban_sdk::vec![env, deposit_a, deposit_b], min_shares)?;
+        Ok((used.get(0).unwrap_or(0), used.get(1).unwrap_or(0), lp_minted))
+    }
+
+    /// Internal: Swap `amount_in` of the token at `in_idx` of a two-token pool
+    /// for the other token through the pool itself. Callers bound slippage on
+    /// the final result.
+    fn swap_in_pool(env: &Env, pool_info: &PoolInfo, in_idx: u32, amount_in: i128) -> u128 {
+        strategy_adapter(pool_info.strategy.kind).swap(env, pool_info, in_idx, 1 - in_idx, amount_in, 0)
+    }
+
+    /// Internal: Deposit (token_a, token_b) already held by this contract,
+    /// trimmed to what the pool's curve accepts.
+    ///
+    /// # Returns
+    /// `(left_a, left_b, lp_minted)` — leftovers are still held by this contract
+    fn deposit_held_pair(
+        env: &Env,
+        pool_info: &PoolInfo,
+        held_a: i128,
+        held_b: i128,
+        min_shares: u128,
+    ) -> Result<(i128, i128, u128), Error> {
+        let offered = strategy_adapter(pool_info.strategy.kind)
+            .deposit_amounts(env, pool_info, &soroban_sdk::vec![env, held_a, held_b]);
+        let (deposit_a, deposit_b) = (offered.get(0).unwrap_or(0), offered.get(1).unwrap_or(0));
+        if deposit_a <= 0 || deposit_b <= 0 {
+            return Err(Error::InvalidInput);
+        }
+        let (used_a, used_b, lp_minted) =
+            Self::deposit_pair_to_pool(env, pool_info, deposit_a, deposit_b, min_shares)?;
+
+        Ok((held_a - used_a, held_b - used_b, lp_minted))
+    }
+
+    /// Internal: Zap `amount` of the pool token at `in_idx`, held by this
+    /// contract, into the pool: swap the optimal fraction, then deposit both legs.
+    ///
+    /// # Returns
+    /// `(swap_amount, left_a, left_b, lp_minted)` — leftovers are still held by this contract
+    fn zap_held_token(
+        env: &Env,
+        pool_info: &PoolInfo,
+        in_idx: u32,
+        amount: i128,
+        min_shares: u128,
+    ) -> Result<(i128, i128, i128, u128), Error> {
+        let swap_amount = strategy_adapter(pool_info.strategy.kind).zap_swap_amount(env, pool_info, in_idx, amount)?;
+        let swapped_out = if swap_amount > 0 {
+            Self::swap_in_pool(env, pool_info, in_idx, swap_amount) as i128
+        } else {
+            0
+        };
+        let (held_a, held_b) = if in_idx == 0 {
+            (amount - swap_amount, swapped_out)
+        } else {
+            (swapped_out, amount - swap_amount)
+        };
+        if held_a <= 0 || held_b <= 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let (left_a, left_b, lp_minted) = Self::deposit_held_pair(env, pool_info, held_a, held_b, min_shares)?;
+        Ok((swap_amount, left_a, left_b, lp_minted))
+    }
+
+    /// Internal: Take `desired` (pool token order) from the user, deposit what
+    /// the pool's curve accepts, refund the rest and mint vault shares.
+    ///
+    /// # Returns
+    /// `(used_amounts, lp_minted, share_balance)`
+    fn deposit_for_user(
+        env: &Env,
+        user: &Address,
+        pool_info: &mut PoolInfo,
+        desired: &Vec<i128>,
+        min_shares: u128,
+        min_vault_shares: i128,
+    ) -> Result<(Vec<i128>, u128, i128), Error> {
+        if desired.len() != pool_info.tokens.len() || desired.iter().any(|amount| amount < 0) {
+            return Err(Error::InvalidInput);
+        }
+
+        let contract_address = env.current_contract_address();
+
+        // STEP 1: Transfer full desired amounts from user to contract.
+        // We transfer the exact function parameters so auth entries are
+        // deterministic and won't break if pool reserves shift between
+        // simulation and execution.
+        use soroban_sdk::token;
+        for (token, amount) in pool_info.tokens.iter().zip(desired.iter()) {
+            if amount > 0 {
+                token::Client::new(env, &token).transfer(user, &contract_address, &amount);
+            }
+        }
+
+        // STEP 2: Pick deposit amounts for the pool's curve.
+        // Verified pools store tokens in pool order, so reserves, auth entries
+        // and deposit amounts all share that index.
+        let offered = strategy_adapter(pool_info.strategy.kind).deposit_amounts(env, pool_info, desired);
+        if offered.len() != desired.len()
+            || desired
+                .iter()
+                .zip(offered.iter())
+                .any(|(want, give)| give < 0 || give > want || (want > 0 && give == 0))
+        {
+            return Err(Error::InvalidInput);
+        }
+
+        // STEP 3: Deposit tokens to the pool in pool token order.
+        let (used, lp_shares_minted) = Self::deposit_to_pool(env, pool_info, &offered, min_shares)?;
+
+        // STEP 4: Refund whatever the pool did not take back to user
+        for (i, token) in pool_info.tokens.iter().enumerate() {
+            let refund = desired.get(i as u32).unwrap_or(0) - used.get(i as u32).unwrap_or(0);
+            if refund > 0 {
+                token::Client::new(env, &token).transfer(&contract_address, user, &refund);
+            }
+        }
+
+        // STEP 5: Mint vault shares proportional to LP deposited
+        let (_, share_balance) =
+            Self::mint_vault_shares(env, pool_info, user, lp_shares_minted as i128, min_vault_shares)?;
+
+        Ok((used, lp_shares_minted, share_balance))
+    }
+
+    /// Deposits tokens to a two-token vault pool.
+    /// User deposits token_a + token_b, contract adds liquidity to Aquarius pool.
+    /// Whatever the pool does not take (measured from this contract's balances) is refunded.
+    ///
+    /// # Arguments
+    /// * `user` - User address
+    /// * `pool_id` - Pool ID
+    /// * `desired_a` - Amount of token_a to deposit
+    /// * `desired_b` - Amount of token_b to deposit
+    /// * `min_shares` - Minimum LP shares to receive (slippage protection)
+    /// * `min_vault_shares` - Minimum vault shares to mint (0 = any non-zero amount)
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_deposit(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        desired_a: i128,
+        desired_b: i128,
+        min_shares: u128,
+        min_vault_shares: i128,
+    ) -> Result<(), Error> {
+        user.require_auth();
+
+        if desired_a <= 0 || desired_b <= 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+
+        Self::require_pool_open(&pool_info)?;
+
+        let desired = soroban_sdk::vec![&env, desired_a, desired_b];
+        let (used, lp_shares_minted, share_balance) =
+            Self::deposit_for_user(&env, &user, &mut pool_info, &desired, min_shares, min_vault_shares)?;
+
+        env.events().publish(
+            (symbol_short!("vault_dep"), user.clone(), pool_id),
+            (used.get(0).unwrap_or(0), used.get(1).unwrap_or(0), lp_shares_minted, share_balance),
+        );
+
+        Ok(())
+    }
+
+    /// Deposits any mix of a vault pool's tokens, for pools of two or more tokens.
+    /// Amounts the pool does not take are refunded.
+    ///
+    /// # Arguments
+    /// * `user` - User address
+    /// * `pool_id` - Pool ID
+    /// * `amounts` - Amount of each pool token, in `PoolInfo.tokens` order (0 to skip a token)
+    /// * `min_shares` - Minimum LP shares to receive (slippage protection)
+    /// * `min_vault_shares` - Minimum vault shares to mint (0 = any non-zero amount)
+    ///
+    /// # Returns
+    /// * `Ok(used_amounts)` - Amount of each token the pool took
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_deposit_tokens(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        amounts: Vec<i128>,
+        min_shares: u128,
+        min_vault_shares: i128,
+    ) -> Result<Vec<i128>, Error> {
+        user.require_auth();
+
+        if !amounts.iter().any(|amount| amount > 0) {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+
+        Self::require_pool_open(&pool_info)?;
+
+        let (used, lp_shares_minted, share_balance) =
+            Self::deposit_for_user(&env, &user, &mut pool_info, &amounts, min_shares, min_vault_shares)?;
+
+        env.events().publish(
+            (symbol_short!("vault_dpt"), user.clone(), pool_id),
+            (used.clone(), lp_shares_minted, share_balance),
+        );
+
+        Ok(used)
+    }
+
+    /// Single-asset vault deposit.
+    /// Deposits a single token into an Aquarius pool. The AMM handles the
+    /// internal swap to balance the deposit across the pool tokens.
+    /// Whatever the pool does not take is refunded.
+    ///
+    /// # Arguments
+    /// * `user` - User address
+    /// * `pool_id` - Pool ID
+    /// * `token_in` - Address of the token being deposited (must be one of the pool's tokens)
+    /// * `amount_in` - Amount of token_in to deposit (in raw units, 7 decimals)
+    /// * `min_shares` - Minimum LP shares to receive (slippage protection)
+    /// * `min_vault_shares` - Minimum vault shares to mint (0 = any non-zero amount)
+    pub fn vault_deposit_single(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        token_in: Address,
+        amount_in: i128,
+        min_shares: u128,
+        min_vault_shares: i128,
+    ) -> Result<(), Error> {
+        user.require_auth();
+
+        if amount_in <= 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+
+        Self::require_pool_open(&pool_info)?;
+
+        // Verify token_in is one of the pool tokens
+        let in_idx = pool_info.tokens.first_index_of(&token_in).ok_or(Error::InvalidInput)?;
+
+        let contract_address = env.current_contract_address();
+
+        // STEP 1: Transfer input token from user to contract
+        use soroban_sdk::token;
+        let token_client = token::Client::new(&env, &token_in);
+        token_client.transfer(&user, &contract_address, &amount_in);
+
+        // STEP 2: Build deposit amounts in pool order —
+        // input token gets amount, others are 0
+        let mut amounts = Vec::new(&env);
+        for i in 0..pool_info.tokens.len() {
+            amounts.push_back(if i == in_idx { amount_in } else { 0 });
+        }
+
+        // STEP 3: Deposit to the pool (single-asset — AMM handles internal swap).
+        // The adapter only authorizes the non-zero token transfer.
+        let (used, lp_shares_minted) = Self::deposit_to_pool(&env, &pool_info, &amounts, min_shares)?;
+        let used_in = used.get(in_idx).unwrap_or(0);
+
+        // STEP 4: Refund whatever the pool did not take back to user
+        let refund = amount_in - used_in;
+        if refund > 0 {
+            token_client.transfer(&contract_address, &user, &refund);
+        }
+
+        // STEP 5: Mint vault shares proportional to LP deposited
+        let (_, share_balance) = Self::mint_vault_shares(&env, &mut pool_info, &user, lp_shares_minted as i128, min_vault_shares)?;
+
+        env.events().publish(
+            (symbol_short!("vault_dep"), user.clone(), pool_id),
+            (used_in, 0i128, lp_shares_minted, share_balance),
+        );
+
+        Ok(())
+    }
+
+    /// Internal: Number of the user's vault shares that `share_percent` (bps) covers.
+    fn vault_shares_for_percent(env: &Env, user: &Address, pool_id: u32, share_percent: u32) -> Result<i128, Error> {
+        if share_percent == 0 || share_percent > 10000 {
+            return Err(Error::InvalidInput);
+        }
+
+        let user_position: UserVaultPosition = env
+            .storage()
+            .persistent()
+            .get(&DataKey::UserVaultPosition(user.clone(), pool_id))
+            .ok_or(Error::PositionNotFound)?;
+
+        if !user_position.active {
+            return Err(Error::PositionNotFound);
+        }
+
+        Ok(user_position
+            .share_ratio
+            .checked_mul(share_percent as i128)
+            .unwrap_or(0)
+            .checked_div(10000)
+            .unwrap_or(0))
+    }
+
+    /// Internal: Take `shares` from a user's vault position and the pool total,
+    /// reducing the user's deposited-LP tracking in proportion.
+    ///
+    /// # Returns
+    /// Pool total shares before the burn
+    fn debit_vault_shares(env: &Env, user: &Address, pool_id: u32, shares: i128) -> Result<i128, Error> {
+        if shares <= 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut user_position: UserVaultPosition = env
+            .storage()
+            .persistent()
+            .get(&DataKey::UserVaultPosition(user.clone(), pool_id))
+            .ok_or(Error::PositionNotFound)?;
+
+        if !user_position.active {
+            return Err(Error::PositionNotFound);
+        }
+
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0);
+
+        let user_shares = user_position.share_ratio;
+
+        if user_shares < shares || total_shares <= 0 {
+            return Err(Error::InsufficientBalance);
+        }
+
+        Self::checkpoint_vault_rewards(env, user, pool_id);
+        let remaining_shares = user_shares.saturating_sub(shares);
+
+        if remaining_shares > 0 {
+            user_position.share_ratio = remaining_shares;
+        } else {
+            user_position.share_ratio = 0;
+            user_position.active = false;
+        }
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::UserVaultPosition(user.clone(), pool_id), &user_position);
+        env.storage()
+            .persistent()
+            .set(&DataKey::VaultTotalShares(pool_id), &total_shares.saturating_sub(shares));
+        Self::track_vault_shares(env, pool_id, user, shares.saturating_neg());
+
+        // Proportionally reduce user's deposited LP tracking
+        let prev_deposited: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::UserDepositedLp(user.clone(), pool_id))
+            .unwrap_or(0);
+        if prev_deposited > 0 {
+            // Reduce proportionally: deposited *= (1 - burned/user_shares)
+            let withdrawn_fraction = shares
+                .checked_mul(prev_deposited)
+                .unwrap_or(0)
+                .checked_div(user_shares)
+                .unwrap_or(0);
+            let new_deposited = prev_deposited.saturating_sub(withdrawn_fraction);
+            env.storage()
+                .persistent()
+                .set(&DataKey::UserDepositedLp(user.clone(), pool_id), &new_deposited);
+        }
+
+        Ok(total_shares)
+    }
+
+    /// Internal: Burn `shares` of a user's vault shares and release the matching
+    /// LP from the pool's accounting. The pool's withdraw fee is paid out of the
+    /// released LP; the rest stays in this contract for the caller to unwind or
+    /// hand over.
+    ///
+    /// # Returns
+    /// `(pool_info, lp_released)` — LP after the withdraw fee
+    fn burn_vault_shares(env: &Env, user: &Address, pool_id: u32, shares: i128) -> Result<(PoolInfo, i128), Error> {
+        let pool_info = Self::load_pool(env, pool_id)?;
+        if Self::read_pool_exit(env, pool_id).is_some() {
+            return Err(Error::PoolInEmergencyExit);
+        }
+
+        let total_shares = Self::debit_vault_shares(env, user, pool_id, shares)?;
+
+        // LP = shares * total_lp / total_shares
+        let lp_to_withdraw = shares
+            .checked_mul(pool_info.total_lp_tokens)
+            .unwrap_or(0)
+            .checked_div(total_shares)
+            .unwrap_or(0);
+
+        Self::release_vault_lp(env, pool_info, lp_to_withdraw)
+    }
+
+    /// Internal: Take `lp_to_withdraw` out of the pool's LP accounting after the
+    /// matching shares were debited, and pay the pool's withdraw fee out of it.
+    ///
+    /// # Returns
+    /// `(pool_info, lp_released)` — LP after the withdraw fee
+    fn release_vault_lp(env: &Env, mut pool_info: PoolInfo, lp_to_withdraw: i128) -> Result<(PoolInfo, i128), Error> {
+        let pool_id = pool_info.pool_id;
+        if lp_to_withdraw <= 0 || lp_to_withdraw > pool_info.total_lp_tokens {
+            return Err(Error::InvalidInput);
+        }
+
+        pool_info.total_lp_tokens = pool_info.total_lp_tokens.saturating_sub(lp_to_withdraw);
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        let fees = Self::read_pool_fees(env, pool_id)?;
+        let fee_lp = Self::take_lp_fee(env, &pool_info, &fees, fees.withdraw_fee_bps, lp_to_withdraw);
+        if fee_lp > 0 {
+            let mut stats = Self::read_compound_stats(env, pool_id);
+            stats.total_withdraw_fee_lp = stats.total_withdraw_fee_lp.saturating_add(fee_lp);
+            Self::write_compound_stats(env, pool_id, &stats);
+        }
+
+        Ok((pool_info, lp_to_withdraw - fee_lp))
+    }
+
+    /// Internal: Burn `shares` in a pool that has been through `emergency_exit_pool`
+    /// and release the pro-rata share of its idle tokens. Dead shares can never
+    /// be redeemed, so the idle is split over the owned shares only and the
+    /// last redemption empties it.
+    ///
+    /// # Returns
+    /// `(pool_info, amounts)` — tokens left in the contract for the caller to pay out
+    fn redeem_idle_shares(env: &Env, user: &Address, pool_id: u32, shares: i128) -> Result<(PoolInfo, Vec<u128>), Error> {
+        let pool_info = Self::load_pool(env, pool_id)?;
+        let mut exit = Self::read_pool_exit(env, pool_id).ok_or(Error::InvalidInput)?;
+
+        let owned_shares = Self::owned_vault_shares(env, pool_id);
+        Self::debit_vault_shares(env, user, pool_id, shares)?;
+
+        let mut amounts = Vec::new(env);
+        let mut idle_left = Vec::new(env);
+        for idle in exit.idle.iter() {
+            let amount = shares
+                .checked_mul(idle)
+                .unwrap_or(0)
+                .checked_div(owned_shares)
+                .unwrap_or(0);
+            amounts.push_back(amount as u128);
+            idle_left.push_back(idle.saturating_sub(amount));
+        }
+
+        Self::track_vault_idle(env, &pool_info.tokens, &exit.idle, &idle_left);
+        exit.idle = idle_left;
+        env.storage()
+            .persistent()
+            .set(&PoolKey::EmergencyExit(pool_id), &exit);
+
+        Ok((pool_info, amounts))
+    }
+
+    /// Internal: A pool's emergency exit state, if `emergency_exit_pool` has run
+    fn read_pool_exit(env: &Env, pool_id: u32) -> Option<PoolExitState> {
+        env.storage().persistent().get(&PoolKey::EmergencyExit(pool_id))
+    }
+
+    /// Internal: A zero minimum for each of the pool's tokens
+    fn no_minimums(env: &Env, pool_info: &PoolInfo) -> Vec<u128> {
+        let mut minimums = Vec::new(env);
+        for _ in pool_info.tokens.iter() {
+            minimums.push_back(0u128);
+        }
+        minimums
+    }
+
+    /// Internal: Remove `lp_amount` of this contract's liquidity from the
+    /// pool. Tokens are left in the contract for the caller to pay out.
+    ///
+    /// Works on unverified pools too: the amounts are measured from this
+    /// contract's balance of each `PoolInfo.tokens` entry, so they never depend
+    /// on the pool's own token order, and `min_amounts` (same order) is
+    /// checked here rather than passed to the pool.
+    ///
+    /// # Returns
+    /// * `Ok(amounts)` - Amount of each token actually withdrawn, in `PoolInfo.tokens` order
+    /// * `Err(Error::SlippageExceeded)` if any amount is below its minimum
+    fn unwind_vault_lp(
+        env: &Env,
+        pool_info: &PoolInfo,
+        lp_amount: i128,
+        min_amounts: &Vec<u128>,
+    ) -> Result<Vec<u128>, Error> {
+        if min_amounts.len() != pool_info.tokens.len() {
+            return Err(Error::InvalidInput);
+        }
+
+        use soroban_sdk::token;
+        let contract_address = env.current_contract_address();
+        let mut balances_before = Vec::new(env);
+        for token in pool_info.tokens.iter() {
+            balances_before.push_back(token::Client::new(env, &token).balance(&contract_address));
+        }
+
+        strategy_adapter(pool_info.strategy.kind).withdraw(
+            env,
+            pool_info,
+            lp_amount,
+            &Self::no_minimums(env, pool_info),
+        );
+
+        let mut withdrawn = Vec::new(env);
+        for (i, token) in pool_info.tokens.iter().enumerate() {
+            let received = token::Client::new(env, &token).balance(&contract_address)
+                - balances_before.get(i as u32).unwrap_or(0);
+            if received < 0 {
+                return Err(Error::InvalidInput);
+            }
+            if (received as u128) < min_amounts.get(i as u32).unwrap_or(0) {
+                return Err(Error::SlippageExceeded);
+            }
+            withdrawn.push_back(received as u128);
+        }
+        Ok(withdrawn)
+    }
+
+    /// Internal: Burn `shares` and pay every pool token to `receiver`.
+    ///
+    /// Normally the LP is unwound through the pool; after `emergency_exit_pool`
+    /// the pro-rata share of the idle tokens is paid instead.
+    ///
+    /// # Returns
+    /// `(lp_withdrawn, amounts)` — `lp_withdrawn` is 0 for idle payouts
+    fn redeem_vault_tokens(
+        env: &Env,
+        user: &Address,
+        pool_id: u32,
+        shares: i128,
+        min_amounts: &Vec<u128>,
+        receiver: &Address,
+    ) -> Result<(i128, Vec<u128>), Error> {
+        if Self::read_pool_exit(env, pool_id).is_some() {
+            let (pool_info, amounts) = Self::redeem_idle_shares(env, user, pool_id, shares)?;
+            if min_amounts.len() != amounts.len()
+                || amounts.iter().zip(min_amounts.iter()).any(|(amount, min)| amount < min)
+            {
+                return Err(Error::SlippageExceeded);
+            }
+            Self::pay_out_tokens(env, &pool_info, receiver, &amounts);
+            return Ok((0, amounts));
+        }
+
+        let (pool_info, lp_to_withdraw) = Self::burn_vault_shares(env, user, pool_id, shares)?;
+        let amounts = Self::unwind_vault_lp(env, &pool_info, lp_to_withdraw, min_amounts)?;
+        Self::pay_out_tokens(env, &pool_info, receiver, &amounts);
+
+        Ok((lp_to_withdraw, amounts))
+    }
+
+    /// Internal: Pay withdrawn pool tokens (pool order) held by this contract to `receiver`.
+    fn pay_out_tokens(env: &Env, pool_info: &PoolInfo, receiver: &Address, amounts: &Vec<u128>) {
+        use soroban_sdk::token;
+        let contract_address = env.current_contract_address();
+        for (token, amount) in pool_info.tokens.iter().zip(amounts.iter()) {
+            if amount > 0 {
+                token::Client::new(env, &token).transfer(&contract_address, receiver, &(amount as i128));
+            }
+        }
+    }
+
+    /// Withdraws tokens from a two-token vault pool.
+    /// User withdraws their share, contract removes liquidity from Aquarius pool.
+    ///
+    /// # Arguments
+    /// * `user` - User address
+    /// * `pool_id` - Pool ID
+    /// * `share_percent` - Percentage of user's position to withdraw (0-10000 = 0-100%)
+    /// * `min_a` - Minimum amount of token_a to receive (slippage protection)
+    /// * `min_b` - Minimum amount of token_b to receive (slippage protection)
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_withdraw(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        share_percent: u32,
+        min_a: u128,
+        min_b: u128,
+    ) -> Result<(), Error> {
+        user.require_auth();
+
+        let shares = Self::vault_shares_for_percent(&env, &user, pool_id, share_percent)?;
+        let min_amounts = soroban_sdk::vec![&env, min_a, min_b];
+        let (lp_to_withdraw, amounts) =
+            Self::redeem_vault_tokens(&env, &user, pool_id, shares, &min_amounts, &user)?;
+
+        env.events().publish(
+            (symbol_short!("vault_wd"), user.clone(), pool_id),
+            (lp_to_withdraw, amounts.get(0).unwrap_or(0), amounts.get(1).unwrap_or(0)),
+        );
+
+        Ok(())
+    }
+
+    /// Withdraws from a vault pool of any number of tokens.
+    ///
+    /// # Arguments
+    /// * `user` - User address
+    /// * `pool_id` - Pool ID
+    /// * `share_percent` - Percentage of user's position to withdraw (0-10000 = 0-100%)
+    /// * `min_amounts` - Minimum amount of each pool token, in `PoolInfo.tokens` order
+    ///
+    /// # Returns
+    /// * `Ok(amounts)` - Amount of each pool token paid to the user
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_withdraw_tokens(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        share_percent: u32,
+        min_amounts: Vec<u128>,
+    ) -> Result<Vec<u128>, Error> {
+        user.require_auth();
+
+        let shares = Self::vault_shares_for_percent(&env, &user, pool_id, share_percent)?;
+        let (lp_to_withdraw, amounts) =
+            Self::redeem_vault_tokens(&env, &user, pool_id, shares, &min_amounts, &user)?;
+
+        env.events().publish(
+            (symbol_short!("vault_wdt"), user.clone(), pool_id),
+            (lp_to_withdraw, amounts.clone()),
+        );
+
+        Ok(amounts)
+    }
+
+    /// Redeems an exact number of vault shares for both tokens of a two-token pool.
+    ///
+    /// # Arguments
+    /// * `user` - Share owner
+    /// * `pool_id` - Pool ID
+    /// * `shares` - Vault shares to burn
+    /// * `min_a` - Minimum amount of token_a to receive (slippage protection)
+    /// * `min_b` - Minimum amount of token_b to receive (slippage protection)
+    /// * `receiver` - Address that receives the tokens
+    ///
+    /// # Returns
+    /// * `Ok((amount_a, amount_b))` - Tokens paid to `receiver`
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_redeem(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        shares: i128,
+        min_a: u128,
+        min_b: u128,
+        receiver: Address,
+    ) -> Result<(u128, u128), Error> {
+        user.require_auth();
+
+        let min_amounts = soroban_sdk::vec![&env, min_a, min_b];
+        let (lp_to_withdraw, amounts) =
+            Self::redeem_vault_tokens(&env, &user, pool_id, shares, &min_amounts, &receiver)?;
+        let (amount_a, amount_b) = (amounts.get(0).unwrap_or(0), amounts.get(1).unwrap_or(0));
+
+        env.events().publish(
+            (symbol_short!("vault_rdm"), user.clone(), pool_id),
+            (shares, lp_to_withdraw, amount_a, amount_b, receiver),
+        );
+
+        Ok((amount_a, amount_b))
+    }
+
+    /// Redeems an exact number of vault shares for every token of a vault pool
+    /// of any number of tokens.
+    ///
+    /// # Arguments
+    /// * `user` - Share owner
+    /// * `pool_id` - Pool ID
+    /// * `shares` - Vault shares to burn
+    /// * `min_amounts` - Minimum amount of each pool token, in `PoolInfo.tokens` order
+    /// * `receiver` - Address that receives the tokens
+    ///
+    /// # Returns
+    /// * `Ok(amounts)` - Amount of each pool token paid to `receiver`
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_redeem_tokens(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        shares: i128,
+        min_amounts: Vec<u128>,
+        receiver: Address,
+    ) -> Result<Vec<u128>, Error> {
+        user.require_auth();
+
+        let (lp_to_withdraw, amounts) =
+            Self::redeem_vault_tokens(&env, &user, pool_id, shares, &min_amounts, &receiver)?;
+
+        env.events().publish(
+            (symbol_short!("vault_rdt"), user.clone(), pool_id),
+            (shares, lp_to_withdraw, amounts.clone(), receiver),
+        );
+
+        Ok(amounts)
+    }
+
+    /// Withdraws an exact amount of LP from a user's vault position for both tokens of a two-token pool.
+    ///
+    /// Exactly `lp_amount` is unwound (the pool's withdraw fee comes out of
+    /// it). The shares burned are rounded up, so the rounding stays with the
+    /// remaining shareholders rather than being paid out.
+    ///
+    /// # Arguments
+    /// * `user` - Share owner
+    /// * `pool_id` - Pool ID
+    /// * `lp_amount` - LP to withdraw
+    /// * `min_a` - Minimum amount of token_a to receive (slippage protection)
+    /// * `min_b` - Minimum amount of token_b to receive (slippage protection)
+    /// * `receiver` - Address that receives the tokens
+    ///
+    /// # Returns
+    /// * `Ok(shares_burned)`
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_withdraw_lp(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        lp_amount: i128,
+        min_a: u128,
+        min_b: u128,
+        receiver: Address,
+    ) -> Result<i128, Error> {
+        user.require_auth();
+
+        let min_amounts = soroban_sdk::vec![&env, min_a, min_b];
+        let (shares, lp_to_withdraw, amounts) =
+            Self::withdraw_exact_vault_lp(&env, &user, pool_id, lp_amount, &min_amounts, &receiver)?;
+        let (amount_a, amount_b) = (amounts.get(0).unwrap_or(0), amounts.get(1).unwrap_or(0));
+
+        env.events().publish(
+            (symbol_short!("vault_wlp"), user.clone(), pool_id),
+            (shares, lp_to_withdraw, amount_a, amount_b, receiver),
+        );
+
+        Ok(shares)
+    }
+
+    /// Withdraws an exact amount of LP from a user's vault position for every
+    /// token of a vault pool of any number of tokens. See `vault_withdraw_lp`.
+    ///
+    /// # Arguments
+    /// * `user` - Share owner
+    /// * `pool_id` - Pool ID
+    /// * `lp_amount` - LP to withdraw
+    /// * `min_amounts` - Minimum amount of each pool token, in `PoolInfo.tokens` order
+    /// * `receiver` - Address that receives the tokens
+    ///
+    /// # Returns
+    /// * `Ok(shares_burned)`
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_withdraw_lp_tokens(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        lp_amount: i128,
+        min_amounts: Vec<u128>,
+        receiver: Address,
+    ) -> Result<i128, Error> {
+        user.require_auth();
+
+        let (shares, lp_to_withdraw, amounts) =
+            Self::withdraw_exact_vault_lp(&env, &user, pool_id, lp_amount, &min_amounts, &receiver)?;
+
+        env.events().publish(
+            (symbol_short!("vault_wlt"), user.clone(), pool_id),
+            (shares, lp_to_withdraw, amounts, receiver),
+        );
+
+        Ok(shares)
+    }
+
+    /// Internal: Burn the shares worth `lp_amount` (rounded up), unwind exactly
+    /// `lp_amount` less the withdraw fee and pay every pool token to `receiver`.
+    ///
+    /// # Returns
+    /// `(shares_burned, lp_withdrawn, amounts)`
+    fn withdraw_exact_vault_lp(
+        env: &Env,
+        user: &Address,
+        pool_id: u32,
+        lp_amount: i128,
+        min_amounts: &Vec<u128>,
+        receiver: &Address,
+    ) -> Result<(i128, i128, Vec<u128>), Error> {
+        if lp_amount <= 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let pool_info = Self::load_pool(env, pool_id)?;
+        if Self::read_pool_exit(env, pool_id).is_some() {
+            return Err(Error::PoolInEmergencyExit);
+        }
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0);
+        if pool_info.total_lp_tokens <= 0 || total_shares <= 0 || lp_amount > pool_info.total_lp_tokens {
+            return Err(Error::InsufficientBalance);
+        }
+
+        // shares = ceil(lp_amount * total_shares / total_lp)
+        let shares = lp_amount
+            .checked_mul(total_shares)
+            .ok_or(Error::InvalidInput)?
+            .saturating_add(pool_info.total_lp_tokens - 1)
+            / pool_info.total_lp_tokens;
+
+        Self::debit_vault_shares(env, user, pool_id, shares)?;
+        let (pool_info, lp_to_withdraw) = Self::release_vault_lp(env, pool_info, lp_amount)?;
+        let amounts = Self::unwind_vault_lp(env, &pool_info, lp_to_withdraw, min_amounts)?;
+        Self::pay_out_tokens(env, &pool_info, receiver, &amounts);
+
+        Ok((shares, lp_to_withdraw, amounts))
+    }
+
+    /// Redeems vault shares for the Aquarius LP share token itself, without
+    /// removing liquidity from the pool.
+    ///
+    /// # Arguments
+    /// * `user` - Share owner
+    /// * `pool_id` - Pool ID
+    /// * `shares` - Vault shares to burn
+    /// * `receiver` - Address that receives the LP tokens
+    ///
+    /// # Returns
+    /// * `Ok(lp_amount)` - LP share tokens sent to `receiver`
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_redeem_lp(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        shares: i128,
+        receiver: Address,
+    ) -> Result<i128, Error> {
+        user.require_auth();
+
+        let (pool_info, lp_amount) = Self::burn_vault_shares(&env, &user, pool_id, shares)?;
+
+        use soroban_sdk::token;
+        token::Client::new(&env, &pool_info.share_token).transfer(
+            &env.current_contract_address(),
+            &receiver,
+            &lp_amount,
+        );
+
+        env.events().publish(
+            (symbol_short!("vault_lpx"), user.clone(), pool_id),
+            (shares, lp_amount, receiver),
+        );
+
+        Ok(lp_amount)
+    }
+
+    /// Moves part of a user's position from one vault pool to another in a
+    /// single transaction.
+    ///
+    /// The LP is withdrawn from `from_pool`, converted through the pools'
+    /// shared token where needed (the leg not in `to_pool` is swapped in
+    /// `from_pool`, then zapped into `to_pool`), and deposited. Both positions
+    /// and their `UserDepositedLp` cost bases are updated; dust is refunded.
+    /// The pools must be two-token pools sharing at least one token, and
+    /// `from_pool` must be verified when its unshared leg is swapped.
+    ///
+    /// # Arguments
+    /// * `user` - Position owner
+    /// * `from_pool` - Pool to leave
+    /// * `to_pool` - Pool to enter
+    /// * `share_percent` - Percentage of the `from_pool` position to move (0-10000 = 0-100%)
+    /// * `min_shares` - Minimum LP minted in `to_pool`; bounds the swap and the
+    ///   deposit end to end, as a worse swap leaves less to deposit
+    /// * `min_vault_shares` - Minimum `to_pool` vault shares to mint (0 = any non-zero amount)
+    ///
+    /// # Returns
+    /// * `Ok(lp_credited)` - LP credited to the user in `to_pool`
+    /// * `Err(Error::SlippageExceeded)` if either minimum is not met
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn vault_migrate(
+        env: Env,
+        user: Address,
+        from_pool: u32,
+        to_pool: u32,
+        share_percent: u32,
+        min_shares: u128,
+        min_vault_shares: i128,
+    ) -> Result<i128, Error> {
+        user.require_auth();
+
+        if from_pool == to_pool {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut to_info = Self::load_pool(&env, to_pool)?;
+        Self::require_pool_open(&to_info)?;
+        Self::require_token_pair(&to_info)?;
+        Self::require_token_pair(&Self::load_pool(&env, from_pool)?)?;
+
+        // STEP 1: Leave the source pool
+        let shares = Self::vault_shares_for_percent(&env, &user, from_pool, share_percent)?;
+        let (from_info, lp_out) = Self::burn_vault_shares(&env, &user, from_pool, shares)?;
+        let out = Self::unwind_vault_lp(&env, &from_info, lp_out, &Self::no_minimums(&env, &from_info))?;
+        let (out_a, out_b) = (out.get(0).unwrap_or(0) as i128, out.get(1).unwrap_or(0) as i128);
+
+        // STEP 2: Route into the destination pool
+        let a_shared = from_info.token_a == to_info.token_a || from_info.token_a == to_info.token_b;
+        let b_shared = from_info.token_b == to_info.token_a || from_info.token_b == to_info.token_b;
+
+        let (left_a, left_b, lp_in) = if a_shared && b_shared {
+            // Same pair: deposit directly in the destination's order
+            let (held_a, held_b) = if from_info.token_a == to_info.token_a {
+                (out_a, out_b)
+            } else {
+                (out_b, out_a)
+            };
+            Self::deposit_held_pair(&env, &to_info, held_a, held_b, min_shares)?
+        } else if a_shared || b_shared {
+            // Swap the unshared leg into the shared token in the source pool,
+            // which is picked by index in the source pool's token order
+            Self::require_pool_verified(&from_info)?;
+            let (shared_token, shared_amount, other_amount, other_idx) = if a_shared {
+                (from_info.token_a.clone(), out_a, out_b, 1u32)
+            } else {
+                (from_info.token_b.clone(), out_b, out_a, 0u32)
+            };
+            let swapped = if other_amount > 0 {
+                Self::swap_in_pool(&env, &from_info, other_idx, other_amount) as i128
+            } else {
+                0
+            };
+
+            let in_idx = if shared_token == to_info.token_a { 0 } else { 1 };
+            let (_, left_a, left_b, lp_in) = Self::zap_held_token(
+                &env,
+                &to_info,
+                in_idx,
+                shared_amount.saturating_add(swapped),
+                min_shares,
+            )?;
+            (left_a, left_b, lp_in)
+        } else {
+            return Err(Error::InvalidInput);
+        };
+
+        // STEP 3: Refund dust in the destination pool's tokens
+        use soroban_sdk::token;
+        let contract_address = env.current_contract_address();
+        if left_a > 0 {
+            token::Client::new(&env, &to_info.token_a).transfer(&contract_address, &user, &left_a);
+        }
+        if left_b > 0 {
+            token::Client::new(&env, &to_info.token_b).transfer(&contract_address, &user, &left_b);
+        }
+
+        // STEP 4: Mint destination shares
+        let (lp_credited, share_balance) =
+            Self::mint_vault_shares(&env, &mut to_info, &user, lp_in as i128, min_vault_shares)?;
+
+        env.events().publish(
+            (symbol_short!("vault_mig"), user.clone()),
+            (from_pool, to_pool, shares, lp_out, lp_credited, share_balance),
+        );
+
+        Ok(lp_credited)
+    }
+
+    /// Zap-in: deposits a single token into a two-token vault pool.
+    ///
+    /// Unlike `vault_deposit_single`, the optimal fraction of `amount` is first
+    /// swapped through the pool so the deposit is balanced, then both legs are
+    /// deposited. Any dust the pool does not take is refunded.
+    ///
+    /// # Arguments
+    /// * `user` - User address
+    /// * `pool_id` - Pool ID
+    /// * `token_in` - Token being deposited (token_a or token_b of the pool)
+    /// * `amount` - Amount of token_in
+    /// * `min_shares` - Minimum LP to receive; the only slippage bound, covering swap and deposit
+    /// * `min_vault_shares` - Minimum vault shares to mint (0 = any non-zero amount)
+    ///
+    /// # Returns
+    /// * `Ok(lp_minted)` - LP added to the vault for the user
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn zap_deposit(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        token_in: Address,
+        amount: i128,
+        min_shares: u128,
+        min_vault_shares: i128,
+    ) -> Result<i128, Error> {
+        user.require_auth();
+
+        if amount <= 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+
+        Self::require_pool_open(&pool_info)?;
+        Self::require_token_pair(&pool_info)?;
+
+        let in_idx: u32 = if token_in == pool_info.token_a {
+            0
+        } else if token_in == pool_info.token_b {
+            1
+        } else {
+            return Err(Error::InvalidInput);
+        };
+
+        let contract_address = env.current_contract_address();
+
+        // STEP 1: Transfer input token from user to contract
+        use soroban_sdk::token;
+        let token_a_client = token::Client::new(&env, &pool_info.token_a);
+        let token_b_client = token::Client::new(&env, &pool_info.token_b);
+        token::Client::new(&env, &token_in).transfer(&user, &contract_address, &amount);
+
+        // STEP 2: Swap the optimal fraction into the other token and deposit
+        // both legs; min_shares bounds the whole zap
+        let (swap_amount, refund_a, refund_b, lp_shares_minted) =
+            Self::zap_held_token(&env, &pool_info, in_idx, amount, min_shares)?;
+
+        // STEP 3: Refund dust
+        if refund_a > 0 {
+            token_a_client.transfer(&contract_address, &user, &refund_a);
+        }
+        if refund_b > 0 {
+            token_b_client.transfer(&contract_address, &user, &refund_b);
+        }
+
+        // STEP 4: Mint vault shares proportional to LP deposited
+        let (lp_minted, share_balance) =
+            Self::mint_vault_shares(&env, &mut pool_info, &user, lp_shares_minted as i128, min_vault_shares)?;
+
+        env.events().publish(
+            (symbol_short!("zap_dep"), user.clone(), pool_id),
+            (token_in, amount, swap_amount, lp_minted, share_balance),
+        );
+
+        Ok(lp_minted)
+    }
+
+    /// Zap-out: withdraws from a two-token vault pool into a single token.
+    ///
+    /// Removes the user's share of liquidity, swaps the other leg back through
+    /// the pool into `token_out` and pays out the total. The swap picks its leg
+    /// by index, so the pool must be verified; plain withdrawals are not.
+    ///
+    /// # Arguments
+    /// * `user` - User address
+    /// * `pool_id` - Pool ID
+    /// * `share_percent` - Percentage of user's position to withdraw (0-10000 = 0-100%)
+    /// * `token_out` - Token to receive (token_a or token_b of the pool)
+    /// * `min_out` - Minimum total token_out to receive; the only slippage bound
+    ///
+    /// # Returns
+    /// * `Ok(amount_out)` - Amount of token_out paid to the user
+    /// * `Err(Error::SlippageExceeded)` if the total is below `min_out`
+    /// * `Err(Error::PoolNotVerified)` if the pool's token order is unchecked
+    ///
+    /// # Authorization
+    /// Requires user authorization
+    pub fn zap_withdraw(
+        env: Env,
+        user: Address,
+        pool_id: u32,
+        share_percent: u32,
+        token_out: Address,
+        min_out: i128,
+    ) -> Result<i128, Error> {
+        user.require_auth();
+
+        let pool_info = Self::load_pool(&env, pool_id)?;
+        Self::require_pool_verified(&pool_info)?;
+        Self::require_token_pair(&pool_info)?;
+        let out_idx: u32 = if token_out == pool_info.token_a {
+            0
+        } else if token_out == pool_info.token_b {
+            1
+        } else {
+            return Err(Error::InvalidInput);
+        };
+
+        // STEP 1: Remove liquidity without per-leg minimums
+        let shares = Self::vault_shares_for_percent(&env, &user, pool_id, share_percent)?;
+        let (pool_info, lp_to_withdraw) = Self::burn_vault_shares(&env, &user, pool_id, shares)?;
+        let amounts = Self::unwind_vault_lp(&env, &pool_info, lp_to_withdraw, &Self::no_minimums(&env, &pool_info))?;
+        let (amount_a, amount_b) = (amounts.get(0).unwrap_or(0), amounts.get(1).unwrap_or(0));
+
+        // STEP 2: Swap the other leg into token_out
+        let (kept, other) = if out_idx == 0 {
+            (amount_a as i128, amount_b as i128)
+        } else {
+            (amount_b as i128, amount_a as i128)
+        };
+        let swapped = if other > 0 {
+            Self::swap_in_pool(&env, &pool_info, 1 - out_idx, other) as i128
+        } else {
+            0
+        };
+        let amount_out = kept.saturating_add(swapped);
+
+        if amount_out < min_out {
+            return Err(Error::SlippageExceeded);
+        }
+
+        // STEP 3: Pay out
+        use soroban_sdk::token;
+        token::Client::new(&env, &token_out).transfer(&env.current_contract_address(), &user, &amount_out);
+
+        env.events().publish(
+            (symbol_short!("zap_wd"), user.clone(), pool_id),
+            (lp_to_withdraw, token_out, amount_out),
+        );
+
+        Ok(amount_out)
+    }
+
+    /// Internal: Claim a pool's AQUA rewards and pay its performance fee to the
+    /// treasury and POL recipient. The remainder stays in this contract for the
+    /// caller to pay out or compound in the same call.
+    ///
+    /// # Returns
+    /// `(total_rewards, fee_amount, remainder)`; all zero if nothing was claimed
+    fn claim_vault_pool_rewards(env: &Env, pool_info: &PoolInfo, aqua_token: &Address) -> Result<(u128, u128, u128), Error> {
+        let fees = Self::read_pool_fees(env, pool_info.pool_id)?;
+
+        let total_rewards = strategy_adapter(pool_info.strategy.kind).claim(env, pool_info);
+        if total_rewards == 0 {
+            return Ok((0, 0, 0));
+        }
+
+        // Split rewards — performance fee to recipients, remainder to compounding
+        let treasury_amount = total_rewards
+            .checked_mul(fees.performance_fee_bps as u128)
+            .unwrap_or(0)
+            .checked_div(10000)
+            .unwrap_or(0);
+
+        let compound_amount = total_rewards.saturating_sub(treasury_amount);
+
+        // Transfer the fee to the pool's treasury and POL recipient
+        use soroban_sdk::token;
+        let aqua_client = token::Client::new(env, aqua_token);
+        let contract_address = env.current_contract_address();
+        let (to_treasury, to_pol) = Self::split_pool_fee(&fees, treasury_amount as i128);
+        if to_treasury > 0 {
+            aqua_client.transfer(&contract_address, &fees.treasury, &to_treasury);
+        }
+        if to_pol > 0 {
+            aqua_client.transfer(&contract_address, &fees.pol_recipient, &to_pol);
+        }
+
+        let mut stats = Self::read_compound_stats(env, pool_info.pool_id);
+        stats.total_rewards_claimed = stats.total_rewards_claimed.saturating_add(total_rewards as i128);
+        stats.total_treasury_fees = stats.total_treasury_fees.saturating_add(to_treasury);
+        stats.total_pol_fees = stats.total_pol_fees.saturating_add(to_pol);
+        Self::write_compound_stats(env, pool_info.pool_id, &stats);
+
+        Ok((total_rewards, treasury_amount, compound_amount))
+    }
+
+    /// Claims boosted rewards from a pool and auto-compounds.
+    /// Treasury cut is the pool's `performance_fee_bps` (defaults to `FeeConfig.vault_fee_bps`,
+    /// 1500 = 15%), split between its treasury and POL recipient; remainder auto-compounds.
+    /// Pool 0 (POL+vault mixed): backend applies an additional cut on the POL share
+    /// in `handleStakingRewardDistribution` so POL effectively pays 30%.
+    /// Backend cron calls this 4x daily using ICE balance for boost.
+    ///
+    /// # Arguments
+    /// * `pool_id` - Pool ID to claim rewards from
+    ///
+    /// # Authorization
+    /// Requires admin authorization
+    /// Claims boosted rewards from a pool, sends `vault_fee_bps` to treasury and the rest to admin.
+    /// The admin (backend) must then swap the AQUA to both pool tokens and call
+    /// `admin_compound_deposit` to complete the compound cycle.
+    ///
+    /// Returns: (total_rewards, fee_amount, compound_amount) — all in AQUA raw units.
+    pub fn claim_and_compound(env: Env, manager: Address, pool_id: u32) -> Result<(i128, i128, i128), Error> {
+        let config = Self::read_token_config(&env)?;
+        Self::require_manager_auth(&env, &manager)?;
+
+        let pool_info = Self::load_pool(&env, pool_id)?;
+
+        Self::require_pool_open(&pool_info)?;
+
+        let contract_address = env.current_contract_address();
+
+        // STEP 1-3: Claim AQUA rewards from the pool and pay the performance fee
+        let (total_rewards, treasury_amount, compound_amount) =
+            Self::claim_vault_pool_rewards(&env, &pool_info, &config.aqua_token)?;
+
+        if total_rewards == 0 {
+            env.events().publish(
+                (symbol_short!("compound"), pool_id),
+                symbol_short!("no_reward"),
+            );
+            return Ok((0, 0, 0));
+        }
+
+        use soroban_sdk::token;
+        let aqua_client = token::Client::new(&env, &config.aqua_token);
+
+        // STEP 4: Transfer remainder to manager wallet for off-chain swap + compound
+        if compound_amount > 0 {
+            aqua_client.transfer(&contract_address, &manager, &(compound_amount as i128));
+        }
+
+        env.events().publish(
+            (symbol_short!("compound"), pool_id),
+            (total_rewards as i128, treasury_amount as i128, compound_amount as i128),
+        );
+
+        Ok((total_rewards as i128, treasury_amount as i128, compound_amount as i128))
+    }
+
+    /// Deposits tokens from admin into an Aquarius pool on behalf of the contract.
+    /// Called by backend after swapping AQUA into the pool tokens.
+    /// This completes the compound cycle started by `claim_and_compound`.
+    ///
+    /// The pool's idle residual is deposited along with `amounts`; whatever
+    /// the pool does not take becomes the new residual, for the next compound.
+    ///
+    /// # Arguments
+    /// * `pool_id` - Pool ID to deposit into
+    /// * `amounts` - Amount of each pool token to deposit (from admin wallet), in
+    ///   `PoolInfo.tokens` order; stableswap pools accept zeros for some tokens
+    pub fn admin_compound_deposit(
+        env: Env,
+        manager: Address,
+        pool_id: u32,
+        amounts: Vec<i128>,
+    ) -> Result<i128, Error> {
+        let config = Self::read_token_config(&env)?;
+        Self::require_manager_auth(&env, &manager)?;
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+
+        Self::require_pool_open(&pool_info)?;
+
+        if amounts.len() != pool_info.tokens.len()
+            || amounts.iter().any(|amount| amount < 0)
+            || !amounts.iter().any(|amount| amount > 0)
+        {
+            return Err(Error::InvalidInput);
+        }
+
+        let contract_address = env.current_contract_address();
+
+        // STEP 1: Transfer the tokens from admin to contract
+        use soroban_sdk::token;
+        for (token, amount) in pool_info.tokens.iter().zip(amounts.iter()) {
+            if amount > 0 {
+                token::Client::new(&env, &token).transfer(&manager, &contract_address, &amount);
+            }
+        }
+
+        // STEP 2: Deposit to the pool together with the idle residual, trimmed
+        // to what the pool's curve accepts (the pool transfers only what it
+        // takes, so offering more would not match the authorized transfers).
+        // Amounts follow the pool's token order, which verified pools store in `tokens`.
+        let idle = Self::read_idle_residual(&env, &pool_info);
+        let mut held = Vec::new(&env);
+        for (amount, idle_amount) in amounts.iter().zip(idle.iter()) {
+            held.push_back(amount.saturating_add(idle_amount));
+        }
+        let offered = strategy_adapter(pool_info.strategy.kind).deposit_amounts(&env, &pool_info, &held);
+        if offered.len() != held.len()
+            || held.iter().zip(offered.iter()).any(|(have, give)| give < 0 || give > have)
+            || !offered.iter().any(|amount| amount > 0)
+        {
+            return Err(Error::InvalidInput);
+        }
+        let (used, lp_shares_minted) = Self::deposit_to_pool(&env, &pool_info, &offered, 0)?;
+        let mut residual = Vec::new(&env);
+        for (amount, taken) in held.iter().zip(used.iter()) {
+            residual.push_back(amount - taken);
+        }
+        Self::write_idle_residual(&env, &pool_info, &residual);
+
+        // STEP 3: Update pool LP tracking
+        pool_info.total_lp_tokens = pool_info
+            .total_lp_tokens
+            .saturating_add(lp_shares_minted as i128);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        // STEP 4: Update compound stats
+        let mut stats = Self::read_compound_stats(&env, pool_id);
+        stats.total_compounded_lp = stats.total_compounded_lp.saturating_add(lp_shares_minted as i128);
+        stats.last_compound_time = env.ledger().timestamp();
+        stats.compound_count = stats.compound_count.saturating_add(1);
+        Self::write_compound_stats(&env, pool_id, &stats);
+        Self::write_pool_snapshot(&env, &pool_info);
+
+        env.events().publish(
+            (symbol_short!("cmp_dep"), pool_id),
+            (lp_shares_minted, pool_info.total_lp_tokens, residual),
+        );
+
+        Ok(lp_shares_minted as i128)
+    }
+
+    /// Internal: Oracle price of `token`, rejecting missing, non-positive or stale prices
+    fn oracle_price(env: &Env, config: &HarvestConfig, token: &Address) -> Result<i128, Error> {
+        match env.try_invoke_contract::<Option<price_oracle::PriceData>, soroban_sdk::Error>(
+            &config.oracle,
+            &Symbol::new(env, "lastprice"),
+            (price_oracle::Asset::Stellar(token.clone()),).into_val(env),
+        ) {
+            Ok(Ok(Some(data)))
+                if data.price > 0
+                    && env.ledger().timestamp().saturating_sub(data.timestamp) <= config.max_price_age =>
+            {
+                Ok(data.price)
+            }
+            _ => Err(Error::OraclePriceUnavailable),
+        }
+    }
+
+    /// Internal: Least `token_out` a swap of `amount_in` of `token_in` may
+    /// return — the oracle value less `max_slippage_bps`
+    fn oracle_min_out(
+        env: &Env,
+        config: &HarvestConfig,
+        token_in: &Address,
+        token_out: &Address,
+        amount_in: i128,
+    ) -> Result<u128, Error> {
+        let price_in = Self::oracle_price(env, config, token_in)?;
+        let price_out = Self::oracle_price(env, config, token_out)?;
+
+        let fair_out = (amount_in as u128)
+            .checked_mul(price_in as u128)
+            .ok_or(Error::InvalidInput)?
+            / price_out as u128;
+        Ok(fair_out.saturating_mul((10000 - config.max_slippage_bps) as u128) / 10000)
+    }
+
+    /// Internal: Sell `amount` of AQUA held by this contract through the
+    /// harvest `sell_pool` for the vault pool token it is paired with.
+    ///
+    /// # Returns
+    /// `(token_idx, amount_out)` — index of the bought token in `pool_info.tokens`
+    fn sell_harvest_rewards(
+        env: &Env,
+        pool_info: &PoolInfo,
+        config: &HarvestConfig,
+        aqua_token: &Address,
+        amount: i128,
+    ) -> Result<(u32, i128), Error> {
+        let sell_pool = config.sell_pool.clone().ok_or(Error::HarvestNotConfigured)?;
+        let sell_tokens = match env.try_invoke_contract::<Vec<Address>, soroban_sdk::Error>(
+            &sell_pool,
+            &Symbol::new(env, "get_tokens"),
+            ().into_val(env),
+        ) {
+            Ok(Ok(tokens)) => tokens,
+            _ => return Err(Error::HarvestNotConfigured),
+        };
+
+        let in_idx = sell_tokens.first_index_of(aqua_token).ok_or(Error::HarvestNotConfigured)?;
+        let (out_idx, token_idx) = sell_tokens
+            .iter()
+            .enumerate()
+            .find_map(|(i, token)| pool_info.tokens.first_index_of(&token).map(|idx| (i as u32, idx)))
+            .ok_or(Error::HarvestNotConfigured)?;
+        let token_out = pool_info.tokens.get(token_idx).unwrap();
+        let min_out = Self::oracle_min_out(env, config, aqua_token, &token_out, amount)?;
+
+        let contract_address = env.current_contract_address();
+        let auth_entries = soroban_sdk::vec![
+            env,
+            InvokerContractAuthEntry::Contract(SubContractInvocation {
+                context: ContractContext {
+                    contract: aqua_token.clone(),
+                    fn_name: Symbol::new(env, "transfer"),
+                    args: (
+                        contract_address.clone(),
+                        sell_pool.clone(),
+                        amount,
+                    ).into_val(env),
+                },
+                sub_invocations: soroban_sdk::vec![env],
+            }),
+        ];
+        env.authorize_as_current_contract(auth_entries);
+
+        let amount_out = AquariusPoolClient::new(env, &sell_pool).swap(
+            &contract_address,
+            &in_idx,
+            &out_idx,
+            &(amount as u128),
+            &min_out,
+        );
+
+        Ok((token_idx, amount_out as i128))
+    }
+
+    /// Internal: Zap `amount` of the vault pool token at `in_idx`, held by this
+    /// contract, into a two-token pool along with its `idle` residual. The
+    /// swap is bounded by the oracle. Leftovers stay in this contract.
+    ///
+    /// # Returns
+    /// `(residual, lp_minted)` — the leftovers, in pool token order
+    fn compound_held_token(
+        env: &Env,
+        pool_info: &PoolInfo,
+        config: &HarvestConfig,
+        in_idx: u32,
+        amount: i128,
+        idle: &Vec<i128>,
+    ) -> Result<(Vec<i128>, u128), Error> {
+        let adapter = strategy_adapter(pool_info.strategy.kind);
+        let out_idx = 1 - in_idx;
+        let swap_amount = adapter.zap_swap_amount(env, pool_info, in_idx, amount)?;
+        let swapped_out = if swap_amount > 0 {
+            let min_out = Self::oracle_min_out(
+                env,
+                config,
+                &pool_info.tokens.get(in_idx).unwrap(),
+                &pool_info.tokens.get(out_idx).unwrap(),
+                swap_amount,
+            )?;
+            adapter.swap(env, pool_info, in_idx, out_idx, swap_amount, min_out) as i128
+        } else {
+            0
+        };
+        let (held_a, held_b) = if in_idx == 0 {
+            (amount - swap_amount, swapped_out)
+        } else {
+            (swapped_out, amount - swap_amount)
+        };
+
+        let (left_a, left_b, lp_minted) = Self::deposit_held_pair(
+            env,
+            pool_info,
+            held_a.saturating_add(idle.get(0).unwrap_or(0)),
+            held_b.saturating_add(idle.get(1).unwrap_or(0)),
+            0,
+        )?;
+        Ok((soroban_sdk::vec![env, left_a, left_b], lp_minted))
+    }
+
+    /// Claims a pool's rewards and compounds them on-chain. Callable by anyone
+    /// once the pool's `min_interval` has passed since its last compound.
+    ///
+    /// The pool's performance fee is paid as in `claim_and_compound`, the caller
+    /// earns `bounty_bps` of the claimed AQUA, and the rest is sold into a pool
+    /// token (see `HarvestConfig`) and deposited. Each swap must return at least
+    /// the oracle value less `max_slippage_bps`. Only two-token pools can be
+    /// harvested: a single-sided deposit into a larger pool is an implicit
+    /// swap the oracle cannot bound.
+    ///
+    /// # Arguments
+    /// * `caller` - Address that receives the bounty
+    /// * `pool_id` - Pool ID to harvest
+    ///
+    /// # Returns
+    /// * `Ok(lp_minted)` - LP added to the pool (0 if there was nothing to claim)
+    /// * `Err(Error::HarvestTooSoon)` before `min_interval` has elapsed
+    /// * `Err(Error::HarvestNotConfigured)` without a `HarvestConfig` or usable sell route,
+    ///   or for a pool of more than two tokens
+    /// * `Err(Error::OraclePriceUnavailable)` if a price is missing or older than `max_price_age`
+    ///
+    /// # Authorization
+    /// Requires caller authorization
+    pub fn harvest(env: Env, caller: Address, pool_id: u32) -> Result<i128, Error> {
+        caller.require_auth();
+
+        let config = Self::read_token_config(&env)?;
+        let harvest_config: HarvestConfig = env
+            .storage()
+            .persistent()
+            .get(&PoolKey::HarvestConfig(pool_id))
+            .ok_or(Error::HarvestNotConfigured)?;
+
+        let mut pool_info = Self::load_pool(&env, pool_id)?;
+
+        Self::require_pool_open(&pool_info)?;
+        if pool_info.tokens.len() != 2 {
+            return Err(Error::HarvestNotConfigured);
+        }
+
+        let now = env.ledger().timestamp();
+        let last_compound = Self::read_compound_stats(&env, pool_id).last_compound_time;
+        if last_compound > 0 && now < last_compound.saturating_add(harvest_config.min_interval) {
+            return Err(Error::HarvestTooSoon);
+        }
+
+        // STEP 1: Claim AQUA rewards and pay the performance fee
+        let (total_rewards, _, remainder) = Self::claim_vault_pool_rewards(&env, &pool_info, &config.aqua_token)?;
+
+        if total_rewards == 0 {
+            env.events().publish(
+                (symbol_short!("harvest"), pool_id),
+                symbol_short!("no_reward"),
+            );
+            return Ok(0);
+        }
+
+        // STEP 2: Pay the caller's bounty out of the remainder
+        let bounty = total_rewards
+            .checked_mul(harvest_config.bounty_bps as u128)
+            .unwrap_or(0)
+            .checked_div(10000)
+            .unwrap_or(0)
+            .min(remainder) as i128;
+        if bounty > 0 {
+            use soroban_sdk::token;
+            token::Client::new(&env, &config.aqua_token).transfer(&env.current_contract_address(), &caller, &bounty);
+        }
+        let to_compound = remainder as i128 - bounty;
+
+        // STEP 3: Turn the AQUA into a vault pool token and add it to the pool,
+        // together with the idle residual of earlier compounds
+        let lp_minted = if to_compound > 0 {
+            let (in_idx, amount_in) = match pool_info.tokens.first_index_of(&config.aqua_token) {
+                Some(idx) => (idx, to_compound),
+                None => Self::sell_harvest_rewards(&env, &pool_info, &harvest_config, &config.aqua_token, to_compound)?,
+            };
+            let idle = Self::read_idle_residual(&env, &pool_info);
+            let (residual, lp_minted) =
+                Self::compound_held_token(&env, &pool_info, &harvest_config, in_idx, amount_in, &idle)?;
+            Self::write_idle_residual(&env, &pool_info, &residual);
+            lp_minted
+        } else {
+            0
+        };
+
+        // STEP 4: Update pool LP tracking and compound stats
+        pool_info.total_lp_tokens = pool_info
+            .total_lp_tokens
+            .saturating_add(lp_minted as i128);
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+
+        let mut stats = Self::read_compound_stats(&env, pool_id);
+        stats.total_compounded_lp = stats.total_compounded_lp.saturating_add(lp_minted as i128);
+        stats.total_harvest_bounties = stats.total_harvest_bounties.saturating_add(bounty);
+        stats.last_compound_time = now;
+        stats.compound_count = stats.compound_count.saturating_add(1);
+        Self::write_compound_stats(&env, pool_id, &stats);
+        Self::write_pool_snapshot(&env, &pool_info);
+
+        env.events().publish(
+            (symbol_short!("harvest"), pool_id),
+            (caller, total_rewards as i128, bounty, lp_minted as i128),
+        );
+
+        Ok(lp_minted as i128)
+    }
+
+    /// Internal: Record the pool's current state as today's snapshot
+    fn write_pool_snapshot(env: &Env, pool_info: &PoolInfo) -> PoolSnapshot {
+        let now = env.ledger().timestamp();
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_info.pool_id))
+            .unwrap_or(0);
+        let price_per_share = if total_shares > 0 {
+            pool_info
+                .total_lp_tokens
+                .checked_mul(PRICE_PER_SHARE_PRECISION)
+                .unwrap_or(0)
+                .checked_div(total_shares)
+                .unwrap_or(0)
+        } else {
+            PRICE_PER_SHARE_PRECISION
+        };
+
+        let snapshot = PoolSnapshot {
+            day: now / 86400,
+            total_lp: pool_info.total_lp_tokens,
+            total_shares,
+            price_per_share,
+            rewards_claimed: Self::read_compound_stats(env, pool_info.pool_id).total_rewards_claimed,
+            taken_at: now,
+        };
+        env.storage()
+            .persistent()
+            .set(&PoolKey::PoolSnapshot(pool_info.pool_id, snapshot.day), &snapshot);
+
+        snapshot
+    }
+
+    /// Records today's snapshot of a pool. Callable by anyone; compounds record
+    /// one automatically, so this only fills days without a compound.
+    ///
+    /// # Arguments
+    /// * `pool_id` - Pool ID
+    ///
+    /// # Returns
+    /// * `Ok(PoolSnapshot)` - The snapshot stored for today
+    pub fn snapshot_pool(env: Env, pool_id: u32) -> Result<PoolSnapshot, Error> {
+        let pool_info = Self::load_pool(&env, pool_id)?;
+        let snapshot = Self::write_pool_snapshot(&env, &pool_info);
+
+        env.events().publish(
+            (symbol_short!("pool_snap"), pool_id),
+            (snapshot.day, snapshot.price_per_share),
+        );
+
+        Ok(snapshot)
+    }
+
+    // ============================================================================
+    // VAULT LIQUIDITY MINING
+    // ============================================================================
+
+    /// Internal: Get a pool's vault reward state
+    fn read_vault_reward_state(env: &Env, pool_id: u32) -> RewardState {
+        env.storage()
+            .persistent()
+            .get(&PoolKey::VaultRewardState(pool_id))
+            .unwrap_or(RewardState {
+                reward_per_token_stored: 0,
+                last_update_time: env.ledger().timestamp(),
+                total_staked: 0,
+                total_rewards_added: 0,
+                total_rewards_claimed: 0,
+            })
+    }
+
+    /// Internal: Get a user's vault reward state for a pool
+    fn read_vault_user_reward(env: &Env, pool_id: u32, user: &Address) -> UserRewardState {
+        env.storage()
+            .persistent()
+            .get(&PoolKey::VaultUserReward(pool_id, user.clone()))
+            .unwrap_or(UserRewardState {
+                staked_balance: 0,
+                reward_per_token_paid: 0,
+                rewards_earned: 0,
+                last_claim_time: 0,
+                total_claimed: 0,
+            })
+    }
+
+    /// Internal: A user's current vault shares in a pool (0 without a position)
+    fn vault_user_shares(env: &Env, user: &Address, pool_id: u32) -> i128 {
+        env.storage()
+            .persistent()
+            .get::<DataKey, UserVaultPosition>(&DataKey::UserVaultPosition(user.clone(), pool_id))
+            .map(|p| p.share_ratio)
+            .unwrap_or(0)
+    }
+
+    /// Internal: A user's vault reward state with rewards accrued on their
+    /// current shares; `staked_balance` is refreshed from the vault position.
+    fn accrue_vault_rewards(env: &Env, user: &Address, pool_id: u32, reward_state: &RewardState) -> UserRewardState {
+        let mut user_state = Self::read_vault_user_reward(env, pool_id, user);
+        user_state.staked_balance = Self::vault_user_shares(env, user, pool_id);
+        user_state.rewards_earned = Self::calculate_user_pending_rewards(reward_state, &user_state);
+        user_state.reward_per_token_paid = reward_state.reward_per_token_stored;
+        user_state
+    }
+
+    /// Internal: A pool's incentive stream (zero rate if never funded)
+    fn read_vault_reward_stream(env: &Env, pool_id: u32) -> VaultRewardStream {
+        env.storage()
+            .persistent()
+            .get(&PoolKey::VaultRewardStream(pool_id))
+            .unwrap_or(VaultRewardStream {
+                reward_rate: 0,
+                period_finish: 0,
+            })
+    }
+
+    /// Internal: A pool's vault reward state with the stream accrued up to now
+    /// over the pool's current vault shares (dead shares excluded). Time with
+    /// no earning shares is not accrued; see `settle_vault_reward_state`.
+    fn accrued_vault_reward_state(env: &Env, pool_id: u32) -> RewardState {
+        Self::accrue_vault_stream(env, pool_id).0
+    }
+
+    /// Internal: Accrue a pool's stream up to now. Also returns the BLUB
+    /// (scaled by REWARD_PRECISION) streamed since the last update while no
+    /// shares were earning, which nobody accrued.
+    fn accrue_vault_stream(env: &Env, pool_id: u32) -> (RewardState, i128) {
+        let mut reward_state = Self::read_vault_reward_state(env, pool_id);
+        let stream = Self::read_vault_reward_stream(env, pool_id);
+        let now = env.ledger().timestamp();
+        let applicable = now.min(stream.period_finish);
+
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
             .unwrap_or(0);
+        let dead_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&PoolKey::DeadShares(pool_id))
+            .unwrap_or(0);
+        let reward_shares = total_shares.saturating_sub(dead_shares);
+
+        let mut unstreamed: i128 = 0;
+        if applicable > reward_state.last_update_time {
+            let elapsed = (applicable - reward_state.last_update_time) as i128;
+            if reward_shares > 0 {
+                reward_state.reward_per_token_stored = reward_state
+                    .reward_per_token_stored
+                    .saturating_add(elapsed.saturating_mul(stream.reward_rate) / reward_shares);
+            } else {
+                unstreamed = elapsed.saturating_mul(stream.reward_rate);
+            }
+        }
+        reward_state.last_update_time = now;
+        reward_state.total_staked = reward_shares;
+        (reward_state, unstreamed)
+    }
+
+    /// Internal: BLUB (scaled by REWARD_PRECISION) set aside for the next funding
+    fn read_vault_reward_unstreamed(env: &Env, pool_id: u32) -> i128 {
         env.storage()
             .persistent()
-            .set(&DataKey::UserDepositedLp(user.clone(), pool_id), &prev_deposited.saturating_add(lp_minted));
+            .get(&PoolKey::VaultRewardUnstreamed(pool_id))
+            .unwrap_or(0)
+    }
+
+    /// Internal: Accrue a pool's stream up to now and set aside what was
+    /// streamed while no shares were earning, so the next
+    /// `fund_vault_rewards` streams it again instead of leaving it stranded
+    /// in `blub_vault_rewards`. The caller stores the returned state.
+    fn settle_vault_reward_state(env: &Env, pool_id: u32) -> RewardState {
+        let (reward_state, unstreamed) = Self::accrue_vault_stream(env, pool_id);
+        if unstreamed > 0 {
+            let total = Self::read_vault_reward_unstreamed(env, pool_id).saturating_add(unstreamed);
+            env.storage()
+                .persistent()
+                .set(&PoolKey::VaultRewardUnstreamed(pool_id), &total);
+        }
+        reward_state
+    }
+
+    /// Internal: Accrue a pool's incentive stream and store the result
+    fn update_vault_reward_state(env: &Env, pool_id: u32) -> RewardState {
+        let reward_state = Self::settle_vault_reward_state(env, pool_id);
+        env.storage()
+            .persistent()
+            .set(&PoolKey::VaultRewardState(pool_id), &reward_state);
+        reward_state
+    }
+
+    /// Internal: Checkpoint a user's vault rewards for a pool.
+    /// This MUST be called BEFORE changing the user's vault shares or the pool's total.
+    fn checkpoint_vault_rewards(env: &Env, user: &Address, pool_id: u32) {
+        if !env.storage().persistent().has(&PoolKey::VaultRewardStream(pool_id)) {
+            return; // Never funded: nothing has accrued yet
+        }
+        let reward_state = Self::update_vault_reward_state(env, pool_id);
+        let user_state = Self::accrue_vault_rewards(env, user, pool_id, &reward_state);
+        env.storage()
+            .persistent()
+            .set(&PoolKey::VaultUserReward(pool_id, user.clone()), &user_state);
+    }
+
+    /// Funds BLUB rewards for a vault pool's depositors, streamed over `duration`.
+    ///
+    /// The amount (plus whatever is left of a running period, and whatever
+    /// was streamed while the pool had no earning shares) is paid out at
+    /// a constant rate until `now + duration`, pro-rata to vault shares held
+    /// over time (dead shares excluded), so depositing just before a funding
+    /// earns nothing extra. The BLUB owed to a pool's depositors is
+    /// `total_rewards_added - total_rewards_claimed` of its vault reward state,
+    /// tracked in the `blub_vault_rewards` ledger bucket apart from the staker
+    /// reward reserve.
+    ///
+    /// # Arguments
+    /// * `funder` - Manager or reward gauge address
+    /// * `pool_id` - Pool ID
+    /// * `amount` - Amount of BLUB rewards to add
+    /// * `duration` - Seconds to stream over (1 to `VAULT_REWARD_MAX_DURATION`)
+    ///
+    /// # Returns
+    /// * `Ok(VaultRewardStream)` - The pool's stream after funding
+    /// * `Err(InvalidInput)` if the amount or duration is invalid or the pool has no depositors
+    ///
+    /// # Authorization
+    /// Requires manager or reward gauge authorization
+    pub fn fund_vault_rewards(
+        env: Env,
+        funder: Address,
+        pool_id: u32,
+        amount: i128,
+        duration: u64,
+    ) -> Result<VaultRewardStream, Error> {
+        let config = Self::read_token_config(&env)?;
+        Self::require_gauge_auth(&env, &funder)?;
+
+        // Hard cap: 100,000 BLUB (7 decimals) per call, same as add_rewards
+        const MAX_BLUB_PER_CALL: i128 = 1_000_000_000_000;
+        if amount <= 0 || amount > MAX_BLUB_PER_CALL || duration == 0 || duration > VAULT_REWARD_MAX_DURATION {
+            return Err(Error::InvalidInput);
+        }
+
+        let pool_info = Self::load_pool(&env, pool_id)?;
+        if !pool_info.active {
+            return Err(Error::PoolNotActive);
+        }
+
+        // Settle the running period up to now before changing the rate
+        let mut reward_state = Self::settle_vault_reward_state(&env, pool_id);
+        if reward_state.total_staked <= 0 {
+            return Err(Error::InvalidInput);
+        }
+
+        use soroban_sdk::token;
+        let blub_client = token::Client::new(&env, &config.blub_token);
+        if blub_client
+            .try_transfer(&funder, &env.current_contract_address(), &amount)
+            .is_err()
+        {
+            return Err(Error::InsufficientBalance);
+        }
+        let now = env.ledger().timestamp();
+        let mut stream = Self::read_vault_reward_stream(&env, pool_id);
+        let leftover = if stream.period_finish > now {
+            ((stream.period_finish - now) as i128).saturating_mul(stream.reward_rate)
+        } else {
+            0
+        };
+        // BLUB streamed while the pool had no earning shares is streamed again
+        let unstreamed = Self::read_vault_reward_unstreamed(&env, pool_id);
+        if unstreamed > 0 {
+            env.storage()
+                .persistent()
+                .remove(&PoolKey::VaultRewardUnstreamed(pool_id));
+        }
+        let leftover = leftover.saturating_add(unstreamed);
+        stream.reward_rate = amount
+            .saturating_mul(REWARD_PRECISION)
+            .saturating_add(leftover)
+            / duration as i128;
+        stream.period_finish = now.saturating_add(duration);
+
+        reward_state.total_rewards_added = reward_state.total_rewards_added.saturating_add(amount);
+        env.storage()
+            .persistent()
+            .set(&PoolKey::VaultRewardState(pool_id), &reward_state);
+        env.storage()
+            .persistent()
+            .set(&PoolKey::VaultRewardStream(pool_id), &stream);
+
+        let mut ledger = Self::read_token_ledger(&env);
+        ledger.blub_vault_rewards = ledger.blub_vault_rewards.saturating_add(amount);
+        Self::write_token_ledger(&env, &ledger);
+
+        env.events().publish(
+            (symbol_short!("vrwd_add"), pool_id),
+            (funder, amount, stream.reward_rate, stream.period_finish),
+        );
+
+        Ok(stream)
+    }
+
+    /// User claims the BLUB rewards earned on their vault shares in a pool.
+    ///
+    /// # Arguments
+    /// * `user` - User address claiming rewards
+    /// * `pool_id` - Pool ID
+    ///
+    /// # Returns
+    /// * `Ok(i128)` - Amount of rewards claimed
+    /// * `Err(NoRewardsToClaim)` - If no rewards available
+    pub fn claim_vault_rewards(env: Env, user: Address, pool_id: u32) -> Result<i128, Error> {
+        user.require_auth();
+
+        let config = Self::read_token_config(&env)?;
+        let now = env.ledger().timestamp();
+
+        let mut reward_state = Self::settle_vault_reward_state(&env, pool_id);
+        let mut user_state = Self::accrue_vault_rewards(&env, &user, pool_id, &reward_state);
+        let pending = user_state.rewards_earned;
+        if pending <= 0 {
+            return Err(Error::NoRewardsToClaim);
+        }
+
+        let mut ledger = Self::read_token_ledger(&env);
+        Self::debit_bucket(&mut ledger.blub_vault_rewards, pending)?;
+        Self::write_token_ledger(&env, &ledger);
+
+        user_state.rewards_earned = 0;
+        user_state.last_claim_time = now;
+        user_state.total_claimed = user_state.total_claimed.saturating_add(pending);
+        reward_state.total_rewards_claimed = reward_state.total_rewards_claimed.saturating_add(pending);
+
+        env.storage()
+            .persistent()
+            .set(&PoolKey::VaultRewardState(pool_id), &reward_state);
+        env.storage()
+            .persistent()
+            .set(&PoolKey::VaultUserReward(pool_id, user.clone()), &user_state);
+
+        use soroban_sdk::token;
+        let blub_client = token::Client::new(&env, &config.blub_token);
+        if blub_client
+            .try_transfer(&env.current_contract_address(), &user, &pending)
+            .is_err()
+        {
+            return Err(Error::InsufficientBalance);
+        }
+
+        env.events().publish(
+            (symbol_short!("vrwd_clm"), user, pool_id),
+            (pending, user_state.total_claimed),
+        );
+
+        Ok(pending)
+    }
+
+    // ============================================================================
+    // QUERY FUNCTIONS - ICE & Vault
+    // ============================================================================
+
+    /// Gets pending AQUA available for ICE locking.
+    pub fn get_pending_aqua_for_ice(env: Env) -> Result<i128, Error> {
+        let global_state: GlobalState = env
+            .storage()
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
+
+        Ok(global_state.pending_aqua_for_ice)
+    }
+
+    /// Gets all 4 ICE token balances.
+    pub fn get_all_ice_balances(env: Env) -> Result<(i128, i128, i128, i128), Error> {
+        let global_state: GlobalState = env
+            .storage()
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
+
+        Ok((
+            global_state.ice_balance,
+            global_state.govern_ice_balance,
+            global_state.upvote_ice_balance,
+            global_state.downvote_ice_balance,
+        ))
+    }
+
+    /// Gets upvoteICE balance for voting.
+    pub fn get_upvote_ice_balance(env: Env) -> Result<i128, Error> {
+        let global_state: GlobalState = env
+            .storage()
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
+
+        Ok(global_state.upvote_ice_balance)
+    }
+
+    /// Gets ICE lock authorization by ID.
+    pub fn get_ice_lock_authorization(env: Env, lock_id: u64) -> Result<IceLockAuthorization, Error> {
+        Self::load_ice_lock(&env, lock_id)
+    }
+
+    /// Gets the ICE locks in `state` among lock IDs `start` to `start + limit`
+    /// (exclusive), oldest first.
+    pub fn get_ice_locks_by_state(env: Env, state: IceLockState, start: u64, limit: u32) -> Result<Vec<IceLockAuthorization>, Error> {
+        let global_state: GlobalState = env
+            .storage()
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
+        let end = start.saturating_add(limit as u64).min(global_state.ice_lock_counter);
+
+        let mut locks = Vec::new(&env);
+        for lock_id in start..end {
+            if let Ok(lock) = Self::load_ice_lock(&env, lock_id) {
+                if lock.state == state {
+                    locks.push_back(lock);
+                }
+            }
+        }
+        Ok(locks)
+    }
+
+    /// Gets the ICE synced in but not yet attributed to a lock by `confirm_ice_lock`.
+    pub fn get_unattributed_ice(env: Env) -> IceAmounts {
+        Self::read_unattributed_ice(&env)
+    }
+
+    /// Gets the AQUA reserved by Authorized ICE locks.
+    pub fn get_authorized_ice_aqua(env: Env) -> i128 {
+        Self::read_authorized_ice_aqua(&env)
+    }
+
+    /// Gets the open ICE locks unlocking on days `from_day` to
+    /// `from_day + days` (exclusive), soonest first. `days` is capped at
+    /// `ICE_MATURITY_PAGE_DAYS`; page on from the day after the window.
+    pub fn get_ice_maturities(env: Env, from_day: u64, days: u64) -> Vec<IceMaturity> {
+        let mut maturities = Vec::new(&env);
+        for day in from_day..from_day.saturating_add(days.min(ICE_MATURITY_PAGE_DAYS)) {
+            maturities.append(&Self::read_ice_maturity_day(&env, day));
+        }
+        maturities
+    }
+
+    /// Gets the number of open ICE locks in the maturity ledger.
+    pub fn get_ice_maturity_count(env: Env) -> u32 {
+        Self::read_ice_maturity_count(&env)
+    }
+
+    /// Gets a day no open ICE lock unlocks before (`None` if none was ever
+    /// recorded). Start `get_upcoming_ice_unlocks` here to include matured
+    /// locks not yet unlocked or renewed.
+    pub fn get_earliest_ice_maturity_day(env: Env) -> Option<u64> {
+        env.storage().instance().get(&IceKey::EarliestMaturityDay)
+    }
+
+    /// Gets the open ICE locks unlocking from `from_day` up to `within_seconds`
+    /// from now, and their total AQUA, reading at most `ICE_MATURITY_PAGE_DAYS`
+    /// days per call.
+    ///
+    /// # Returns
+    /// `(maturities, total_aqua, next_day)` — continue from `next_day` while it
+    /// is not past the window's last day
+    pub fn get_upcoming_ice_unlocks(env: Env, from_day: u64, within_seconds: u64) -> (Vec<IceMaturity>, i128, u64) {
+        let until = env.ledger().timestamp().saturating_add(within_seconds);
+        let end_day = (until / 86400)
+            .saturating_add(1)
+            .min(from_day.saturating_add(ICE_MATURITY_PAGE_DAYS));
+
+        let mut upcoming = Vec::new(&env);
+        let mut total_aqua = 0i128;
+        for day in from_day..end_day {
+            for maturity in Self::read_ice_maturity_day(&env, day).iter() {
+                if maturity.unlocks_at > until {
+                    break;
+                }
+                total_aqua = total_aqua.saturating_add(maturity.aqua_amount);
+                upcoming.push_back(maturity);
+            }
+        }
+        (upcoming, total_aqua, end_day.max(from_day))
+    }
+
+    /// Gets the ICE renewal policy, if set.
+    pub fn get_ice_renewal_policy(env: Env) -> Option<IceRenewalPolicy> {
+        env.storage().instance().get(&IceKey::RenewalPolicy)
+    }
+
+    /// Gets the ICE vote allocation registry resolved against the last synced
+    /// upvoteICE balance.
+    pub fn get_vote_allocations(env: Env) -> Result<IceVoteReport, Error> {
+        let upvote_ice_balance = Self::get_upvote_ice_balance(env.clone())?;
+        Ok(Self::resolve_vote_targets(&env, &Self::read_vote_targets(&env), upvote_ice_balance))
+    }
+
+    /// Gets the pools BLUB stakers can vote on.
+    pub fn get_vote_pools(env: Env) -> Vec<Address> {
+        Self::read_vote_pools(&env)
+    }
+
+    /// Gets the current ICE voting epoch (timestamp / `ICE_VOTE_EPOCH_SECONDS`)
+    /// and the time it ends.
+    pub fn get_current_vote_epoch(env: Env) -> (u64, u64) {
+        let epoch = env.ledger().timestamp() / ICE_VOTE_EPOCH_SECONDS;
+        (epoch, (epoch + 1) * ICE_VOTE_EPOCH_SECONDS)
+    }
+
+    /// Gets the staked-BLUB weight voted for each pool in an epoch.
+    pub fn get_ice_vote_tally(env: Env, epoch: u64) -> Vec<PoolVoteTally> {
+        Self::read_vote_tally(&env, epoch)
+    }
+
+    /// Gets a staker's ballot for an epoch, if cast.
+    pub fn get_user_ice_vote(env: Env, user: Address, epoch: u64) -> Option<UserIceVote> {
+        env.storage().persistent().get(&IceKey::UserVote(epoch, user))
+    }
+
+    /// Gets the last epoch applied to the ICE vote allocation registry.
+    pub fn get_last_finalized_vote_epoch(env: Env) -> Option<u64> {
+        env.storage().instance().get(&IceKey::LastFinalizedEpoch)
+    }
+
+    /// Gets one pool's entry in the ICE vote allocation registry, if any.
+    pub fn get_pool_vote_allocation(env: Env, pool: Address) -> Result<Option<IceVoteAllocation>, Error> {
+        let report = Self::get_vote_allocations(env)?;
+        Ok(report.allocations.iter().find(|allocation| allocation.pool == pool))
+    }
+
+    /// Gets pool information by ID.
+    pub fn get_pool_info(env: Env, pool_id: u32) -> Result<PoolInfo, Error> {
+        Self::load_pool(&env, pool_id)
+    }
+
+    /// Gets total vault shares for a pool (v1.8.0+).
+    pub fn get_vault_total_shares(env: Env, pool_id: u32) -> i128 {
+        env.storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0)
+    }
+
+    /// Gets user's vault position in a specific pool.
+    pub fn get_user_vault_position(env: Env, user: Address, pool_id: u32) -> Result<UserVaultPosition, Error> {
+        env.storage()
+            .persistent()
+            .get(&DataKey::UserVaultPosition(user, pool_id))
+            .ok_or(Error::PositionNotFound)
+    }
+
+    /// Gets total number of vault pools.
+    /// Gets compound stats for a vault pool.
+    pub fn get_pool_compound_stats(env: Env, pool_id: u32) -> PoolCompoundStats {
+        Self::read_compound_stats(&env, pool_id)
+    }
+
+    /// Gets a pool's fee settings (the global vault fee if none were set).
+    pub fn get_pool_fees(env: Env, pool_id: u32) -> Result<PoolFeeConfig, Error> {
+        Self::read_pool_fees(&env, pool_id)
+    }
+
+    /// Gets a pool's `harvest` settings, if permissionless harvesting is enabled.
+    pub fn get_harvest_config(env: Env, pool_id: u32) -> Option<HarvestConfig> {
+        env.storage().persistent().get(&PoolKey::HarvestConfig(pool_id))
+    }
 
-        env.events().publish(
-            (symbol_short!("vault_dep"), user.clone(), pool_id),
-            (amount_in, 0i128, lp_shares_minted, user_position.share_ratio),
-        );
+    /// Gets a pool's snapshot for a day (timestamp / 86400), if one was recorded.
+    pub fn get_pool_snapshot(env: Env, pool_id: u32, day: u64) -> Option<PoolSnapshot> {
+        env.storage().persistent().get(&PoolKey::PoolSnapshot(pool_id, day))
+    }
 
-        Ok(())
+    /// Gets a pool's recorded snapshots from `from_day` to `to_day` inclusive,
+    /// oldest first. Days without a snapshot are skipped. At most
+    /// `POOL_HISTORY_MAX_DAYS` days are read per call; page longer ranges by
+    /// calling again from the day after the returned window.
+    pub fn get_pool_history(env: Env, pool_id: u32, from_day: u64, to_day: u64) -> Vec<PoolSnapshot> {
+        let to_day = to_day.min(from_day.saturating_add(POOL_HISTORY_MAX_DAYS - 1));
+
+        let mut history = Vec::new(&env);
+        for day in from_day..=to_day {
+            if let Some(snapshot) = env
+                .storage()
+                .persistent()
+                .get::<PoolKey, PoolSnapshot>(&PoolKey::PoolSnapshot(pool_id, day))
+            {
+                history.push_back(snapshot);
+            }
+        }
+        history
     }
 
-    /// Withdraws tokens from a vault pool.
-    /// User withdraws their share, contract removes liquidity from Aquarius pool.
+    /// Gets a pool's trailing APR over the last `days` days (1-365).
     ///
-    /// # Arguments
-    /// * `user` - User address
-    /// * `pool_id` - Pool ID
-    /// * `share_percent` - Percentage of user's position to withdraw (0-10000 = 0-100%)
-    /// * `min_a` - Minimum amount of token_a to receive (slippage protection)
-    /// * `min_b` - Minimum amount of token_b to receive (slippage protection)
+    /// Compares the latest snapshot of the last `POOL_APR_PROBE_DAYS` days with
+    /// the oldest snapshot of the first `POOL_APR_PROBE_DAYS` days of the window,
+    /// and annualizes the price-per-share growth linearly over the time between
+    /// them. Reads at most `2 * POOL_APR_PROBE_DAYS` days whatever the window.
     ///
-    /// # Authorization
-    /// Requires user authorization
-    pub fn vault_withdraw(
-        env: Env,
-        user: Address,
-        pool_id: u32,
-        share_percent: u32,
-        min_a: u128,
-        min_b: u128,
-    ) -> Result<(), Error> {
-        user.require_auth();
+    /// # Returns
+    /// * `Some(PoolApr)` - Yield between the two snapshots
+    /// * `None` if no distinct snapshots are found at the two ends
+    pub fn get_pool_trailing_apr(env: Env, pool_id: u32, days: u32) -> Option<PoolApr> {
+        if days == 0 || days > 365 {
+            return None;
+        }
+        let today = env.ledger().timestamp() / 86400;
+        let window_start = today.saturating_sub(days as u64);
 
-        if share_percent == 0 || share_percent > 10000 {
-            return Err(Error::InvalidInput);
+        let read = |day: u64| -> Option<PoolSnapshot> {
+            env.storage().persistent().get(&PoolKey::PoolSnapshot(pool_id, day))
+        };
+        let start = (window_start..=today)
+            .take(POOL_APR_PROBE_DAYS as usize)
+            .find_map(read)?;
+        let end = (window_start..=today)
+            .rev()
+            .take(POOL_APR_PROBE_DAYS as usize)
+            .find_map(read)?;
+
+        let elapsed = end.taken_at.saturating_sub(start.taken_at) as i128;
+        if end.day <= start.day || elapsed == 0 || start.price_per_share <= 0 {
+            return None;
         }
 
-        let mut pool_info: PoolInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
+        // (end / start - 1) in bps, scaled from `elapsed` seconds to a year
+        let growth_bps = (end.price_per_share - start.price_per_share)
+            .checked_mul(10000)?
+            .checked_div(start.price_per_share)?;
+        let apr_bps = growth_bps.checked_mul(365 * 86400)?.checked_div(elapsed)?;
+
+        Some(PoolApr {
+            from_day: start.day,
+            to_day: end.day,
+            start_price_per_share: start.price_per_share,
+            end_price_per_share: end.price_per_share,
+            apr_bps,
+        })
+    }
 
-        let mut user_position: UserVaultPosition = env
+    /// Gets the pool tokens (pool token order) left over by earlier compounds,
+    /// which the next compound deposits first.
+    pub fn get_pool_idle_residual(env: Env, pool_id: u32) -> Result<Vec<i128>, Error> {
+        let pool_info = Self::load_pool(&env, pool_id)?;
+        Ok(Self::read_idle_residual(&env, &pool_info))
+    }
+
+    /// Gets a pool's vault reward state (liquidity-mining rewards on vault
+    /// shares), accrued up to now.
+    pub fn get_vault_reward_state(env: Env, pool_id: u32) -> RewardState {
+        Self::accrued_vault_reward_state(&env, pool_id)
+    }
+
+    /// Gets a pool's incentive stream: rate and end of the current period.
+    pub fn get_vault_reward_stream(env: Env, pool_id: u32) -> VaultRewardStream {
+        Self::read_vault_reward_stream(&env, pool_id)
+    }
+
+    /// Gets the BLUB streamed so far while a pool had no earning shares,
+    /// which the next `fund_vault_rewards` streams again.
+    pub fn get_vault_reward_unstreamed(env: Env, pool_id: u32) -> i128 {
+        let (_, unstreamed) = Self::accrue_vault_stream(&env, pool_id);
+        Self::read_vault_reward_unstreamed(&env, pool_id).saturating_add(unstreamed) / REWARD_PRECISION
+    }
+
+    /// Gets a user's vault reward state for a pool, with pending rewards
+    /// accrued up to now in `rewards_earned`.
+    pub fn get_user_vault_rewards(env: Env, user: Address, pool_id: u32) -> UserRewardState {
+        let reward_state = Self::accrued_vault_reward_state(&env, pool_id);
+        Self::accrue_vault_rewards(&env, &user, pool_id, &reward_state)
+    }
+
+    /// Gets user's compound gains for a specific pool.
+    /// Returns (current_lp, deposited_lp, compound_gain_lp).
+    pub fn get_user_compound_gains(env: Env, user: Address, pool_id: u32) -> (i128, i128, i128) {
+        let pool_info = Self::load_pool(&env, pool_id).unwrap_or(PoolInfo {
+            pool_id,
+            pool_address: env.current_contract_address(),
+            token_a: env.current_contract_address(),
+            token_b: env.current_contract_address(),
+            tokens: Vec::new(&env),
+            share_token: env.current_contract_address(),
+            total_lp_tokens: 0,
+            active: false,
+            added_at: 0,
+            deprecated: false,
+            strategy: PoolStrategy {
+                kind: StrategyKind::AquariusConstantProduct,
+                params: Bytes::new(&env),
+            },
+            verified: false,
+        });
+
+        let user_position: UserVaultPosition = env
             .storage()
             .persistent()
             .get(&DataKey::UserVaultPosition(user.clone(), pool_id))
-            .ok_or(Error::PositionNotFound)?;
-
-        if !user_position.active {
-            return Err(Error::PositionNotFound);
-        }
+            .unwrap_or(UserVaultPosition {
+                user: user.clone(),
+                pool_id,
+                share_ratio: 0,
+                deposited_at: 0,
+                active: false,
+            });
 
-        // STEP 1: Convert user's shares to LP amount
+        // Convert shares to LP: user_lp = shares * total_lp / total_shares
         let total_shares: i128 = env
             .storage()
             .persistent()
             .get(&DataKey::VaultTotalShares(pool_id))
             .unwrap_or(0);
 
-        let user_shares = user_position.share_ratio;
-
-        if user_shares <= 0 || total_shares <= 0 {
-            return Err(Error::InsufficientBalance);
-        }
+        let current_lp = if total_shares > 0 {
+            user_position.share_ratio
+                .checked_mul(pool_info.total_lp_tokens)
+                .unwrap_or(0)
+                .checked_div(total_shares)
+                .unwrap_or(0)
+        } else {
+            0
+        };
 
-        // User's LP = shares * total_lp / total_shares
-        let user_total_lp = user_shares
-            .checked_mul(pool_info.total_lp_tokens)
-            .unwrap_or(0)
-            .checked_div(total_shares)
+        let deposited_lp: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::UserDepositedLp(user, pool_id))
             .unwrap_or(0);
 
-        if user_total_lp <= 0 {
-            return Err(Error::InsufficientBalance);
-        }
-
-        // Calculate LP and shares to withdraw based on percentage
-        let lp_to_withdraw = user_total_lp
-            .checked_mul(share_percent as i128)
-            .unwrap_or(0)
-            .checked_div(10000)
-            .unwrap_or(0);
+        let compound_gain = current_lp.saturating_sub(deposited_lp);
 
-        let shares_to_burn = user_shares
-            .checked_mul(share_percent as i128)
-            .unwrap_or(0)
-            .checked_div(10000)
-            .unwrap_or(0);
+        (current_lp, deposited_lp, compound_gain)
+    }
 
-        if lp_to_withdraw <= 0 {
-            return Err(Error::InvalidInput);
-        }
+    /// Gets total number of vault pools.
+    pub fn get_pool_count(env: Env) -> Result<u32, Error> {
+        let global_state: GlobalState = env
+            .storage()
+            .instance()
+            .get(&DataKey::GlobalState)
+            .ok_or(Error::NotInitialized)?;
 
-        // STEP 2: Authorize Aquarius pool to burn LP tokens from this contract
-        let contract_address = env.current_contract_address();
+        Ok(global_state.pool_count)
+    }
 
-        // Determine Aquarius pool token ordering for min_amounts and withdrawn amounts.
-        let pool_tokens_result = env.try_invoke_contract::<Vec<Address>, soroban_sdk::Error>(
-            &pool_info.pool_address,
-            &Symbol::new(&env, "get_tokens"),
-            ().into_val(&env),
-        );
-        let token_a_is_idx0 = match pool_tokens_result {
-            Ok(Ok(ref tokens)) if tokens.len() >= 2 => {
-                tokens.get(0).unwrap() == pool_info.token_a
-            }
-            _ => true,
-        };
+    /// Gets a pool's emergency exit state, or None if it never exited.
+    pub fn get_pool_exit_state(env: Env, pool_id: u32) -> Option<PoolExitState> {
+        Self::read_pool_exit(&env, pool_id)
+    }
 
-        // Note: Aquarius pool calls burn(from, amount), not transfer
-        let auth_entries = soroban_sdk::vec![
-            &env,
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: pool_info.share_token.clone(),
-                    fn_name: Symbol::new(&env, "burn"),
-                    args: (
-                        contract_address.clone(),
-                        lp_to_withdraw,
-                    ).into_val(&env),
-                },
-                sub_invocations: soroban_sdk::vec![&env],
-            }),
-        ];
-        env.authorize_as_current_contract(auth_entries);
+    /// Gets a pool's deposit caps (zero = no cap).
+    pub fn get_pool_limits(env: Env, pool_id: u32) -> PoolLimits {
+        Self::read_pool_limits(&env, pool_id)
+    }
 
-        // STEP 3: Withdraw from Aquarius pool
-        let aquarius_pool = AquariusPoolClient::new(&env, &pool_info.pool_address);
+    /// Gets how much more LP `user` can add to a pool before hitting a cap.
+    /// The UI converts `remaining_lp` into token amounts to cap deposit input.
+    pub fn get_pool_capacity(env: Env, pool_id: u32, user: Address) -> Result<PoolCapacity, Error> {
+        let pool_info = Self::load_pool(&env, pool_id)?;
+        let limits = Self::read_pool_limits(&env, pool_id);
 
-        // Build min_amounts in Aquarius token order.
-        let mut min_amounts = Vec::new(&env);
-        if token_a_is_idx0 {
-            min_amounts.push_back(min_a);
-            min_amounts.push_back(min_b);
+        let total_shares: i128 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0);
+        let user_shares = env
+            .storage()
+            .persistent()
+            .get::<DataKey, UserVaultPosition>(&DataKey::UserVaultPosition(user, pool_id))
+            .map(|p| p.share_ratio)
+            .unwrap_or(0);
+        let user_lp = if total_shares > 0 {
+            user_shares
+                .checked_mul(pool_info.total_lp_tokens)
+                .unwrap_or(0)
+                .checked_div(total_shares)
+                .unwrap_or(0)
         } else {
-            min_amounts.push_back(min_b); // token_b at Aquarius idx0
-            min_amounts.push_back(min_a); // token_a at Aquarius idx1
-        }
-
-        let withdrawn_amounts = aquarius_pool.withdraw(
-            &contract_address,
-            &(lp_to_withdraw as u128),
-            &min_amounts,
-        );
-
-        // STEP 4: Transfer tokens to user, mapping Aquarius indices back to token_a/token_b.
-        use soroban_sdk::token;
-        let token_a_client = token::Client::new(&env, &pool_info.token_a);
-        let token_b_client = token::Client::new(&env, &pool_info.token_b);
+            0
+        };
 
-        let amount_a = if token_a_is_idx0 {
-            withdrawn_amounts.get(0).unwrap_or(0)
+        let remaining_total_lp = if limits.max_total_lp > 0 {
+            limits.max_total_lp.saturating_sub(pool_info.total_lp_tokens).max(0)
         } else {
-            withdrawn_amounts.get(1).unwrap_or(0)
+            i128::MAX
         };
-        let amount_b = if token_a_is_idx0 {
-            withdrawn_amounts.get(1).unwrap_or(0)
+        let remaining_user_lp = if limits.max_user_lp > 0 {
+            limits.max_user_lp.saturating_sub(user_lp).max(0)
         } else {
-            withdrawn_amounts.get(0).unwrap_or(0)
+            i128::MAX
         };
 
-        token_a_client.transfer(&contract_address, &user, &(amount_a as i128));
-        token_b_client.transfer(&contract_address, &user, &(amount_b as i128));
-
-        // STEP 4: Update pool total LP
-        pool_info.total_lp_tokens = pool_info.total_lp_tokens.saturating_sub(lp_to_withdraw);
+        Ok(PoolCapacity {
+            pool_id,
+            limits,
+            total_lp: pool_info.total_lp_tokens,
+            user_lp,
+            remaining_total_lp,
+            remaining_user_lp,
+            remaining_lp: remaining_total_lp.min(remaining_user_lp),
+        })
+    }
 
-        // STEP 5: Burn shares and update totals
-        let remaining_shares = user_shares.saturating_sub(shares_to_burn);
+    /// Lists vault pools with IDs in `[start, start + limit)` that match `filter`.
+    ///
+    /// Pages over pool IDs rather than matches, so a page may hold fewer than
+    /// `limit` pools; continue from `start + limit` until it reaches
+    /// `get_pool_count()`. `limit` is capped at 50.
+    pub fn get_pools(env: Env, start: u32, limit: u32, filter: PoolFilter) -> Result<Vec<PoolInfo>, Error> {
+        let pool_count = Self::get_pool_count(env.clone())?;
+        let end = start.saturating_add(limit.min(50)).min(pool_count);
 
-        if remaining_shares > 0 {
-            user_position.share_ratio = remaining_shares;
-        } else {
-            user_position.share_ratio = 0;
-            user_position.active = false;
+        let mut pools = Vec::new(&env);
+        for pool_id in start..end {
+            let pool_info = match Self::load_pool(&env, pool_id) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+            let matches = match filter {
+                PoolFilter::All => true,
+                PoolFilter::Active => pool_info.active && !pool_info.deprecated,
+                PoolFilter::Paused => !pool_info.active && !pool_info.deprecated,
+                PoolFilter::Deprecated => pool_info.deprecated,
+            };
+            if matches {
+                pools.push_back(pool_info);
+            }
         }
 
-        let new_total_shares = total_shares.saturating_sub(shares_to_burn);
+        Ok(pools)
+    }
 
+    /// Gets the LP deficit recorded by the last `sync_pool_lp` (0 if none).
+    pub fn get_pool_lp_deficit(env: Env, pool_id: u32) -> i128 {
         env.storage()
             .persistent()
-            .set(&DataKey::PoolInfo(pool_id), &pool_info);
-        env.storage()
-            .persistent()
-            .set(&DataKey::UserVaultPosition(user.clone(), pool_id), &user_position);
-        env.storage()
-            .persistent()
-            .set(&DataKey::VaultTotalShares(pool_id), &new_total_shares);
-
-        // Proportionally reduce user's deposited LP tracking
-        let prev_deposited: i128 = env
-            .storage()
-            .persistent()
-            .get(&DataKey::UserDepositedLp(user.clone(), pool_id))
-            .unwrap_or(0);
-        if prev_deposited > 0 && user_total_lp > 0 {
-            // Reduce proportionally: deposited *= (1 - withdrawn/total)
-            let withdrawn_fraction = lp_to_withdraw
-                .checked_mul(prev_deposited)
-                .unwrap_or(0)
-                .checked_div(user_total_lp)
-                .unwrap_or(0);
-            let new_deposited = prev_deposited.saturating_sub(withdrawn_fraction);
-            env.storage()
-                .persistent()
-                .set(&DataKey::UserDepositedLp(user.clone(), pool_id), &new_deposited);
-        }
-
-        env.events().publish(
-            (symbol_short!("vault_wd"), user.clone(), pool_id),
-            (lp_to_withdraw, amount_a, amount_b),
-        );
-
-        Ok(())
+            .get(&LedgerKey::PoolLpDeficit(pool_id))
+            .unwrap_or(0)
     }
 
-    /// Claims boosted rewards from a pool and auto-compounds.
-    /// Treasury cut is `config.vault_fee_bps` (default 1500 = 15%); remainder auto-compounds.
-    /// Pool 0 (POL+vault mixed): backend applies an additional cut on the POL share
-    /// in `handleStakingRewardDistribution` so POL effectively pays 30%.
-    /// Backend cron calls this 4x daily using ICE balance for boost.
+    // ============================================================================
+    // Invariant Checking (v1.9.0)
+    // ============================================================================
+
+    /// Checks the contract-wide accounting invariants and returns a report.
     ///
-    /// # Arguments
-    /// * `pool_id` - Pool ID to claim rewards from
+    /// * BLUB balance covers `RewardState.total_staked` plus unclaimed staker
+    ///   rewards, unclaimed vault incentives and BLUB held idle by vault pools
+    /// * `RewardState.total_staked` equals the running sum of registered
+    ///   stakers' balances (once the staker registry is complete)
+    /// * Every pool with a complete depositor registry has
+    ///   `VaultTotalShares` minus dead shares equal to its depositors' shares
+    /// * The first `INVARIANT_POOL_PAGE_LIMIT` pools hold enough share tokens
+    ///   to cover `total_lp_tokens`
     ///
-    /// # Authorization
-    /// Requires admin authorization
-    /// Claims boosted rewards from a pool, sends `vault_fee_bps` to treasury and the rest to admin.
-    /// The admin (backend) must then swap the AQUA to both pool tokens and call
-    /// `admin_compound_deposit` to complete the compound cycle.
+    /// Costs a bounded number of reads. Later pools' LP backing is checked
+    /// with `check_pool_invariants`.
     ///
-    /// Returns: (total_rewards, treasury_amount, compound_amount) — all in AQUA raw units.
-    pub fn claim_and_compound(env: Env, manager: Address, pool_id: u32) -> Result<(i128, i128, i128), Error> {
-        let config = Self::get_config(env.clone())?;
-        Self::require_manager_auth(&env, &manager)?;
+    /// # Returns
+    /// * `Ok(InvariantReport)` - Aggregate values and pass/fail flags
+    /// * `Err(Error::NotInitialized)` if contract is not initialized
+    pub fn check_invariants(env: Env) -> Result<InvariantReport, Error> {
+        Self::build_invariant_report(&env)
+    }
 
-        let pool_info: PoolInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
+    /// Checks vault pools with IDs in `[start, start + limit)`.
+    ///
+    /// Each pool's share-token balance must cover `PoolInfo.total_lp_tokens`
+    /// (plus POL LP when the pool is the POL pool), and its depositors'
+    /// shares must add up to `VaultTotalShares` minus dead shares. `limit` is
+    /// capped at `INVARIANT_POOL_PAGE_LIMIT`.
+    pub fn check_pool_invariants(env: Env, start: u32, limit: u32) -> Result<Vec<PoolInvariantReport>, Error> {
+        let pool_count = Self::get_pool_count(env.clone())?;
+        let end = start
+            .saturating_add(limit.min(INVARIANT_POOL_PAGE_LIMIT))
+            .min(pool_count);
+
+        let mut pools = Vec::new(&env);
+        for pool_id in start..end {
+            if let Some(report) = Self::pool_invariant_report(&env, pool_id)? {
+                pools.push_back(report);
+            }
+        }
 
-        if !pool_info.active {
-            return Err(Error::PoolNotActive);
+        Ok(pools)
+    }
+
+    /// Sums the staked balances of registered stakers `[start, start + limit)`.
+    ///
+    /// `ok` is set once the registry is complete and the running staked sum
+    /// equals `RewardState.total_staked`; the page sums over all pages must
+    /// equal that running sum. `limit` is capped at `INVARIANT_USER_PAGE_LIMIT`.
+    pub fn check_staker_invariants(env: Env, start: u32, limit: u32) -> RegistrySumPage {
+        let count: u32 = env.storage().instance().get(&LedgerKey::StakerCount).unwrap_or(0);
+        let end = start
+            .saturating_add(limit.min(INVARIANT_USER_PAGE_LIMIT))
+            .min(count);
+
+        let mut sum: i128 = 0;
+        for i in start..end {
+            if let Some(user) = env
+                .storage()
+                .persistent()
+                .get::<LedgerKey, Address>(&LedgerKey::StakerByIndex(i))
+            {
+                sum = sum.saturating_add(Self::get_user_reward_state(&env, &user).staked_balance);
+            }
         }
 
-        let contract_address = env.current_contract_address();
+        let complete = env
+            .storage()
+            .instance()
+            .get(&LedgerKey::StakerRegistryComplete)
+            .unwrap_or(false);
+        let tracked_sum = Self::staked_balance_sum(&env);
+        let expected = Self::get_reward_state(&env).total_staked;
+
+        RegistrySumPage {
+            start,
+            next: end,
+            count,
+            complete,
+            sum,
+            tracked_sum,
+            expected,
+            ok: complete && tracked_sum == expected,
+        }
+    }
 
-        // STEP 1: Claim AQUA rewards from Aquarius pool
-        let aquarius_pool = AquariusPoolClient::new(&env, &pool_info.pool_address);
-        let total_rewards = aquarius_pool.claim(&contract_address);
+    /// Sums the vault shares of a pool's registered depositors `[start, start + limit)`.
+    ///
+    /// `ok` is set once the pool's registry is complete and the running share
+    /// sum equals `VaultTotalShares` minus dead shares; the page sums over all
+    /// pages must equal that running sum. `limit` is capped at
+    /// `INVARIANT_USER_PAGE_LIMIT`.
+    pub fn check_vault_share_invariants(env: Env, pool_id: u32, start: u32, limit: u32) -> RegistrySumPage {
+        let count: u32 = env
+            .storage()
+            .persistent()
+            .get(&LedgerKey::VaultUserCount(pool_id))
+            .unwrap_or(0);
+        let end = start
+            .saturating_add(limit.min(INVARIANT_USER_PAGE_LIMIT))
+            .min(count);
 
-        if total_rewards == 0 {
-            env.events().publish(
-                (symbol_short!("compound"), pool_id),
-                symbol_short!("no_reward"),
-            );
-            return Ok((0, 0, 0));
+        let mut sum: i128 = 0;
+        for i in start..end {
+            let user: Option<Address> = env
+                .storage()
+                .persistent()
+                .get(&LedgerKey::VaultUserByIndex(pool_id, i));
+            if let Some(user) = user {
+                let position: Option<UserVaultPosition> = env
+                    .storage()
+                    .persistent()
+                    .get(&DataKey::UserVaultPosition(user, pool_id));
+                if let Some(position) = position {
+                    sum = sum.saturating_add(position.share_ratio);
+                }
+            }
         }
 
-        // STEP 2: Split rewards — `vault_fee_bps` to treasury, remainder to admin for compounding
-        let treasury_amount = (total_rewards as u128)
-            .checked_mul(config.vault_fee_bps as u128)
-            .unwrap_or(0)
-            .checked_div(10000)
-            .unwrap_or(0);
+        let complete = Self::vault_registry_complete(&env, pool_id);
+        let tracked_sum = Self::vault_share_sum(&env, pool_id);
+        let expected = Self::owned_vault_shares(&env, pool_id);
+
+        RegistrySumPage {
+            start,
+            next: end,
+            count,
+            complete,
+            sum,
+            tracked_sum,
+            expected,
+            ok: complete && tracked_sum == expected,
+        }
+    }
 
-        let compound_amount = total_rewards.saturating_sub(treasury_amount);
+    /// Enables or disables the invariant check in `upgrade` (admin-only).
+    ///
+    /// # Authorization
+    /// Requires admin authorization.
+    pub fn set_upgrade_invariant_check(env: Env, admin: Address, enabled: bool) -> Result<(), Error> {
+        Self::require_admin_auth(&env, &admin)?;
+        env.storage().instance().set(&ConfigKey::UpgradeInvariantCheck, &enabled);
+        env.events().publish((symbol_short!("inv_guard"),), enabled);
+        Ok(())
+    }
 
-        use soroban_sdk::token;
-        let aqua_client = token::Client::new(&env, &config.aqua_token);
+    /// Registers existing stakers so their balances enter the running staked sum.
+    ///
+    /// Stakers are registered automatically whenever their staked balance
+    /// changes; this backfills stakers from before v1.9.0. Pass
+    /// `complete = true` with the last batch to enable the sum check; a
+    /// staker left out then shows up as a `stakers_ok` failure.
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn register_stakers(env: Env, manager: Address, users: Vec<Address>, complete: bool) -> Result<u32, Error> {
+        Self::require_manager_auth(&env, &manager)?;
 
-        // STEP 3: Transfer treasury cut to vault treasury
-        if treasury_amount > 0 {
-            aqua_client.transfer(&contract_address, &config.vault_treasury, &(treasury_amount as i128));
+        for user in users.iter() {
+            Self::register_staker(&env, &user);
         }
-
-        // STEP 4: Transfer remainder to manager wallet for off-chain swap + compound
-        if compound_amount > 0 {
-            aqua_client.transfer(&contract_address, &manager, &(compound_amount as i128));
+        if complete {
+            env.storage().instance().set(&LedgerKey::StakerRegistryComplete, &true);
         }
 
-        env.events().publish(
-            (symbol_short!("compound"), pool_id),
-            (total_rewards as i128, treasury_amount as i128, compound_amount as i128),
-        );
-
-        Ok((total_rewards as i128, treasury_amount as i128, compound_amount as i128))
+        let count: u32 = env.storage().instance().get(&LedgerKey::StakerCount).unwrap_or(0);
+        env.events().publish((symbol_short!("stk_reg"),), (count, complete));
+        Ok(count)
     }
 
-    /// Deposits tokens from admin into an Aquarius pool on behalf of the contract.
-    /// Called by backend after swapping AQUA into both pool tokens.
-    /// This completes the compound cycle started by `claim_and_compound`.
+    /// Registers existing vault depositors of a pool so their shares enter
+    /// the pool's running share sum.
     ///
-    /// # Arguments
-    /// * `pool_id` - Pool ID to deposit into
-    /// * `amount_a` - Amount of token_a to deposit (from admin wallet)
-    /// * `amount_b` - Amount of token_b to deposit (from admin wallet)
-    pub fn admin_compound_deposit(
+    /// Depositors are registered automatically on deposit; this backfills
+    /// positions from before v1.9.0. Pass `complete = true` with the last
+    /// batch to enable the share-sum check for the pool; a depositor left
+    /// out then shows up as share drift.
+    ///
+    /// # Authorization
+    /// Requires manager authorization
+    pub fn register_vault_users(
         env: Env,
         manager: Address,
         pool_id: u32,
-        amount_a: i128,
-        amount_b: i128,
-    ) -> Result<i128, Error> {
-        let config = Self::get_config(env.clone())?;
+        users: Vec<Address>,
+        complete: bool,
+    ) -> Result<u32, Error> {
         Self::require_manager_auth(&env, &manager)?;
 
-        let mut pool_info: PoolInfo = env
-            .storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)?;
-
-        if !pool_info.active {
-            return Err(Error::PoolNotActive);
+        if !env.storage().persistent().has(&DataKey::PoolInfo(pool_id)) {
+            return Err(Error::PoolNotFound);
         }
 
-        if amount_a <= 0 || amount_b <= 0 {
-            return Err(Error::InvalidInput);
+        for user in users.iter() {
+            Self::register_vault_user(&env, pool_id, &user);
         }
+        if complete {
+            env.storage()
+                .persistent()
+                .set(&LedgerKey::VaultUserRegistryComplete(pool_id), &true);
+        }
+        Self::refresh_share_drift(&env, pool_id);
 
-        let contract_address = env.current_contract_address();
-
-        // STEP 1: Transfer both tokens from admin to contract
-        use soroban_sdk::token;
-        let token_a_client = token::Client::new(&env, &pool_info.token_a);
-        let token_b_client = token::Client::new(&env, &pool_info.token_b);
-
-        token_a_client.transfer(&manager, &contract_address, &amount_a);
-        token_b_client.transfer(&manager, &contract_address, &amount_b);
-
-        // STEP 2: Deposit to Aquarius pool
-        // Aquarius pools order tokens by contract address (sorted).
-        // desired_amounts and auth entries must follow the pool's token order,
-        // which may differ from our PoolInfo (token_a, token_b) order.
-        let aquarius_pool = AquariusPoolClient::new(&env, &pool_info.pool_address);
-
-        let (first_token, first_amount, second_token, second_amount) =
-            if pool_info.token_a < pool_info.token_b {
-                // token_a comes first in pool order
-                (pool_info.token_a.clone(), amount_a, pool_info.token_b.clone(), amount_b)
-            } else {
-                // token_b comes first in pool order
-                (pool_info.token_b.clone(), amount_b, pool_info.token_a.clone(), amount_a)
-            };
-
-        let auth_entries = soroban_sdk::vec![
-            &env,
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: first_token,
-                    fn_name: Symbol::new(&env, "transfer"),
-                    args: (
-                        contract_address.clone(),
-                        pool_info.pool_address.clone(),
-                        first_amount,
-                    ).into_val(&env),
-                },
-                sub_invocations: soroban_sdk::vec![&env],
-            }),
-            InvokerContractAuthEntry::Contract(SubContractInvocation {
-                context: ContractContext {
-                    contract: second_token,
-                    fn_name: Symbol::new(&env, "transfer"),
-                    args: (
-                        contract_address.clone(),
-                        pool_info.pool_address.clone(),
-                        second_amount,
-                    ).into_val(&env),
-                },
-                sub_invocations: soroban_sdk::vec![&env],
-            }),
-        ];
-        env.authorize_as_current_contract(auth_entries);
-
-        let mut desired_amounts = Vec::new(&env);
-        desired_amounts.push_back(first_amount as u128);
-        desired_amounts.push_back(second_amount as u128);
-
-        let (_actual_amounts, lp_shares_minted) = aquarius_pool.deposit(
-            &contract_address,
-            &desired_amounts,
-            &0u128,
-        );
+        let count: u32 = env
+            .storage()
+            .persistent()
+            .get(&LedgerKey::VaultUserCount(pool_id))
+            .unwrap_or(0);
+        env.events().publish((symbol_short!("vlt_reg"), pool_id), (count, complete));
+        Ok(count)
+    }
 
-        // STEP 3: Update pool LP tracking
-        pool_info.total_lp_tokens = pool_info
-            .total_lp_tokens
-            .saturating_add(lp_shares_minted as i128);
+    /// Returns the number of registered stakers.
+    pub fn get_staker_count(env: Env) -> u32 {
+        env.storage().instance().get(&LedgerKey::StakerCount).unwrap_or(0)
+    }
 
+    /// Returns the number of registered vault depositors of a pool.
+    pub fn get_vault_user_count(env: Env, pool_id: u32) -> u32 {
         env.storage()
             .persistent()
-            .set(&DataKey::PoolInfo(pool_id), &pool_info);
+            .get(&LedgerKey::VaultUserCount(pool_id))
+            .unwrap_or(0)
+    }
 
-        // STEP 4: Update compound stats
-        let mut stats: PoolCompoundStats = env
+    /// Internal: Add a user to the staker registry (no-op if already registered).
+    /// The user's stored balance joins the running staked sum.
+    fn register_staker(env: &Env, user: &Address) {
+        let key = LedgerKey::StakerRegistered(user.clone());
+        if env.storage().persistent().has(&key) {
+            return;
+        }
+        let count: u32 = env.storage().instance().get(&LedgerKey::StakerCount).unwrap_or(0);
+        env.storage().persistent().set(&LedgerKey::StakerByIndex(count), user);
+        env.storage().persistent().set(&key, &true);
+        env.storage().instance().set(&LedgerKey::StakerCount, &(count + 1));
+
+        let balance = Self::get_user_reward_state(env, user).staked_balance;
+        env.storage().instance().set(
+            &LedgerKey::StakedBalanceSum,
+            &Self::staked_balance_sum(env).saturating_add(balance),
+        );
+    }
+
+    /// Internal: Add a user to a pool's depositor registry (no-op if already registered).
+    /// The user's stored shares join the pool's running share sum.
+    fn register_vault_user(env: &Env, pool_id: u32, user: &Address) {
+        let key = LedgerKey::VaultUserRegistered(pool_id, user.clone());
+        if env.storage().persistent().has(&key) {
+            return;
+        }
+        let count: u32 = env
             .storage()
             .persistent()
-            .get(&DataKey::PoolCompoundStats(pool_id))
-            .unwrap_or(PoolCompoundStats {
-                total_compounded_lp: 0,
-                total_rewards_claimed: 0,
-                total_treasury_fees: 0,
-                last_compound_time: 0,
-                compound_count: 0,
-            });
-        stats.total_compounded_lp = stats.total_compounded_lp.saturating_add(lp_shares_minted as i128);
-        stats.last_compound_time = env.ledger().timestamp();
-        stats.compound_count = stats.compound_count.saturating_add(1);
+            .get(&LedgerKey::VaultUserCount(pool_id))
+            .unwrap_or(0);
+        env.storage()
+            .persistent()
+            .set(&LedgerKey::VaultUserByIndex(pool_id, count), user);
+        env.storage().persistent().set(&key, &true);
         env.storage()
             .persistent()
-            .set(&DataKey::PoolCompoundStats(pool_id), &stats);
+            .set(&LedgerKey::VaultUserCount(pool_id), &(count + 1));
 
-        env.events().publish(
-            (symbol_short!("cmp_dep"), pool_id),
-            (lp_shares_minted, pool_info.total_lp_tokens),
+        let shares = env
+            .storage()
+            .persistent()
+            .get::<DataKey, UserVaultPosition>(&DataKey::UserVaultPosition(user.clone(), pool_id))
+            .map(|p| p.share_ratio)
+            .unwrap_or(0);
+        env.storage().persistent().set(
+            &LedgerKey::VaultShareSum(pool_id),
+            &Self::vault_share_sum(env, pool_id).saturating_add(shares),
         );
-
-        Ok(lp_shares_minted as i128)
     }
 
-    // ============================================================================
-    // QUERY FUNCTIONS - ICE & Vault
-    // ============================================================================
-
-    /// Gets pending AQUA available for ICE locking.
-    pub fn get_pending_aqua_for_ice(env: Env) -> Result<i128, Error> {
-        let global_state: GlobalState = env
-            .storage()
-            .instance()
-            .get(&DataKey::GlobalState)
-            .ok_or(Error::NotInitialized)?;
-
-        Ok(global_state.pending_aqua_for_ice)
+    /// Internal: Record a change to a staker's balance, already stored, in the running sum
+    fn track_staked_balance(env: &Env, user: &Address, delta: i128) {
+        if env.storage().persistent().has(&LedgerKey::StakerRegistered(user.clone())) {
+            env.storage().instance().set(
+                &LedgerKey::StakedBalanceSum,
+                &Self::staked_balance_sum(env).saturating_add(delta),
+            );
+        } else {
+            Self::register_staker(env, user);
+        }
     }
 
-    /// Gets all 4 ICE token balances.
-    pub fn get_all_ice_balances(env: Env) -> Result<(i128, i128, i128, i128), Error> {
-        let global_state: GlobalState = env
+    /// Internal: Record a change to a depositor's shares, already stored
+    /// together with `VaultTotalShares`, in the pool's running sum
+    fn track_vault_shares(env: &Env, pool_id: u32, user: &Address, delta: i128) {
+        if env
             .storage()
-            .instance()
-            .get(&DataKey::GlobalState)
-            .ok_or(Error::NotInitialized)?;
-
-        Ok((
-            global_state.ice_balance,
-            global_state.govern_ice_balance,
-            global_state.upvote_ice_balance,
-            global_state.downvote_ice_balance,
-        ))
+            .persistent()
+            .has(&LedgerKey::VaultUserRegistered(pool_id, user.clone()))
+        {
+            env.storage().persistent().set(
+                &LedgerKey::VaultShareSum(pool_id),
+                &Self::vault_share_sum(env, pool_id).saturating_add(delta),
+            );
+        } else {
+            Self::register_vault_user(env, pool_id, user);
+        }
+        Self::refresh_share_drift(env, pool_id);
     }
 
-    /// Gets upvoteICE balance for voting.
-    pub fn get_upvote_ice_balance(env: Env) -> Result<i128, Error> {
-        let global_state: GlobalState = env
-            .storage()
-            .instance()
-            .get(&DataKey::GlobalState)
-            .ok_or(Error::NotInitialized)?;
-
-        Ok(global_state.upvote_ice_balance)
-    }
+    /// Internal: Flag or clear a complete pool whose share sum is off and
+    /// keep the count of flagged pools the upgrade guard reads
+    fn refresh_share_drift(env: &Env, pool_id: u32) {
+        let drifted = Self::vault_registry_complete(env, pool_id)
+            && Self::vault_share_sum(env, pool_id) != Self::owned_vault_shares(env, pool_id);
+        let key = LedgerKey::ShareDrift(pool_id);
+        if drifted == env.storage().persistent().has(&key) {
+            return;
+        }
 
-    /// Gets ICE lock authorization by ID.
-    pub fn get_ice_lock_authorization(env: Env, lock_id: u64) -> Result<IceLockAuthorization, Error> {
-        env.storage()
-            .persistent()
-            .get(&DataKey::IceLockAuth(lock_id))
-            .ok_or(Error::NotFound)
+        let count: u32 = env.storage().instance().get(&LedgerKey::ShareDriftCount).unwrap_or(0);
+        if drifted {
+            env.storage().persistent().set(&key, &true);
+            env.storage().instance().set(&LedgerKey::ShareDriftCount, &count.saturating_add(1));
+        } else {
+            env.storage().persistent().remove(&key);
+            env.storage().instance().set(&LedgerKey::ShareDriftCount, &count.saturating_sub(1));
+        }
     }
 
-    /// Gets pool information by ID.
-    pub fn get_pool_info(env: Env, pool_id: u32) -> Result<PoolInfo, Error> {
-        env.storage()
-            .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .ok_or(Error::PoolNotFound)
+    /// Internal: Running sum of registered stakers' balances
+    fn staked_balance_sum(env: &Env) -> i128 {
+        env.storage().instance().get(&LedgerKey::StakedBalanceSum).unwrap_or(0)
     }
 
-    /// Gets total vault shares for a pool (v1.8.0+).
-    pub fn get_vault_total_shares(env: Env, pool_id: u32) -> i128 {
+    /// Internal: Running sum of a pool's registered depositors' shares
+    fn vault_share_sum(env: &Env, pool_id: u32) -> i128 {
         env.storage()
             .persistent()
-            .get(&DataKey::VaultTotalShares(pool_id))
+            .get(&LedgerKey::VaultShareSum(pool_id))
             .unwrap_or(0)
     }
 
-    /// Gets user's vault position in a specific pool.
-    pub fn get_user_vault_position(env: Env, user: Address, pool_id: u32) -> Result<UserVaultPosition, Error> {
-        env.storage()
-            .persistent()
-            .get(&DataKey::UserVaultPosition(user, pool_id))
-            .ok_or(Error::PositionNotFound)
-    }
-
-    /// Gets total number of vault pools.
-    /// Gets compound stats for a vault pool.
-    pub fn get_pool_compound_stats(env: Env, pool_id: u32) -> PoolCompoundStats {
+    /// Internal: Whether every depositor of a pool is registered
+    fn vault_registry_complete(env: &Env, pool_id: u32) -> bool {
         env.storage()
             .persistent()
-            .get(&DataKey::PoolCompoundStats(pool_id))
-            .unwrap_or(PoolCompoundStats {
-                total_compounded_lp: 0,
-                total_rewards_claimed: 0,
-                total_treasury_fees: 0,
-                last_compound_time: 0,
-                compound_count: 0,
-            })
+            .get(&LedgerKey::VaultUserRegistryComplete(pool_id))
+            .unwrap_or(false)
     }
 
-    /// Gets user's compound gains for a specific pool.
-    /// Returns (current_lp, deposited_lp, compound_gain_lp).
-    pub fn get_user_compound_gains(env: Env, user: Address, pool_id: u32) -> (i128, i128, i128) {
-        let pool_info: PoolInfo = env
+    /// Internal: `VaultTotalShares` minus the dead shares nobody owns
+    fn owned_vault_shares(env: &Env, pool_id: u32) -> i128 {
+        let total_shares: i128 = env
             .storage()
             .persistent()
-            .get(&DataKey::PoolInfo(pool_id))
-            .unwrap_or(PoolInfo {
-                pool_id,
-                pool_address: env.current_contract_address(),
-                token_a: env.current_contract_address(),
-                token_b: env.current_contract_address(),
-                share_token: env.current_contract_address(),
-                total_lp_tokens: 0,
-                active: false,
-                added_at: 0,
-            });
-
-        let user_position: UserVaultPosition = env
+            .get(&DataKey::VaultTotalShares(pool_id))
+            .unwrap_or(0);
+        let dead_shares: i128 = env
             .storage()
             .persistent()
-            .get(&DataKey::UserVaultPosition(user.clone(), pool_id))
-            .unwrap_or(UserVaultPosition {
-                user: user.clone(),
-                pool_id,
-                share_ratio: 0,
-                deposited_at: 0,
-                active: false,
-            });
+            .get(&PoolKey::DeadShares(pool_id))
+            .unwrap_or(0);
+        total_shares.saturating_sub(dead_shares)
+    }
 
-        // Convert shares to LP: user_lp = shares * total_lp / total_shares
+    /// Internal: LP backing and share-sum check for one pool (`None` if it does not exist)
+    fn pool_invariant_report(env: &Env, pool_id: u32) -> Result<Option<PoolInvariantReport>, Error> {
+        let pool_info = match Self::load_pool(env, pool_id) {
+            Ok(p) => p,
+            Err(_) => return Ok(None),
+        };
+
+        use soroban_sdk::token;
+
+        let pol_lp = Self::pool_pol_lp(env, &pool_info)?;
+        let actual_lp = token::Client::new(env, &pool_info.share_token)
+            .balance(&env.current_contract_address());
         let total_shares: i128 = env
             .storage()
             .persistent()
             .get(&DataKey::VaultTotalShares(pool_id))
             .unwrap_or(0);
-
-        let current_lp = if total_shares > 0 {
-            user_position.share_ratio
-                .checked_mul(pool_info.total_lp_tokens)
-                .unwrap_or(0)
-                .checked_div(total_shares)
-                .unwrap_or(0)
-        } else {
-            0
-        };
-
-        let deposited_lp: i128 = env
+        let dead_shares: i128 = env
             .storage()
             .persistent()
-            .get(&DataKey::UserDepositedLp(user, pool_id))
+            .get(&PoolKey::DeadShares(pool_id))
             .unwrap_or(0);
+        let user_shares = Self::vault_share_sum(env, pool_id);
 
-        let compound_gain = current_lp.saturating_sub(deposited_lp);
+        let lp_ok = actual_lp >= pool_info.total_lp_tokens.saturating_add(pol_lp);
+        let shares_ok = !Self::vault_registry_complete(env, pool_id)
+            || user_shares == total_shares.saturating_sub(dead_shares);
 
-        (current_lp, deposited_lp, compound_gain)
+        Ok(Some(PoolInvariantReport {
+            pool_id,
+            tracked_lp: pool_info.total_lp_tokens,
+            pol_lp,
+            actual_lp,
+            lp_ok,
+            total_shares,
+            dead_shares,
+            user_shares,
+            shares_ok,
+            ok: lp_ok && shares_ok,
+            idle_residual: Self::read_idle_residual(env, &pool_info),
+        }))
     }
 
-    /// Gets total number of vault pools.
-    pub fn get_pool_count(env: Env) -> Result<u32, Error> {
-        let global_state: GlobalState = env
+    /// Internal: POL LP held in a vault pool's share token.
+    /// Non-zero only when the vault pool is also the POL (liquidity_contract) pool.
+    fn pool_pol_lp(env: &Env, pool_info: &PoolInfo) -> Result<i128, Error> {
+        let tokens = Self::read_token_config(env)?;
+        if pool_info.pool_address == tokens.liquidity_contract {
+            Ok(Self::get_pol(env).aqua_blub_lp_position)
+        } else {
+            Ok(0)
+        }
+    }
+
+    /// Internal: Evaluate the bounded-cost accounting invariants
+    fn build_invariant_report(env: &Env) -> Result<InvariantReport, Error> {
+        if !env.storage().instance().has(&DataKey::GlobalState) {
+            return Err(Error::NotInitialized);
+        }
+        let tokens = Self::read_token_config(env)?;
+        let contract = env.current_contract_address();
+
+        use soroban_sdk::token;
+
+        // BLUB solvency: principal + staker and vault rewards not yet claimed
+        let reward_state = Self::get_reward_state(env);
+        let blub_balance = token::Client::new(env, &tokens.blub_token).balance(&contract);
+        let ledger = Self::read_token_ledger(env);
+        let unclaimed_rewards = ledger.blub_reward_reserve;
+        let blub_ok = blub_balance
+            >= reward_state
+                .total_staked
+                .saturating_add(unclaimed_rewards)
+                .saturating_add(ledger.blub_vault_rewards)
+                .saturating_add(ledger.blub_vault_idle);
+
+        // Staked balances: running sum over the registry
+        let staked_sum = Self::staked_balance_sum(env);
+        let stakers_complete: bool = env
             .storage()
             .instance()
-            .get(&DataKey::GlobalState)
-            .ok_or(Error::NotInitialized)?;
+            .get(&LedgerKey::StakerRegistryComplete)
+            .unwrap_or(false);
+        let stakers_ok = !stakers_complete || staked_sum == reward_state.total_staked;
 
-        Ok(global_state.pool_count)
+        // Vault shares: pools flagged whenever their share sum changes
+        let share_drift_pools: u32 = env
+            .storage()
+            .instance()
+            .get(&LedgerKey::ShareDriftCount)
+            .unwrap_or(0);
+        let shares_ok = share_drift_pools == 0;
+
+        // LP backing: live balance check of the first pools
+        let lp_pools_checked = Self::get_pool_count(env.clone())?.min(INVARIANT_POOL_PAGE_LIMIT);
+        let mut lp_ok = true;
+        for pool_id in 0..lp_pools_checked {
+            if let Some(report) = Self::pool_invariant_report(env, pool_id)? {
+                lp_ok = lp_ok && report.lp_ok;
+            }
+        }
+
+        Ok(InvariantReport {
+            blub_balance,
+            total_staked: reward_state.total_staked,
+            unclaimed_rewards,
+            unclaimed_vault_rewards: ledger.blub_vault_rewards,
+            vault_idle_blub: ledger.blub_vault_idle,
+            blub_ok,
+            staked_sum,
+            stakers_ok,
+            share_drift_pools,
+            shares_ok,
+            lp_pools_checked,
+            lp_ok,
+            ok: blub_ok && stakers_ok && shares_ok && lp_ok,
+            checked_at: env.ledger().timestamp(),
+        })
     }
 }
 
@@ -5677,4 +10740,6 @@ impl Default for ProtocolOwnedLiquidity {
             ice_voting_power_used: 0,
         }
     }
-} 
\ No newline at end of file
+} 
+
+mod test;
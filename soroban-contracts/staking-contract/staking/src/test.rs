@@ -0,0 +1,1525 @@
+#![cfg(test)]
+
+use super::*;
+use soroban_sdk::{
+    contract, contractimpl, contracttype, testutils::{Address as _, Events as _, Ledger as _}, token, vec, Address, BytesN, Env, IntoVal, Symbol,
+    Val, Vec,
+};
+
+// ============================================================================
+// Mock Aquarius Pool
+// Constant-product pools mint shares pro-rata to the scarcest token and take
+// the matching amounts; stableswap pools take any mix but charge a tenth of
+// the imbalance. Reserves are the pool's token balances. `claim` pays out
+// whatever reward was set with `set_reward`.
+// ============================================================================
+
+const MOCK_FEE_BPS: u32 = 30;
+
+#[contracttype]
+#[derive(Clone)]
+enum MockPoolKey {
+    Tokens,
+    ShareToken,
+    Kind,
+    TotalShares,
+    Reward,
+}
+
+#[contract]
+pub struct MockPool;
+
+#[contractimpl]
+impl MockPool {
+    pub fn init(env: Env, tokens: Vec<Address>, share_token: Address, kind: Symbol) {
+        env.storage().instance().set(&MockPoolKey::Tokens, &tokens);
+        env.storage().instance().set(&MockPoolKey::ShareToken, &share_token);
+        env.storage().instance().set(&MockPoolKey::Kind, &kind);
+        env.storage().instance().set(&MockPoolKey::TotalShares, &0u128);
+    }
+
+    pub fn pool_type(env: Env) -> Symbol {
+        env.storage().instance().get(&MockPoolKey::Kind).unwrap()
+    }
+
+    pub fn get_tokens(env: Env) -> Vec<Address> {
+        env.storage().instance().get(&MockPoolKey::Tokens).unwrap()
+    }
+
+    pub fn share_id(env: Env) -> Address {
+        env.storage().instance().get(&MockPoolKey::ShareToken).unwrap()
+    }
+
+    pub fn get_total_shares(env: Env) -> u128 {
+        env.storage().instance().get(&MockPoolKey::TotalShares).unwrap_or(0)
+    }
+
+    pub fn get_fee_fraction(_env: Env) -> u32 {
+        MOCK_FEE_BPS
+    }
+
+    pub fn get_reserves(env: Env) -> Vec<u128> {
+        let pool = env.current_contract_address();
+        let mut reserves = Vec::new(&env);
+        for token in Self::get_tokens(env.clone()).iter() {
+            reserves.push_back(token::Client::new(&env, &token).balance(&pool) as u128);
+        }
+        reserves
+    }
+
+    pub fn estimate_deposit(env: Env, desired_amounts: Vec<u128>) -> u128 {
+        Self::quote(&env, &desired_amounts).1
+    }
+
+    pub fn deposit(env: Env, user: Address, desired_amounts: Vec<u128>, min_shares: u128) -> (Vec<u128>, u128) {
+        user.require_auth();
+
+        let (amounts, shares) = Self::quote(&env, &desired_amounts);
+        assert!(shares > 0 && shares >= min_shares, "shares below minimum");
+
+        let pool = env.current_contract_address();
+        for (token, amount) in Self::get_tokens(env.clone()).iter().zip(amounts.iter()) {
+            if amount > 0 {
+                token::Client::new(&env, &token).transfer(&user, &pool, &(amount as i128));
+            }
+        }
+        token::StellarAssetClient::new(&env, &Self::share_id(env.clone())).mint(&user, &(shares as i128));
+        let total = Self::get_total_shares(env.clone()) + shares;
+        env.storage().instance().set(&MockPoolKey::TotalShares, &total);
+
+        (amounts, shares)
+    }
+
+    pub fn withdraw(env: Env, user: Address, share_amount: u128, min_amounts: Vec<u128>) -> Vec<u128> {
+        user.require_auth();
+
+        let reserves = Self::get_reserves(env.clone());
+        let total = Self::get_total_shares(env.clone());
+        token::Client::new(&env, &Self::share_id(env.clone())).burn(&user, &(share_amount as i128));
+
+        let pool = env.current_contract_address();
+        let mut amounts = Vec::new(&env);
+        for (i, token) in Self::get_tokens(env.clone()).iter().enumerate() {
+            let amount = share_amount * reserves.get(i as u32).unwrap() / total;
+            assert!(amount >= min_amounts.get(i as u32).unwrap_or(0), "amount below minimum");
+            token::Client::new(&env, &token).transfer(&pool, &user, &(amount as i128));
+            amounts.push_back(amount);
+        }
+        env.storage().instance().set(&MockPoolKey::TotalShares, &(total - share_amount));
+
+        amounts
+    }
+
+    /// Makes the next `claim` pay `amount` of `token`, which the pool must hold
+    pub fn set_reward(env: Env, token: Address, amount: u128) {
+        env.storage().instance().set(&MockPoolKey::Reward, &(token, amount));
+    }
+
+    pub fn claim(env: Env, user: Address) -> u128 {
+        user.require_auth();
+
+        let reward: Option<(Address, u128)> = env.storage().instance().get(&MockPoolKey::Reward);
+        match reward {
+            Some((token, amount)) if amount > 0 => {
+                token::Client::new(&env, &token).transfer(&env.current_contract_address(), &user, &(amount as i128));
+                env.storage().instance().remove(&MockPoolKey::Reward);
+                amount
+            }
+            _ => 0,
+        }
+    }
+
+    pub fn swap(env: Env, user: Address, in_idx: u32, out_idx: u32, in_amount: u128, out_min: u128) -> u128 {
+        user.require_auth();
+
+        let reserves = Self::get_reserves(env.clone());
+        let (r_in, r_out) = (reserves.get(in_idx).unwrap(), reserves.get(out_idx).unwrap());
+        let in_after_fee = in_amount * (10000 - MOCK_FEE_BPS as u128);
+        let out = in_after_fee * r_out / (r_in * 10000 + in_after_fee);
+        assert!(out >= out_min, "out below minimum");
+
+        let pool = env.current_contract_address();
+        let tokens = Self::get_tokens(env.clone());
+        token::Client::new(&env, &tokens.get(in_idx).unwrap()).transfer(&user, &pool, &(in_amount as i128));
+        token::Client::new(&env, &tokens.get(out_idx).unwrap()).transfer(&pool, &user, &(out as i128));
+
+        out
+    }
+}
+
+impl MockPool {
+    /// Amounts the pool takes out of `desired` and the shares it mints for them
+    fn quote(env: &Env, desired: &Vec<u128>) -> (Vec<u128>, u128) {
+        let total = Self::get_total_shares(env.clone());
+        let reserves = Self::get_reserves(env.clone());
+        if total == 0 {
+            return (desired.clone(), desired.iter().sum());
+        }
+
+        if Self::pool_type(env.clone()) == Symbol::new(env, "stable") {
+            let max = desired.iter().max().unwrap_or(0);
+            let min = desired.iter().min().unwrap_or(0);
+            let raw = desired.iter().sum::<u128>() - (max - min) / 10;
+            return (desired.clone(), raw * total / reserves.iter().sum::<u128>());
+        }
+
+        let mut shares = u128::MAX;
+        for (amount, reserve) in desired.iter().zip(reserves.iter()) {
+            shares = shares.min(amount * total / reserve);
+        }
+        let mut amounts = Vec::new(env);
+        for reserve in reserves.iter() {
+            amounts.push_back((shares * reserve).div_ceil(total));
+        }
+        (amounts, shares)
+    }
+}
+
+// ============================================================================
+// Mock SEP-40 Oracle
+// ============================================================================
+
+#[contracttype]
+#[derive(Clone)]
+enum MockOracleKey {
+    Price(Address),
+}
+
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    /// Records `price` for `asset` at the current ledger time
+    pub fn set_price(env: Env, asset: Address, price: i128) {
+        let data = price_oracle::PriceData {
+            price,
+            timestamp: env.ledger().timestamp(),
+        };
+        env.storage().instance().set(&MockOracleKey::Price(asset), &data);
+    }
+
+    pub fn lastprice(env: Env, asset: price_oracle::Asset) -> Option<price_oracle::PriceData> {
+        match asset {
+            price_oracle::Asset::Stellar(address) => env.storage().instance().get(&MockOracleKey::Price(address)),
+            price_oracle::Asset::Other(_) => None,
+        }
+    }
+}
+
+// ============================================================================
+// Setup
+// ============================================================================
+
+struct Setup<'a> {
+    env: Env,
+    admin: Address,
+    vault: StakingRegistryClient<'a>,
+    aqua: Address,
+    blub: Address,
+    usdc: Address,
+    pool: MockPoolClient<'a>,
+    share: Address,
+    ice: IceTokens,
+}
+
+fn create_token(env: &Env, admin: &Address) -> Address {
+    env.register_stellar_asset_contract_v2(admin.clone()).address()
+}
+
+fn mint(env: &Env, token: &Address, to: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, token).mint(to, &amount);
+}
+
+fn balance(env: &Env, token: &Address, of: &Address) -> i128 {
+    token::Client::new(env, token).balance(of)
+}
+
+/// Contract error returned by `func`, undecoded (the client maps every
+/// contract error to `InvalidInput`)
+fn call_error(env: &Env, contract: &Address, func: &str, args: Vec<Val>) -> soroban_sdk::Error {
+    env.try_invoke_contract::<Val, soroban_sdk::Error>(contract, &Symbol::new(env, func), args)
+        .err()
+        .unwrap()
+        .unwrap()
+}
+
+/// A mock pool of `kind` over `token_a`/`token_b`, seeded with 1,000,000 of
+/// each, and its share token
+fn create_pool<'a>(env: &Env, token_a: &Address, token_b: &Address, kind: &str) -> (MockPoolClient<'a>, Address) {
+    let pool = MockPoolClient::new(env, &env.register_contract(None, MockPool));
+    let share = create_token(env, &pool.address);
+    pool.init(&vec![env, token_a.clone(), token_b.clone()], &share, &Symbol::new(env, kind));
+
+    let seeder = Address::generate(env);
+    mint(env, token_a, &seeder, 1_000_000);
+    mint(env, token_b, &seeder, 1_000_000);
+    pool.deposit(&seeder, &vec![env, 1_000_000u128, 1_000_000u128], &0);
+    (pool, share)
+}
+
+/// An initialized vault and a BLUB/USDC mock pool of `kind` seeded with
+/// 1,000,000 of each token (not yet added to the vault)
+fn setup(kind: &str) -> Setup<'static> {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let vault = StakingRegistryClient::new(&env, &env.register_contract(None, StakingRegistry));
+    let aqua = create_token(&env, &admin);
+    let blub = create_token(&env, &admin);
+    let usdc = create_token(&env, &admin);
+    let ice = IceTokens {
+        ice_token: create_token(&env, &admin),
+        govern_ice_token: create_token(&env, &admin),
+        upvote_ice_token: create_token(&env, &admin),
+        downvote_ice_token: create_token(&env, &admin),
+    };
+    vault.initialize(
+        &admin,
+        &Address::generate(&env),
+        &aqua,
+        &blub,
+        &Address::generate(&env),
+        &ice,
+        &Address::generate(&env),
+        &1500,
+    );
+
+    let (pool, share) = create_pool(&env, &blub, &usdc, kind);
+
+    Setup {
+        env,
+        admin,
+        vault,
+        aqua,
+        blub,
+        usdc,
+        pool,
+        share,
+        ice,
+    }
+}
+
+impl Setup<'_> {
+    /// Adds the mock pool to the vault, giving its tokens in reverse order
+    fn add_pool(&self) -> u32 {
+        self.vault
+            .add_pool(&self.admin, &self.pool.address, &self.usdc, &self.blub, &self.share)
+    }
+
+    /// Creates another constant-product pool and adds it to the vault
+    fn add_other_pool(&self, token_a: &Address, token_b: &Address) -> u32 {
+        let (pool, share) = create_pool(&self.env, token_a, token_b, "constant_product");
+        self.vault.add_pool(&self.admin, &pool.address, token_a, token_b, &share)
+    }
+
+    fn funded_user(&self, amount: i128) -> Address {
+        let user = Address::generate(&self.env);
+        mint(&self.env, &self.blub, &user, amount);
+        mint(&self.env, &self.usdc, &user, amount);
+        user
+    }
+
+    fn user_shares(&self, user: &Address, pool_id: u32) -> i128 {
+        self.vault.get_user_vault_position(user, &pool_id).share_ratio
+    }
+
+    /// Funds the mock pool with `amount` AQUA for the vault's next claim
+    fn set_aqua_reward(&self, amount: i128) {
+        mint(&self.env, &self.aqua, &self.pool.address, amount);
+        self.pool.set_reward(&self.aqua, &(amount as u128));
+    }
+
+    /// Gives the vault `amount` AQUA pending for ICE, as locks would
+    fn add_pending_ice_aqua(&self, amount: i128) {
+        mint(&self.env, &self.aqua, &self.vault.address, amount);
+        self.env.as_contract(&self.vault.address, || {
+            let mut global_state: GlobalState = self.env.storage().instance().get(&DataKey::GlobalState).unwrap();
+            global_state.pending_aqua_for_ice += amount;
+            self.env.storage().instance().set(&DataKey::GlobalState, &global_state);
+        });
+    }
+
+    /// Authorizes and transfers an ICE lock of `aqua_amount` for `years`
+    fn transferred_ice_lock(&self, aqua_amount: i128, years: u64) -> u64 {
+        self.add_pending_ice_aqua(aqua_amount);
+        let lock_id = self.vault.authorize_ice_lock(&self.admin, &aqua_amount, &years);
+        self.vault.transfer_authorized_aqua(&self.admin, &lock_id);
+        lock_id
+    }
+}
+
+// ============================================================================
+// Aquarius adapter
+// ============================================================================
+
+#[test]
+fn test_add_pool_stores_pool_token_order() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+
+    let info = s.vault.get_pool_info(&pool_id);
+    assert_eq!(info.tokens, vec![&s.env, s.blub.clone(), s.usdc.clone()]);
+    assert_eq!((info.token_a, info.token_b), (s.blub.clone(), s.usdc.clone()));
+    assert_eq!(info.strategy.kind, StrategyKind::AquariusConstantProduct);
+    assert!(info.verified);
+}
+
+#[test]
+fn test_add_pool_rejects_mismatched_metadata() {
+    let s = setup("constant_product");
+
+    let wrong_share = create_token(&s.env, &s.admin);
+    let args = (s.admin.clone(), s.pool.address.clone(), s.blub.clone(), s.usdc.clone(), wrong_share).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "add_pool", args),
+        Error::PoolMetadataMismatch.into()
+    );
+
+    let other = create_token(&s.env, &s.admin);
+    let args = (s.admin.clone(), s.pool.address.clone(), s.blub.clone(), other, s.share.clone()).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "add_pool", args),
+        Error::PoolMetadataMismatch.into()
+    );
+}
+
+#[test]
+fn test_constant_product_deposit_trims_to_reserve_ratio() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+
+    s.vault.vault_deposit(&user, &pool_id, &10_000, &15_000, &0, &0);
+
+    // Reserves are 1:1, so the extra 5,000 USDC is refunded
+    assert_eq!(balance(&s.env, &s.blub, &user), 90_000);
+    assert_eq!(balance(&s.env, &s.usdc, &user), 90_000);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 20_000);
+    assert_eq!(balance(&s.env, &s.share, &s.vault.address), 20_000);
+}
+
+#[test]
+fn test_stable_deposit_offers_balanced_amounts() {
+    let s = setup("stable");
+    let pool_id = s.add_pool();
+    assert_eq!(s.vault.get_pool_info(&pool_id).strategy.kind, StrategyKind::AquariusStable);
+    let user = s.funded_user(100_000);
+
+    // The pool's estimate shows the imbalanced mix minting fewer shares per
+    // token, so only the balanced 10,000 / 10,000 is offered
+    s.vault.vault_deposit(&user, &pool_id, &10_000, &15_000, &0, &0);
+
+    assert_eq!(balance(&s.env, &s.blub, &user), 90_000);
+    assert_eq!(balance(&s.env, &s.usdc, &user), 90_000);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 20_000);
+}
+
+// ============================================================================
+// Invariants
+// ============================================================================
+
+#[test]
+fn test_invariants_track_staked_and_share_sums() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let depositor = s.funded_user(100_000);
+    let staker = s.funded_user(50_000);
+    s.vault.vault_deposit(&depositor, &pool_id, &100_000, &100_000, &0, &0);
+    s.vault.stake(&staker, &50_000, &1);
+
+    let report = s.vault.check_invariants();
+    assert_eq!(report.staked_sum, 50_000);
+    assert_eq!(report.lp_pools_checked, 1);
+    assert!(report.stakers_ok && report.shares_ok && report.lp_ok && report.ok);
+
+    let stakers = s.vault.check_staker_invariants(&0, &15);
+    assert_eq!((stakers.count, stakers.sum, stakers.tracked_sum), (1, 50_000, 50_000));
+    assert!(stakers.ok);
+
+    // Admin corrections move the depositor's shares and the total together
+    s.vault.admin_adjust_vault_position(&s.admin, &depositor, &pool_id, &150_000, &0);
+    let shares = s.vault.check_vault_share_invariants(&pool_id, &0, &15);
+    assert_eq!((shares.sum, shares.tracked_sum, shares.expected), (150_000, 150_000, 150_000));
+    assert!(shares.ok);
+    assert!(s.vault.check_pool_invariants(&pool_id, &1).get(0).unwrap().ok);
+}
+
+#[test]
+fn test_upgrade_guard_refuses_share_drift() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let first = s.funded_user(100_000);
+    let second = s.funded_user(100_000);
+    s.vault.vault_deposit(&first, &pool_id, &100_000, &100_000, &0, &0);
+    s.vault.set_upgrade_invariant_check(&s.admin, &true);
+
+    // Shares minted outside any depositor's position
+    s.env.as_contract(&s.vault.address, || {
+        s.env.storage().persistent().set(&DataKey::VaultTotalShares(pool_id), &250_000i128);
+    });
+    s.vault.vault_deposit(&second, &pool_id, &10_000, &10_000, &0, &0);
+
+    let report = s.vault.check_invariants();
+    assert_eq!(report.share_drift_pools, 1);
+    assert!(!report.shares_ok && !report.ok);
+    assert!(!s.vault.check_vault_share_invariants(&pool_id, &0, &15).ok);
+
+    let args = (s.admin.clone(), BytesN::from_array(&s.env, &[0u8; 32])).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "upgrade", args),
+        Error::InvariantViolation.into()
+    );
+}
+
+#[test]
+fn test_upgrade_guard_refuses_lp_shortfall() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+    s.vault.set_upgrade_invariant_check(&s.admin, &true);
+
+    // The vault tracks more LP than it holds
+    let mut info = s.vault.get_pool_info(&pool_id);
+    info.total_lp_tokens += 1;
+    s.env.as_contract(&s.vault.address, || {
+        s.env.storage().persistent().set(&DataKey::PoolInfo(pool_id), &info);
+    });
+
+    let report = s.vault.check_invariants();
+    assert!(report.shares_ok && !report.lp_ok && !report.ok);
+    assert!(!s.vault.check_pool_invariants(&pool_id, &1).get(0).unwrap().lp_ok);
+
+    let args = (s.admin.clone(), BytesN::from_array(&s.env, &[0u8; 32])).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "upgrade", args),
+        Error::InvariantViolation.into()
+    );
+}
+
+// ============================================================================
+// Staker rewards
+// ============================================================================
+
+#[test]
+fn test_emergency_reset_recheckpoints_stakers_below_new_accumulator() {
+    let s = setup("constant_product");
+    let staker = s.funded_user(200_000);
+    mint(&s.env, &s.blub, &s.admin, 12_000);
+
+    s.vault.stake(&staker, &100_000, &1);
+    s.vault.add_rewards(&s.admin, &10_000);
+    // The second stake checkpoints 10,000 earned at the inflated accumulator
+    s.vault.stake(&staker, &100_000, &1);
+    assert_eq!(s.vault.get_pending_rewards(&staker), 10_000);
+
+    // Nothing was really owed: the whole reserve is burned
+    assert_eq!(s.vault.admin_emergency_reset_rewards(&s.admin, &0, &0), 10_000);
+    assert_eq!(s.vault.get_reward_ledger(), (200_000, 0));
+    assert_eq!(s.vault.get_pending_rewards(&staker), 0);
+
+    assert_eq!(s.vault.recheckpoint_rewards(&s.admin, &0, &10), 1);
+
+    // New rewards accrue in full instead of filling the old checkpoint's gap
+    s.vault.add_rewards(&s.admin, &2_000);
+    assert_eq!(s.vault.get_pending_rewards(&staker), 2_000);
+}
+
+#[test]
+fn test_emergency_reset_never_burns_other_buckets() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let staker = s.funded_user(100_000);
+    let depositor = s.funded_user(100_000);
+    mint(&s.env, &s.blub, &s.admin, 15_000);
+
+    s.vault.stake(&staker, &100_000, &1);
+    s.vault.add_rewards(&s.admin, &10_000);
+    s.vault.vault_deposit(&depositor, &pool_id, &100_000, &100_000, &0, &0);
+    s.vault.fund_vault_rewards(&s.admin, &pool_id, &5_000, &86_400);
+
+    // A stale reserve bucket claims more BLUB than is left for rewards
+    let mut ledger = s.vault.get_token_ledger().ledger;
+    ledger.blub_reward_reserve = 15_000;
+    s.env.as_contract(&s.vault.address, || {
+        s.env.storage().instance().set(&LedgerKey::TokenLedger, &ledger);
+    });
+
+    assert_eq!(s.vault.admin_emergency_reset_rewards(&s.admin, &0, &0), 10_000);
+    assert_eq!(balance(&s.env, &s.blub, &s.vault.address), 105_000);
+    let ledger = s.vault.get_token_ledger().ledger;
+    assert_eq!((ledger.blub_reward_reserve, ledger.blub_vault_rewards), (5_000, 5_000));
+}
+
+// ============================================================================
+// Token ledger
+// ============================================================================
+
+#[test]
+fn test_token_ledger_tracks_each_purpose() {
+    let s = setup("constant_product");
+    let staker = s.funded_user(100_000);
+    mint(&s.env, &s.blub, &s.admin, 5_000);
+
+    s.vault.stake(&staker, &100_000, &1);
+    s.vault.add_rewards(&s.admin, &5_000);
+    let report = s.vault.get_token_ledger();
+    assert_eq!((report.ledger.blub_principal, report.ledger.blub_reward_reserve), (100_000, 5_000));
+    assert_eq!((report.blub_balance, report.blub_unallocated), (105_000, 0));
+
+    assert_eq!(s.vault.claim_rewards(&staker), 5_000);
+    assert_eq!(s.vault.get_reward_ledger(), (100_000, 0));
+
+    // Tokens sent to the contract outside any flow stay unallocated
+    mint(&s.env, &s.blub, &s.vault.address, 700);
+    assert_eq!(s.vault.get_token_ledger().blub_unallocated, 700);
+}
+
+#[test]
+fn test_buckets_refuse_payouts_from_other_purposes() {
+    let s = setup("constant_product");
+    let staker = s.funded_user(100_000);
+    mint(&s.env, &s.blub, &s.admin, 5_000);
+    s.vault.stake(&staker, &100_000, &1);
+    s.vault.add_rewards(&s.admin, &5_000);
+
+    // The contract holds the BLUB, but it is principal, not POL in transit
+    mint(&s.env, &s.aqua, &s.vault.address, 1_000);
+    let args = (s.admin.clone(), 1_000i128, 1_000i128).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "manual_deposit_pol", args),
+        Error::InsufficientBucketBalance.into()
+    );
+
+    // Rewards above the reserve would come out of principal
+    let mut ledger = s.vault.get_token_ledger().ledger;
+    ledger.blub_reward_reserve = 1_000;
+    s.env.as_contract(&s.vault.address, || {
+        s.env.storage().instance().set(&LedgerKey::TokenLedger, &ledger);
+    });
+    let args = (staker.clone(),).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "claim_rewards", args),
+        Error::InsufficientBucketBalance.into()
+    );
+    assert_eq!(balance(&s.env, &s.blub, &s.vault.address), 105_000);
+}
+
+// ============================================================================
+// Zaps
+// ============================================================================
+
+#[test]
+fn test_zap_deposit_swaps_through_pool() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+
+    let lp = s.vault.zap_deposit(&user, &pool_id, &s.blub, &20_000, &0, &0);
+
+    assert!(lp > 0);
+    assert!(balance(&s.env, &s.blub, &user) >= 80_000);
+    assert!(balance(&s.env, &s.usdc, &user) >= 100_000);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, lp);
+    assert_eq!(balance(&s.env, &s.share, &s.vault.address), lp);
+}
+
+#[test]
+fn test_zap_withdraw_swaps_other_leg_back() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+
+    // Half the position unwinds to 49,750 of each token; the USDC is sold for BLUB
+    let args = (user.clone(), pool_id, 5_000u32, s.blub.clone(), 97_114i128).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "zap_withdraw", args),
+        Error::SlippageExceeded.into()
+    );
+    assert_eq!(s.vault.zap_withdraw(&user, &pool_id, &5_000, &s.blub, &97_113), 97_113);
+    assert_eq!(balance(&s.env, &s.blub, &user), 97_113);
+    assert_eq!(balance(&s.env, &s.usdc, &user), 0);
+}
+
+#[test]
+fn test_zap_withdraw_requires_verified_pool() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+
+    // A pool carried over from before verification existed
+    let mut info = s.vault.get_pool_info(&pool_id);
+    info.verified = false;
+    s.env.as_contract(&s.vault.address, || {
+        s.env.storage().persistent().set(&DataKey::PoolInfo(pool_id), &info);
+    });
+
+    let args = (user.clone(), pool_id, 5_000u32, s.blub.clone(), 0i128).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "zap_withdraw", args),
+        Error::PoolNotVerified.into()
+    );
+
+    // Plain withdrawals still work
+    s.vault.vault_withdraw(&user, &pool_id, &5_000, &0, &0);
+    assert_eq!(balance(&s.env, &s.blub, &user), 49_750);
+}
+
+// ============================================================================
+// Vault flows
+// ============================================================================
+
+#[test]
+fn test_withdraw_lp_unwinds_exact_amount() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+
+    // A compound doubles the LP behind each share
+    mint(&s.env, &s.blub, &s.admin, 100_000);
+    mint(&s.env, &s.usdc, &s.admin, 100_000);
+    s.vault.admin_compound_deposit(&s.admin, &pool_id, &vec![&s.env, 100_000i128, 100_000i128]);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 400_000);
+
+    let pool_shares_before = s.pool.get_total_shares();
+    let burned = s
+        .vault
+        .vault_withdraw_lp_tokens(&user, &pool_id, &15_001, &vec![&s.env, 0u128, 0u128], &user);
+
+    // ceil(15,001 * 200,000 / 400,000) shares, and exactly 15,001 LP burned in the pool
+    assert_eq!(burned, 7_501);
+    assert_eq!(s.user_shares(&user, pool_id), 199_000 - 7_501);
+    assert_eq!(s.pool.get_total_shares(), pool_shares_before - 15_001);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 400_000 - 15_001);
+    assert_eq!(balance(&s.env, &s.share, &s.vault.address), 400_000 - 15_001);
+
+    // Exact-LP withdrawals are told apart from share redemptions
+    let (_, topics, _) = s.env.events().all().last().unwrap();
+    let topic: Symbol = topics.get(0).unwrap().into_val(&s.env);
+    assert_eq!(topic, Symbol::new(&s.env, "vault_wlt"));
+}
+
+#[test]
+fn test_deposit_caps_limit_pool_and_user_lp() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let curator = Address::generate(&s.env);
+    let first = s.funded_user(100_000);
+    let second = s.funded_user(100_000);
+    s.vault.set_curator(&s.admin, &curator);
+
+    let args = (first.clone(), pool_id, 300_000i128, 150_000i128).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_pool_limits", args),
+        Error::Unauthorized.into()
+    );
+    s.vault.set_pool_limits(&curator, &pool_id, &300_000, &150_000);
+
+    s.vault.vault_deposit(&first, &pool_id, &50_000, &50_000, &0, &0);
+    let capacity = s.vault.get_pool_capacity(&pool_id, &first);
+    assert_eq!((capacity.total_lp, capacity.user_lp), (100_000, 99_000));
+    assert_eq!((capacity.remaining_total_lp, capacity.remaining_user_lp, capacity.remaining_lp), (200_000, 51_000, 51_000));
+
+    // 60,000 more LP would put the user at 159,000
+    let args = (first.clone(), pool_id, 30_000i128, 30_000i128, 0u128, 0i128).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "vault_deposit", args),
+        Error::DepositCapExceeded.into()
+    );
+
+    // The pool fills up to exactly its caps
+    s.vault.vault_deposit(&second, &pool_id, &75_000, &75_000, &0, &0);
+    s.vault.vault_deposit(&first, &pool_id, &25_000, &25_000, &0, &0);
+    assert_eq!(s.vault.get_pool_capacity(&pool_id, &second).remaining_lp, 0);
+    let args = (first.clone(), pool_id, 1_000i128, 1_000i128, 0u128, 0i128).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "vault_deposit", args),
+        Error::DepositCapExceeded.into()
+    );
+}
+
+#[test]
+fn test_pool_fees_split_and_tracked() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    let treasury = Address::generate(&s.env);
+    let pol = Address::generate(&s.env);
+    let fees = PoolFeeConfig {
+        performance_fee_bps: 1000,
+        deposit_fee_bps: 100,
+        withdraw_fee_bps: 200,
+        treasury: treasury.clone(),
+        pol_recipient: pol.clone(),
+        pol_share_bps: 5000,
+    };
+
+    let too_high = PoolFeeConfig { withdraw_fee_bps: 1001, ..fees.clone() };
+    let args = (s.admin.clone(), pool_id, too_high).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_pool_fees", args),
+        Error::InvalidInput.into()
+    );
+    s.vault.set_pool_fees(&s.admin, &pool_id, &fees);
+
+    // 1% of the 100,000 LP minted, half of it to each recipient
+    s.vault.vault_deposit(&user, &pool_id, &50_000, &50_000, &0, &0);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 99_000);
+    assert_eq!((balance(&s.env, &s.share, &treasury), balance(&s.env, &s.share, &pol)), (500, 500));
+
+    // 2% of the 98,000 LP released
+    s.vault.vault_withdraw(&user, &pool_id, &10_000, &0, &0);
+    assert_eq!((balance(&s.env, &s.share, &treasury), balance(&s.env, &s.share, &pol)), (1_480, 1_480));
+    assert_eq!(balance(&s.env, &s.blub, &user), 50_000 + 48_020);
+
+    // 10% of claimed AQUA; the rest goes to the manager for compounding
+    s.set_aqua_reward(10_000);
+    assert_eq!(s.vault.claim_and_compound(&s.admin, &pool_id), (10_000, 1_000, 9_000));
+    assert_eq!((balance(&s.env, &s.aqua, &treasury), balance(&s.env, &s.aqua, &pol)), (500, 500));
+    assert_eq!(balance(&s.env, &s.aqua, &s.admin), 9_000);
+
+    let stats = s.vault.get_pool_compound_stats(&pool_id);
+    assert_eq!((stats.total_deposit_fee_lp, stats.total_withdraw_fee_lp), (1_000, 1_960));
+    assert_eq!((stats.total_rewards_claimed, stats.total_treasury_fees, stats.total_pol_fees), (10_000, 500, 500));
+}
+
+#[test]
+fn test_first_deposit_locks_dead_shares() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let first = s.funded_user(100_000);
+    let second = s.funded_user(100_000);
+
+    s.vault.vault_deposit(&first, &pool_id, &100_000, &100_000, &0, &0);
+    assert_eq!(s.user_shares(&first, pool_id), 200_000 - VAULT_DEAD_SHARES);
+    assert_eq!(s.vault.get_vault_total_shares(&pool_id), 200_000);
+
+    // Later deposits mint at the pool's share price, dead shares included
+    s.vault.vault_deposit(&second, &pool_id, &50_000, &50_000, &0, &0);
+    assert_eq!(s.user_shares(&second, pool_id), 100_000);
+    assert_eq!(s.vault.get_vault_total_shares(&pool_id), 300_000);
+
+    let report = s.vault.check_pool_invariants(&pool_id, &1).get(0).unwrap();
+    assert_eq!(report.dead_shares, VAULT_DEAD_SHARES);
+    assert!(report.lp_ok);
+}
+
+#[test]
+fn test_emergency_exit_redeems_idle_pro_rata() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let first = s.funded_user(100_000);
+    let second = s.funded_user(100_000);
+    s.vault.vault_deposit(&first, &pool_id, &100_000, &100_000, &0, &0);
+    s.vault.vault_deposit(&second, &pool_id, &50_000, &50_000, &0, &0);
+
+    // Minimums above what the LP is worth revert the exit
+    let args = (s.admin.clone(), pool_id, vec![&s.env, 150_001u128, 0u128]).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "emergency_exit_pool", args),
+        Error::SlippageExceeded.into()
+    );
+
+    let exit = s.vault.emergency_exit_pool(&s.admin, &pool_id, &vec![&s.env, 150_000u128, 150_000u128]);
+    assert_eq!(exit.lp_unwound, 300_000);
+    assert_eq!(exit.idle, vec![&s.env, 150_000i128, 150_000i128]);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 0);
+    assert_eq!(balance(&s.env, &s.share, &s.vault.address), 0);
+    assert_eq!(s.vault.get_token_ledger().ledger.blub_vault_idle, 150_000);
+
+    // LP-based exits are refused; redemptions pay shares / owned shares of the idle tokens
+    let args = (second.clone(), pool_id, 1_000i128, vec![&s.env, 0u128, 0u128], second.clone()).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "vault_withdraw_lp_tokens", args),
+        Error::PoolInEmergencyExit.into()
+    );
+
+    let paid = s
+        .vault
+        .vault_redeem_tokens(&second, &pool_id, &100_000, &vec![&s.env, 50_000u128, 50_000u128], &second);
+    assert_eq!(paid, vec![&s.env, 50_167u128, 50_167u128]);
+    assert_eq!(balance(&s.env, &s.blub, &second), 100_167);
+
+    let paid = s
+        .vault
+        .vault_redeem_tokens(&first, &pool_id, &199_000, &vec![&s.env, 0u128, 0u128], &first);
+    assert_eq!(paid, vec![&s.env, 99_833u128, 99_833u128]);
+
+    // The last redemption empties the idle; nothing is left for the dead shares
+    let exit = s.vault.get_pool_exit_state(&pool_id).unwrap();
+    assert_eq!(exit.idle, vec![&s.env, 0i128, 0i128]);
+    assert_eq!(s.vault.get_token_ledger().ledger.blub_vault_idle, 0);
+}
+
+#[test]
+fn test_vault_migrate_bounds_vault_shares() {
+    let s = setup("constant_product");
+    let from_pool = s.add_pool();
+    let to_pool = s.add_other_pool(&s.blub, &s.usdc);
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &from_pool, &100_000, &100_000, &0, &0);
+
+    // Half the position is 49,750 of each token: 99,500 LP in the new pool,
+    // of which the dead shares of its first deposit are locked
+    let args = (user.clone(), from_pool, to_pool, 5_000u32, 0u128, 98_501i128).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "vault_migrate", args),
+        Error::SlippageExceeded.into()
+    );
+    assert_eq!(s.vault.vault_migrate(&user, &from_pool, &to_pool, &5_000, &99_500, &98_500), 99_500);
+    assert_eq!(s.user_shares(&user, from_pool), 99_500);
+    assert_eq!(s.user_shares(&user, to_pool), 98_500);
+}
+
+#[test]
+fn test_vault_migrate_swaps_unshared_leg() {
+    let s = setup("constant_product");
+    let from_pool = s.add_pool();
+    let to_pool = s.add_other_pool(&s.blub, &s.aqua);
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &from_pool, &100_000, &100_000, &0, &0);
+
+    // USDC is sold for BLUB in the source pool, then zapped into BLUB/AQUA
+    let lp = s.vault.vault_migrate(&user, &from_pool, &to_pool, &10_000, &0, &0);
+    assert!(lp > 0);
+    assert_eq!(s.vault.get_pool_info(&from_pool).total_lp_tokens, 1_000);
+    assert_eq!(s.vault.get_pool_info(&to_pool).total_lp_tokens, lp);
+    assert_eq!(balance(&s.env, &s.usdc, &user), 0);
+    assert_eq!(balance(&s.env, &s.usdc, &s.vault.address), 0);
+}
+
+// ============================================================================
+// Harvest
+// ============================================================================
+
+/// Enables `harvest` on `pool_id` with AQUA sold through a new AQUA/BLUB pool,
+/// every token priced at 1.0, a 1% bounty and 3% max slippage
+fn enable_harvest<'a>(s: &Setup<'a>, pool_id: u32) -> MockOracleClient<'a> {
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000_000);
+    let (sell_pool, _) = create_pool(&s.env, &s.aqua, &s.blub, "constant_product");
+    let oracle = MockOracleClient::new(&s.env, &s.env.register_contract(None, MockOracle));
+    for token in [&s.aqua, &s.blub, &s.usdc] {
+        oracle.set_price(token, &10_000_000);
+    }
+    let config = HarvestConfig {
+        min_interval: 3_600,
+        bounty_bps: 100,
+        oracle: oracle.address.clone(),
+        max_price_age: 600,
+        max_slippage_bps: 300,
+        sell_pool: Some(sell_pool.address.clone()),
+    };
+    s.vault.set_harvest_config(&s.admin, &pool_id, &config);
+    oracle
+}
+
+#[test]
+fn test_harvest_compounds_and_pays_bounty() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+    enable_harvest(&s, pool_id);
+    let caller = Address::generate(&s.env);
+
+    // 15% performance fee, 1% bounty; the other 8,400 AQUA is compounded
+    s.set_aqua_reward(10_000);
+    let lp = s.vault.harvest(&caller, &pool_id);
+    assert!(lp > 0);
+    assert_eq!(balance(&s.env, &s.aqua, &caller), 100);
+    assert_eq!(balance(&s.env, &s.aqua, &s.vault.address), 0);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 200_000 + lp);
+
+    let stats = s.vault.get_pool_compound_stats(&pool_id);
+    assert_eq!((stats.total_harvest_bounties, stats.compound_count), (100, 1));
+    assert_eq!(stats.total_compounded_lp, lp);
+
+    s.set_aqua_reward(10_000);
+    let args = (caller.clone(), pool_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "harvest", args),
+        Error::HarvestTooSoon.into()
+    );
+}
+
+#[test]
+fn test_harvest_swaps_are_oracle_bounded() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+    let oracle = enable_harvest(&s, pool_id);
+    let caller = Address::generate(&s.env);
+    s.set_aqua_reward(10_000);
+
+    // Prices older than max_price_age are refused
+    s.env.ledger().with_mut(|l| l.timestamp += 601);
+    let args = (caller.clone(), pool_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "harvest", args),
+        Error::OraclePriceUnavailable.into()
+    );
+
+    // At an oracle price of 2.0 per AQUA the sell pool pays far too little
+    oracle.set_price(&s.aqua, &20_000_000);
+    oracle.set_price(&s.blub, &10_000_000);
+    oracle.set_price(&s.usdc, &10_000_000);
+    assert!(s.vault.try_harvest(&caller, &pool_id).is_err());
+
+    oracle.set_price(&s.aqua, &10_000_000);
+    assert!(s.vault.harvest(&caller, &pool_id) > 0);
+}
+
+#[test]
+fn test_harvest_refuses_pools_of_more_than_two_tokens() {
+    let s = setup("stable");
+    let pool = MockPoolClient::new(&s.env, &s.env.register_contract(None, MockPool));
+    let share = create_token(&s.env, &pool.address);
+    let tokens = vec![&s.env, s.blub.clone(), s.usdc.clone(), s.aqua.clone()];
+    pool.init(&tokens, &share, &Symbol::new(&s.env, "stable"));
+    let strategy = PoolStrategy {
+        kind: StrategyKind::AquariusStable,
+        params: Bytes::new(&s.env),
+    };
+    let pool_id = s.vault.add_strategy_pool(&s.admin, &pool.address, &tokens, &share, &strategy);
+
+    let config = HarvestConfig {
+        min_interval: 3_600,
+        bounty_bps: 100,
+        oracle: Address::generate(&s.env),
+        max_price_age: 600,
+        max_slippage_bps: 300,
+        sell_pool: None,
+    };
+    let args = (s.admin.clone(), pool_id, config.clone()).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_harvest_config", args),
+        Error::InvalidInput.into()
+    );
+
+    // A config stored before the check existed is not used either
+    s.env.as_contract(&s.vault.address, || {
+        s.env.storage().persistent().set(&PoolKey::HarvestConfig(pool_id), &config);
+    });
+    let args = (s.admin.clone(), pool_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "harvest", args),
+        Error::HarvestNotConfigured.into()
+    );
+}
+
+// ============================================================================
+// Pool history
+// ============================================================================
+
+#[test]
+fn test_pool_history_and_trailing_apr() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+
+    s.env.ledger().with_mut(|l| l.timestamp = 10 * 86_400);
+    s.vault.snapshot_pool(&pool_id);
+    let first = s.vault.snapshot_pool(&pool_id);
+    assert_eq!((first.day, first.total_lp, first.total_shares), (10, 200_000, 200_000));
+    assert_eq!(first.price_per_share, PRICE_PER_SHARE_PRECISION);
+
+    // Ten days later a compound adds 10% LP per share and records its own snapshot
+    s.env.ledger().with_mut(|l| l.timestamp = 20 * 86_400);
+    mint(&s.env, &s.blub, &s.admin, 10_000);
+    mint(&s.env, &s.usdc, &s.admin, 10_000);
+    s.vault.admin_compound_deposit(&s.admin, &pool_id, &vec![&s.env, 10_000i128, 10_000i128]);
+
+    let history = s.vault.get_pool_history(&pool_id, &0, &30);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history.get(1).unwrap().price_per_share, PRICE_PER_SHARE_PRECISION * 11 / 10);
+
+    // 10% over 10 days, annualized linearly
+    let apr = s.vault.get_pool_trailing_apr(&pool_id, &10).unwrap();
+    assert_eq!((apr.from_day, apr.to_day, apr.apr_bps), (10, 20, 36_500));
+
+    // The window's first week holds no snapshot, so there is no start point
+    assert_eq!(s.vault.get_pool_trailing_apr(&pool_id, &30), None);
+    assert_eq!(s.vault.get_pool_trailing_apr(&pool_id, &0), None);
+}
+
+// ============================================================================
+// Vault incentive stream
+// ============================================================================
+
+#[test]
+fn test_vault_rewards_stream_pro_rata_over_time() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let first = s.funded_user(100_000);
+    let second = s.funded_user(100_000);
+    s.vault.vault_deposit(&first, &pool_id, &100_000, &100_000, &0, &0);
+
+    // 1,000,000 BLUB over 1,000 seconds streams 1,000 BLUB a second
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000);
+    mint(&s.env, &s.blub, &s.admin, 1_000_000);
+    let stream = s.vault.fund_vault_rewards(&s.admin, &pool_id, &1_000_000, &1_000);
+    assert_eq!(stream.reward_rate, 1_000 * REWARD_PRECISION);
+    assert_eq!(stream.period_finish, 2_000);
+    assert_eq!(s.vault.get_token_ledger().ledger.blub_vault_rewards, 1_000_000);
+
+    // A deposit made after funding only earns from then on
+    s.env.ledger().with_mut(|l| l.timestamp = 1_100);
+    s.vault.vault_deposit(&second, &pool_id, &50_000, &50_000, &0, &0);
+    s.env.ledger().with_mut(|l| l.timestamp = 1_200);
+
+    // 100s alone, then 100s at 199,000 : 100,000 owned shares (rounded down)
+    assert_eq!(s.vault.claim_vault_rewards(&first, &pool_id), 166_555);
+    assert_eq!(s.vault.claim_vault_rewards(&second, &pool_id), 33_444);
+    assert_eq!(balance(&s.env, &s.blub, &second), 50_000 + 33_444);
+    assert_eq!(s.vault.get_token_ledger().ledger.blub_vault_rewards, 1_000_000 - 166_555 - 33_444);
+
+    let args = (second.clone(), pool_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "claim_vault_rewards", args),
+        Error::NoRewardsToClaim.into()
+    );
+}
+
+#[test]
+fn test_vault_rewards_streamed_without_shares_roll_into_next_funding() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let first = s.funded_user(100_000);
+    s.vault.vault_deposit(&first, &pool_id, &100_000, &100_000, &0, &0);
+
+    s.env.ledger().with_mut(|l| l.timestamp = 1_000);
+    mint(&s.env, &s.blub, &s.admin, 1_100_000);
+    s.vault.fund_vault_rewards(&s.admin, &pool_id, &1_000_000, &1_000);
+
+    // The only depositor leaves after 200s; only the dead shares remain
+    s.env.ledger().with_mut(|l| l.timestamp = 1_200);
+    s.vault.vault_withdraw(&first, &pool_id, &10_000, &0, &0);
+    assert_eq!(s.vault.claim_vault_rewards(&first, &pool_id), 199_999);
+
+    // 300s stream with nobody earning; claims in between keep it set aside
+    s.env.ledger().with_mut(|l| l.timestamp = 1_350);
+    assert_eq!(s.vault.get_vault_reward_unstreamed(&pool_id), 150_000);
+    let args = (first.clone(), pool_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "claim_vault_rewards", args),
+        Error::NoRewardsToClaim.into()
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = 1_500);
+    assert_eq!(s.vault.get_vault_reward_unstreamed(&pool_id), 300_000);
+
+    // The next funding streams the new amount, the running period's rest
+    // and the 300,000 streamed to nobody
+    let second = s.funded_user(100_000);
+    s.vault.vault_deposit(&second, &pool_id, &100_000, &100_000, &0, &0);
+    let stream = s.vault.fund_vault_rewards(&s.admin, &pool_id, &100_000, &100);
+    assert_eq!(stream.reward_rate, 9_000 * REWARD_PRECISION);
+    assert_eq!(s.vault.get_vault_reward_unstreamed(&pool_id), 0);
+
+    // Everything funded is paid out but the first claim's rounding dust
+    s.env.ledger().with_mut(|l| l.timestamp = 1_600);
+    let paid = s.vault.claim_vault_rewards(&second, &pool_id);
+    assert_eq!(paid, 900_000);
+    let ledger = s.vault.get_token_ledger().ledger;
+    assert_eq!(ledger.blub_vault_rewards, 1);
+}
+
+// ============================================================================
+// Compound residual
+// ============================================================================
+
+#[test]
+fn test_compound_residual_is_tracked_and_reused() {
+    let s = setup("constant_product");
+    let pool_id = s.add_pool();
+    let user = s.funded_user(100_000);
+    s.vault.vault_deposit(&user, &pool_id, &100_000, &100_000, &0, &0);
+    mint(&s.env, &s.blub, &s.admin, 10_000);
+    mint(&s.env, &s.usdc, &s.admin, 10_000);
+
+    // At 1:1 reserves the pool takes 4,000 of each; the extra BLUB stays idle
+    let lp = s
+        .vault
+        .admin_compound_deposit(&s.admin, &pool_id, &vec![&s.env, 10_000i128, 4_000i128]);
+    assert_eq!(lp, 8_000);
+    assert_eq!(s.vault.get_pool_idle_residual(&pool_id), vec![&s.env, 6_000i128, 0i128]);
+    assert_eq!(balance(&s.env, &s.blub, &s.vault.address), 6_000);
+    assert_eq!(s.vault.get_token_ledger().ledger.blub_vault_idle, 6_000);
+    let report = s.vault.check_pool_invariants(&pool_id, &1).get(0).unwrap();
+    assert_eq!(report.idle_residual, vec![&s.env, 6_000i128, 0i128]);
+
+    // The next compound deposits the residual with the new amounts
+    let lp = s
+        .vault
+        .admin_compound_deposit(&s.admin, &pool_id, &vec![&s.env, 0i128, 6_000i128]);
+    assert_eq!(lp, 12_000);
+    assert_eq!(s.vault.get_pool_idle_residual(&pool_id), vec![&s.env, 0i128, 0i128]);
+    assert_eq!(balance(&s.env, &s.blub, &s.vault.address), 0);
+    assert_eq!(s.vault.get_token_ledger().ledger.blub_vault_idle, 0);
+    assert_eq!(s.vault.get_pool_info(&pool_id).total_lp_tokens, 220_000);
+}
+
+// ============================================================================
+// ICE locks
+// ============================================================================
+
+fn ice_amounts(ice: i128, upvote_ice: i128) -> IceAmounts {
+    IceAmounts {
+        ice,
+        govern_ice: 0,
+        upvote_ice,
+        downvote_ice: 0,
+    }
+}
+
+#[test]
+fn test_ice_lock_authorize_cancel_and_stale_cancel() {
+    let s = setup("constant_product");
+    s.add_pending_ice_aqua(1_000);
+
+    let args = (s.admin.clone(), 1_001i128, 1u64).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "authorize_ice_lock", args),
+        Error::InsufficientPendingAqua.into()
+    );
+
+    let first = s.vault.authorize_ice_lock(&s.admin, &400, &1);
+    let second = s.vault.authorize_ice_lock(&s.admin, &600, &2);
+    assert_eq!(s.vault.get_pending_aqua_for_ice(), 0);
+    assert_eq!(s.vault.get_authorized_ice_aqua(), 1_000);
+
+    // The manager cancels at once and gets the reserve back
+    assert_eq!(s.vault.cancel_ice_lock(&s.admin, &first), 400);
+    assert_eq!(s.vault.get_pending_aqua_for_ice(), 400);
+    assert_eq!(s.vault.get_ice_lock_authorization(&first).state, IceLockState::Expired);
+
+    // Anyone else only once the authorization is stale
+    let stranger = Address::generate(&s.env);
+    let args = (stranger.clone(), second).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "cancel_ice_lock", args),
+        Error::Unauthorized.into()
+    );
+    s.env.ledger().with_mut(|l| l.timestamp += ICE_AUTH_STALE_SECONDS);
+    assert_eq!(s.vault.cancel_ice_lock(&stranger, &second), 600);
+    assert_eq!(s.vault.get_pending_aqua_for_ice(), 1_000);
+    assert_eq!(s.vault.get_authorized_ice_aqua(), 0);
+
+    // Expired locks can be neither cancelled again nor transferred
+    let args = (s.admin.clone(), second).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "cancel_ice_lock", args),
+        Error::InvalidIceLockState.into()
+    );
+    let args = (s.admin.clone(), second).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "transfer_authorized_aqua", args),
+        Error::InvalidIceLockState.into()
+    );
+}
+
+#[test]
+fn test_ice_lock_transfer_confirm_and_unlock() {
+    let s = setup("constant_product");
+    s.env.ledger().with_mut(|l| l.timestamp = 10 * 86_400);
+    let lock_id = s.transferred_ice_lock(1_000, 1);
+
+    let lock = s.vault.get_ice_lock_authorization(&lock_id);
+    assert_eq!(lock.state, IceLockState::Transferred);
+    assert_eq!(lock.unlocks_at, 10 * 86_400 + ICE_LOCK_YEAR_SECONDS);
+    assert_eq!(balance(&s.env, &s.aqua, &s.admin), 1_000);
+    assert_eq!(s.vault.get_authorized_ice_aqua(), 0);
+    assert_eq!(s.vault.get_ice_maturity_count(), 1);
+
+    // Nothing can be attributed before the ICE has been synced in
+    let args = (s.admin.clone(), lock_id, ice_amounts(500, 500)).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "confirm_ice_lock", args),
+        Error::InsufficientBalance.into()
+    );
+
+    mint(&s.env, &s.ice.ice_token, &s.vault.address, 500);
+    mint(&s.env, &s.ice.upvote_ice_token, &s.vault.address, 500);
+    s.vault.sync_all_ice_balances(&s.admin);
+    assert_eq!(s.vault.get_unattributed_ice(), ice_amounts(500, 500));
+
+    let args = (s.admin.clone(), lock_id, ice_amounts(501, 500)).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "confirm_ice_lock", args),
+        Error::InsufficientBalance.into()
+    );
+    s.vault.confirm_ice_lock(&s.admin, &lock_id, &ice_amounts(500, 500));
+    assert_eq!(s.vault.get_unattributed_ice(), ice_amounts(0, 0));
+    let confirmed = s.vault.get_ice_locks_by_state(&IceLockState::Confirmed, &0, &10);
+    assert_eq!(confirmed.len(), 1);
+    assert_eq!(confirmed.get(0).unwrap().ice_received, ice_amounts(500, 500));
+
+    // The unlock is recorded once the term is over, closing its maturity
+    let args = (s.admin.clone(), lock_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "record_ice_unlock", args),
+        Error::UnlockNotReady.into()
+    );
+    s.env.ledger().with_mut(|l| l.timestamp = lock.unlocks_at);
+    s.vault.record_ice_unlock(&s.admin, &lock_id);
+    assert_eq!(s.vault.get_ice_lock_authorization(&lock_id).state, IceLockState::Unlocked);
+    assert_eq!(s.vault.get_ice_maturity_count(), 0);
+}
+
+#[test]
+fn test_ice_maturities_are_bucketed_by_unlock_day() {
+    let s = setup("constant_product");
+    s.env.ledger().with_mut(|l| l.timestamp = 10 * 86_400);
+    let first = s.transferred_ice_lock(1_000, 1);
+    let longer = s.transferred_ice_lock(500, 2);
+    s.env.ledger().with_mut(|l| l.timestamp += 3_600);
+    let later = s.transferred_ice_lock(300, 1);
+
+    let day = (10 * 86_400 + ICE_LOCK_YEAR_SECONDS) / 86_400;
+    assert_eq!(s.vault.get_ice_maturity_count(), 3);
+    assert_eq!(s.vault.get_earliest_ice_maturity_day(), Some(day));
+    let maturities = s.vault.get_ice_maturities(&day, &1);
+    assert_eq!(maturities.len(), 2);
+    assert_eq!(maturities.get(0).unwrap().lock_id, first);
+    assert_eq!(maturities.get(1).unwrap().lock_id, later);
+
+    // A year from the first transfer only the first lock is due
+    s.env.ledger().with_mut(|l| l.timestamp = 10 * 86_400);
+    let (upcoming, total_aqua, next_day) = s.vault.get_upcoming_ice_unlocks(&day, &ICE_LOCK_YEAR_SECONDS);
+    assert_eq!(upcoming.len(), 1);
+    assert_eq!(total_aqua, 1_000);
+    assert_eq!(next_day, day + 1);
+
+    // Registering locks already in the ledger adds nothing
+    assert_eq!(s.vault.register_ice_maturities(&s.admin, &vec![&s.env, first, longer, later]), 3);
+}
+
+#[test]
+fn test_renew_ice_lock_splits_matured_aqua_by_ladder() {
+    let s = setup("constant_product");
+    let lock_id = s.transferred_ice_lock(1_001, 1);
+    mint(&s.env, &s.ice.ice_token, &s.vault.address, 100);
+    s.vault.sync_all_ice_balances(&s.admin);
+    s.vault.confirm_ice_lock(&s.admin, &lock_id, &ice_amounts(100, 0));
+
+    let args = (s.admin.clone(), lock_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "renew_ice_lock", args),
+        Error::IceRenewalNotConfigured.into()
+    );
+    let uneven = IceRenewalPolicy {
+        ladder_bps: vec![&s.env, 2_000, 0, 3_000, 4_000, 0],
+    };
+    let args = (s.admin.clone(), uneven).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_ice_renewal_policy", args),
+        Error::InvalidInput.into()
+    );
+    s.vault.set_ice_renewal_policy(
+        &s.admin,
+        &IceRenewalPolicy {
+            ladder_bps: vec![&s.env, 2_000, 0, 3_000, 5_000, 0],
+        },
+    );
+
+    let args = (s.admin.clone(), lock_id).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "renew_ice_lock", args),
+        Error::UnlockNotReady.into()
+    );
+
+    // The manager pays the released AQUA back; the longest rung takes the remainder
+    s.env.ledger().with_mut(|l| l.timestamp = ICE_LOCK_YEAR_SECONDS);
+    let new_locks = s.vault.renew_ice_lock(&s.admin, &lock_id);
+    assert_eq!(new_locks.len(), 3);
+    for (id, rung) in new_locks.iter().zip([(200, 1), (300, 3), (501, 4)]) {
+        let lock = s.vault.get_ice_lock_authorization(&id);
+        assert_eq!(lock.state, IceLockState::Authorized);
+        assert_eq!((lock.aqua_amount, lock.duration_years), rung);
+    }
+
+    assert_eq!(balance(&s.env, &s.aqua, &s.admin), 0);
+    assert_eq!(s.vault.get_authorized_ice_aqua(), 1_001);
+    assert_eq!(s.vault.get_ice_lock_authorization(&lock_id).state, IceLockState::Unlocked);
+    assert_eq!(s.vault.get_ice_maturity_count(), 0);
+}
+
+// ============================================================================
+// ICE votes
+// ============================================================================
+
+fn vote_target(pool: &Address, weight: IceVoteWeight) -> IceVoteTarget {
+    IceVoteTarget {
+        pool: pool.clone(),
+        weight,
+    }
+}
+
+#[test]
+fn test_vote_allocations_fit_synced_upvote_ice() {
+    let s = setup("constant_product");
+    let pol_pool = Address::generate(&s.env);
+    let vault_pool = s.pool.address.clone();
+
+    // Nothing synced yet: a fixed amount cannot be backed
+    let targets = vec![&s.env, vote_target(&pol_pool, IceVoteWeight::Amount(300))];
+    let args = (s.admin.clone(), targets).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_vote_allocations", args),
+        Error::IceVotesOverallocated.into()
+    );
+
+    mint(&s.env, &s.ice.upvote_ice_token, &s.vault.address, 1_000);
+    s.vault.sync_all_ice_balances(&s.admin);
+
+    let duplicated = vec![
+        &s.env,
+        vote_target(&pol_pool, IceVoteWeight::Bps(1_000)),
+        vote_target(&pol_pool, IceVoteWeight::Bps(1_000)),
+    ];
+    let args = (s.admin.clone(), duplicated).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_vote_allocations", args),
+        Error::InvalidInput.into()
+    );
+    let over = vec![
+        &s.env,
+        vote_target(&pol_pool, IceVoteWeight::Bps(6_000)),
+        vote_target(&vault_pool, IceVoteWeight::Bps(5_000)),
+    ];
+    let args = (s.admin.clone(), over).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_vote_allocations", args),
+        Error::IceVotesOverallocated.into()
+    );
+
+    let report = s.vault.set_vote_allocations(
+        &s.admin,
+        &vec![
+            &s.env,
+            vote_target(&pol_pool, IceVoteWeight::Bps(5_000)),
+            vote_target(&vault_pool, IceVoteWeight::Amount(300)),
+        ],
+    );
+    assert_eq!(report.total_allocated, 800);
+    assert_eq!(report.allocations.get(0).unwrap().ice_amount, 500);
+    assert!(report.within_balance);
+    assert_eq!(s.vault.get_pool_vote_allocation(&vault_pool).unwrap().ice_amount, 300);
+
+    // Once the operator is set, only the operator edits the registry
+    let operator = Address::generate(&s.env);
+    s.vault.set_vote_operator(&s.admin, &operator);
+    let args = (s.admin.clone(), Vec::<IceVoteTarget>::new(&s.env)).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "set_vote_allocations", args),
+        Error::Unauthorized.into()
+    );
+
+    // The registry is kept when upvoteICE drops, but reported over the balance
+    token::Client::new(&s.env, &s.ice.upvote_ice_token).transfer(&s.vault.address, &operator, &600);
+    s.vault.sync_all_ice_balances(&s.admin);
+    let report = s.vault.get_vote_allocations();
+    assert_eq!((report.total_allocated, report.upvote_ice_balance), (500, 400));
+    assert!(!report.within_balance);
+}
+
+#[test]
+fn test_staker_votes_tally_and_finalize_into_registry() {
+    let s = setup("constant_product");
+    let first = s.funded_user(30_000);
+    let second = s.funded_user(10_000);
+    s.vault.stake(&first, &30_000, &1);
+    s.vault.stake(&second, &10_000, &1);
+
+    s.env.ledger().with_mut(|l| l.timestamp = ICE_VOTE_EPOCH_SECONDS);
+    let (epoch, ends_at) = s.vault.get_current_vote_epoch();
+    let listed = Address::generate(&s.env);
+    let other = Address::generate(&s.env);
+    s.vault.set_vote_pools(&s.admin, &vec![&s.env, listed.clone(), other.clone()]);
+
+    let unlisted = vec![&s.env, PoolVote { pool: s.pool.address.clone(), bps: 10_000 }];
+    let args = (first.clone(), unlisted).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "cast_ice_vote", args),
+        Error::InvalidInput.into()
+    );
+    let args = (s.admin.clone(), vec![&s.env, PoolVote { pool: listed.clone(), bps: 10_000 }]).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "cast_ice_vote", args),
+        Error::InsufficientBalance.into()
+    );
+
+    s.vault.cast_ice_vote(&first, &vec![&s.env, PoolVote { pool: listed.clone(), bps: 10_000 }]);
+    s.vault.cast_ice_vote(
+        &second,
+        &vec![
+            &s.env,
+            PoolVote { pool: listed.clone(), bps: 5_000 },
+            PoolVote { pool: other.clone(), bps: 5_000 },
+        ],
+    );
+    let tally = s.vault.get_ice_vote_tally(&epoch);
+    assert_eq!(tally.get(0).unwrap(), PoolVoteTally { pool: listed.clone(), weight: 35_000 });
+    assert_eq!(tally.get(1).unwrap(), PoolVoteTally { pool: other.clone(), weight: 5_000 });
+
+    // Voting again replaces the ballot
+    s.vault.cast_ice_vote(&first, &vec![&s.env, PoolVote { pool: other.clone(), bps: 10_000 }]);
+    let tally = s.vault.get_ice_vote_tally(&epoch);
+    assert_eq!((tally.get(0).unwrap().weight, tally.get(1).unwrap().weight), (5_000, 35_000));
+
+    let args = (epoch,).into_val(&s.env);
+    assert_eq!(
+        call_error(&s.env, &s.vault.address, "finalize_ice_vote_epoch", args),
+        Error::VoteEpochNotEnded.into()
+    );
+
+    // Once ended, the tally becomes the registry in bps of upvoteICE
+    s.env.ledger().with_mut(|l| l.timestamp = ends_at);
+    let targets = s.vault.finalize_ice_vote_epoch(&epoch);
+    assert_eq!(
+        targets,
+        vec![
+            &s.env,
+            vote_target(&listed, IceVoteWeight::Bps(1_250)),
+            vote_target(&other, IceVoteWeight::Bps(8_750)),
+        ]
+    );
+    assert_eq!(s.vault.get_last_finalized_vote_epoch(), Some(epoch));
+    for finalized in [epoch, epoch - 1] {
+        let args = (finalized,).into_val(&s.env);
+        assert_eq!(
+            call_error(&s.env, &s.vault.address, "finalize_ice_vote_epoch", args),
+            Error::AlreadyExecuted.into()
+        );
+    }
+}
+
+#[test]
+fn test_unstake_lowers_ice_ballot() {
+    let s = setup("constant_product");
+    let staker = s.funded_user(30_000);
+    s.vault.stake(&staker, &30_000, &1);
+    let pool = Address::generate(&s.env);
+    s.vault.set_vote_pools(&s.admin, &vec![&s.env, pool.clone()]);
+
+    // Vote once the unstake cooldown has passed
+    s.env.ledger().with_mut(|l| l.timestamp = DEFAULT_UNSTAKE_COOLDOWN_SECONDS);
+    let epoch = s.vault.cast_ice_vote(&staker, &vec![&s.env, PoolVote { pool: pool.clone(), bps: 5_000 }]);
+    assert_eq!(s.vault.get_ice_vote_tally(&epoch).get(0).unwrap().weight, 15_000);
+
+    // The unstaked BLUB leaves the ballot and the tally
+    s.vault.unstake(&staker, &20_000);
+    assert_eq!(s.vault.get_user_ice_vote(&staker, &epoch).unwrap().weight, 10_000);
+    assert_eq!(s.vault.get_ice_vote_tally(&epoch).get(0).unwrap().weight, 5_000);
+
+    // Unstaking the rest drops the ballot
+    s.vault.unstake(&staker, &10_000);
+    assert_eq!(s.vault.get_user_ice_vote(&staker, &epoch), None);
+    assert_eq!(s.vault.get_ice_vote_tally(&epoch).get(0).unwrap().weight, 0);
+}
@@ -377,10 +377,11 @@ export class SorobanVaultService {
     desiredA: string;
     desiredB: string;
     minShares: string;
+    minVaultShares?: string; // minimum vault shares to mint; defaults to "0" (any non-zero amount)
     walletName: string;
   }): Promise<{ success: boolean; error?: string; transactionHash?: string }> {
     try {
-      const { userAddress, poolId, desiredA, desiredB, minShares, walletName } = params;
+      const { userAddress, poolId, desiredA, desiredB, minShares, minVaultShares = "0", walletName } = params;
       console.log("[VaultDeposit] Starting deposit...", { userAddress, poolId, desiredA, desiredB, walletName });
 
       // Setup wallet - use existing WalletConnect kit for WalletConnect, create new kit for others
@@ -411,6 +412,7 @@ export class SorobanVaultService {
       const desiredAScVal = nativeToScVal(BigInt(Math.round(parseFloat(desiredA) * 1e7)), { type: "i128" });
       const desiredBScVal = nativeToScVal(BigInt(Math.round(parseFloat(desiredB) * 1e7)), { type: "i128" });
       const minSharesScVal = nativeToScVal(BigInt(Math.round(parseFloat(minShares) * 1e7)), { type: "u128" });
+      const minVaultSharesScVal = nativeToScVal(BigInt(Math.round(parseFloat(minVaultShares) * 1e7)), { type: "i128" });
 
       let tx = new TransactionBuilder(account, {
         fee: "1000000", // 1 XLM — required for Soroban mainnet inclusion; assembleTransaction adds resource fee on top
@@ -423,7 +425,8 @@ export class SorobanVaultService {
             poolIdScVal,
             desiredAScVal,
             desiredBScVal,
-            minSharesScVal
+            minSharesScVal,
+            minVaultSharesScVal
           )
         )
         .setTimeout(300) // 5 min TTL — prevents tx expiry during signing / submission lag
@@ -490,10 +493,11 @@ export class SorobanVaultService {
     tokenIn: string;      // contract address of the token being deposited
     amountIn: string;      // human-readable amount (e.g. "100.5")
     minShares: string;
+    minVaultShares?: string; // minimum vault shares to mint; defaults to "0" (any non-zero amount)
     walletName: string;
   }): Promise<{ success: boolean; error?: string; transactionHash?: string }> {
     try {
-      const { userAddress, poolId, tokenIn, amountIn, minShares, walletName } = params;
+      const { userAddress, poolId, tokenIn, amountIn, minShares, minVaultShares = "0", walletName } = params;
       console.log("[VaultDepositSingle] Starting deposit...", { userAddress, poolId, tokenIn, amountIn, walletName });
 
       // Setup wallet
@@ -522,6 +526,7 @@ export class SorobanVaultService {
       const tokenInScVal = nativeToScVal(tokenIn, { type: "address" });
       const amountInScVal = nativeToScVal(BigInt(Math.round(parseFloat(amountIn) * 1e7)), { type: "i128" });
       const minSharesScVal = nativeToScVal(BigInt(Math.round(parseFloat(minShares) * 1e7)), { type: "u128" });
+      const minVaultSharesScVal = nativeToScVal(BigInt(Math.round(parseFloat(minVaultShares) * 1e7)), { type: "i128" });
 
       let tx = new TransactionBuilder(account, {
         fee: "1000000",
@@ -534,7 +539,8 @@ export class SorobanVaultService {
             poolIdScVal,
             tokenInScVal,
             amountInScVal,
-            minSharesScVal
+            minSharesScVal,
+            minVaultSharesScVal
           )
         )
         .setTimeout(300)